@@ -1,18 +1,22 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Order, Reply, Response, StdResult, SubMsg, Uint128, WasmMsg
+    entry_point, from_json, instantiate2_address, to_json_binary, Addr, BankMsg, Binary, Coin, Decimal, Deps, DepsMut, Env, Event, HexBinary, MessageInfo, Order, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg
 };
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, MinterResponse};
+use cw_ownable::Ownership;
+use sha2::{Digest, Sha256};
 use cw2::set_contract_version;
-use cw20::Cw20ExecuteMsg;
+use cw_utils::{one_coin, PaymentError};
 use cw_storage_plus::Bound;
-use xion_capypolls_poll::state::PollConfig;
-use xion_capypolls_poll::msg::InstantiateMsg as PollInstantiateMsg;
+use xion_capypolls_poll::contract::{MAX_STAKE_AMOUNT, MIN_STAKE_AMOUNT};
+use xion_capypolls_poll::state::{Collateral, StakeLimits, MAX_OUTCOMES, MAX_TOKEN_SUPPLY, MIN_OUTCOMES};
+use xion_capypolls_poll::msg::{ExecuteMsg as PollExecuteMsg, InstantiateMsg as PollInstantiateMsg, QueryMsg as PollQueryMsg, CollateralInfo, EpochScheduleInfo, ExitPenaltyInfo, OptimisticInfo, OracleInfo, OutcomeInfo, PollEvent, SaleInfo, ScalarInfo};
 use crate::{
     error::ContractError,
     msg::{
-        ConfigResponse, ExecuteMsg, InstantiateMsg, PollCountResponse, PollDetailsResponse, PollResponse,
-        QueryMsg,
+        CollateralDenomsResponse, CollectedFeesResponse, ConfigResponse, DenomFee, DenomStatsEntry, DenomStatsResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, OutcomeTokenInfo, PollCountResponse, PollDetailsResponse, PollEntry, PollResponse, PollSortBy, PollStatus, PollsResponse, PredictedAddressesResponse, MintAuthorityResponse, TokenAuthority,
+        QueryMsg, ResolversResponse, UserPollEntry, UserPollsResponse,
     },
-    state::{Config, MarketStats, PollInfo, TempPollData, CONFIG, MARKET_STATS, POLLS, POLL_COUNT, POLL_SEQUENCE, TEMP_POLL_DATA, UNIQUE_PARTICIPANTS},
+    state::{decode_reply_id, encode_reply_id, legacy, Config, MarketStats, COLLECTED_FEES, DENOM_STATS, PollInfo, TempPollData, status_key, COLLATERAL_DENOMS, CONFIG, MARKET_STATS, CREATOR_NONCES, NEXT_PENDING_ID, PENDING_POLLS, POLL_COUNT, POLL_SEQUENCE, REPLY_POLL_INIT, SALT_POLL, UNIQUE_PARTICIPANTS, USER_POLLS, polls},
 };
 
 const CONTRACT_NAME: &str = "crates.io:xion-capypolls-core";
@@ -21,11 +25,10 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const MIN_DURATION: u64 = 60; // 1 minute
 const MAX_DURATION: u64 = 2592000; // 30 days
 const MAX_PROTOCOL_FEE: u64 = 1000; // 10%
+const BPS_DENOMINATOR: u64 = 10_000;
+/// Polls brought up to date per migration when `MigrateMsg` sets no limit
+const DEFAULT_MIGRATE_LIMIT: u32 = 100;
 
-// Reply IDs
-const REPLY_YES_TOKEN_INIT: u64 = 1;
-const REPLY_NO_TOKEN_INIT: u64 = 2;
-const REPLY_POLL_INIT: u64 = 3;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -42,6 +45,7 @@ pub fn instantiate(
 
     let config = Config {
         owner: info.sender.clone(),
+        resolvers: vec![],
         initial_fee: msg.initial_fee,
         protocol_fee: msg.protocol_fee,
         poll_code_id: msg.poll_code_id,
@@ -58,6 +62,8 @@ pub fn instantiate(
         active_polls_count: 0,
         total_polls_created: 0,
         total_unique_participants: 0,
+        resolved_polls_count: 0,
+        total_volume: Uint128::zero(),
     };
     MARKET_STATS.save(deps.storage, &market_stats)?;
 
@@ -66,6 +72,7 @@ pub fn instantiate(
         .add_attribute("owner", info.sender))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute_create_poll(
     deps: DepsMut,
     env: Env,
@@ -74,113 +81,238 @@ pub fn execute_create_poll(
     avatar: String,
     description: String,
     duration: u64,
-    yes_token_name: String,
-    yes_token_symbol: String,
-    no_token_name: String,
-    no_token_symbol: String,
+    outcomes: Vec<OutcomeTokenInfo>,
+    oracle: Option<OracleInfo>,
+    optimistic: Option<OptimisticInfo>,
+    scalar: Option<ScalarInfo>,
+    epoch_schedule: Option<EpochScheduleInfo>,
+    exit_penalty: Option<ExitPenaltyInfo>,
+    limits: Option<StakeLimits>,
+    sale: Option<SaleInfo>,
+    denom: Option<String>,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    
-    // Validate XION fee payment
-    let payment = info.funds
-        .iter()
-        .find(|coin| coin.denom == "uxion")
-        .ok_or(ContractError::NoXionPayment {})?;
 
-    if payment.amount != config.initial_fee {
+    // Validate fee payment, any whitelisted denom at its own rate. Anything sent along with it
+    // would never be credited, so the fee has to come alone.
+    let payment = one_coin(&info).map_err(|err| match err {
+        PaymentError::NoFunds {} => ContractError::NoXionPayment {},
+        _ => ContractError::InvalidFeeAmount {},
+    })?;
+    let fee = creation_fee(deps.as_ref(), &config, &payment.denom)?.ok_or(ContractError::NoXionPayment {})?;
+    if payment.amount != fee {
         return Err(ContractError::InvalidFeeAmount {});
     }
+    let fee_denom = payment.denom;
+    add_collected_fee(deps.storage, &fee_denom, fee)?;
+
+    let denom = denom.unwrap_or_else(|| config.denom.clone());
+    if creation_fee(deps.as_ref(), &config, &denom)?.is_none() {
+        return Err(ContractError::DenomNotWhitelisted { denom });
+    }
+
+    // The protocol's stake bounds are in XION, so they only back polls in the config denom
+    let limits = match limits {
+        None if denom == config.denom => Some(StakeLimits {
+            min_stake: Some(Uint128::new(MIN_STAKE_AMOUNT)),
+            max_stake: Some(Uint128::new(MAX_STAKE_AMOUNT)),
+            ..StakeLimits::default()
+        }),
+        limits => limits,
+    };
 
     // Validate duration
-    if duration < MIN_DURATION || duration > MAX_DURATION {
+    if !(MIN_DURATION..=MAX_DURATION).contains(&duration) {
         return Err(ContractError::InvalidDuration {
             min: MIN_DURATION,
             max: MAX_DURATION,
         });
     }
 
+    if !(MIN_OUTCOMES..=MAX_OUTCOMES).contains(&outcomes.len()) {
+        return Err(ContractError::InvalidOutcomeCount {
+            min: MIN_OUTCOMES,
+            max: MAX_OUTCOMES,
+        });
+    }
+
+    // Every creation gets its own pending id so batched or nested creations never share state
+    let pending_id = NEXT_PENDING_ID.may_load(deps.storage)?.unwrap_or_default();
+    NEXT_PENDING_ID.save(deps.storage, &(pending_id + 1))?;
+
+    // Addresses are fixed up front, so every contract can be instantiated in one go
+    let nonce = CREATOR_NONCES.may_load(deps.storage, &info.sender)?.unwrap_or_default();
+    CREATOR_NONCES.save(deps.storage, &info.sender, &(nonce + 1))?;
+    let predicted = predict_poll_addresses(deps.as_ref(), &env, &config, &info.sender, nonce, outcomes.len())?;
+
     // Save temporary data for reply handler
     let temp_data = TempPollData {
         creator: info.sender.clone(),
-        question: question.clone(),
+        question,
         avatar,
         description,
-        yes_token: None,
-        no_token: None,
-        poll_addr: None,
+        outcomes,
+        outcome_tokens: predicted.outcome_tokens.clone(),
+        poll_addr: Some(predicted.poll.clone()),
         duration,
+        denom,
+        oracle,
+        optimistic,
+        scalar,
+        epoch_schedule,
+        exit_penalty,
+        limits,
+        sale,
     };
-    TEMP_POLL_DATA.save(deps.storage, &temp_data)?;
-
-    // Create YES token
-    let yes_token_init = cw20_base::msg::InstantiateMsg {
-        name: yes_token_name,
-        symbol: yes_token_symbol,
-        decimals: 18,
-        initial_balances: vec![],
-        mint: Some(cw20::MinterResponse {
-            minter: info.sender.to_string(),
-            cap: None,
-        }),
-        marketing: None,
-    };
+    PENDING_POLLS.save(deps.storage, pending_id, &temp_data)?;
 
-    let yes_token_instantiate = SubMsg::reply_on_success(
-        WasmMsg::Instantiate {
-            admin: Some(info.sender.to_string()),
-            code_id: config.token_code_id,
-            msg: to_json_binary(&yes_token_init)?,
-            funds: vec![],
-            label: format!("YES Token for Poll {}", question),
+    let mut messages = temp_data
+        .outcomes
+        .iter()
+        .enumerate()
+        .map(|(index, outcome)| {
+            let token_init = cw20_base::msg::InstantiateMsg {
+                name: outcome.token_name.clone(),
+                symbol: outcome.token_symbol.clone(),
+                decimals: 18,
+                initial_balances: vec![],
+                // The factory mints until the poll exists, then hands over in `REPLY_POLL_INIT`
+                mint: Some(cw20::MinterResponse {
+                    minter: env.contract.address.to_string(),
+                    cap: Some(Uint128::new(MAX_TOKEN_SUPPLY)),
+                }),
+                marketing: None,
+            };
+            Ok(SubMsg::new(WasmMsg::Instantiate2 {
+                admin: Some(env.contract.address.to_string()),
+                code_id: config.token_code_id,
+                label: format!("{} Token for Poll {}", outcome.name, temp_data.question),
+                msg: to_json_binary(&token_init)?,
+                funds: vec![],
+                salt: creation_salt(deps.as_ref(), &temp_data.creator, nonce, index as u8)?,
+            }))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let poll_init = PollInstantiateMsg {
+        capy_core: env.contract.address.to_string(),
+        poll_creator: temp_data.creator.to_string(),
+        outcomes: temp_data
+            .outcomes
+            .iter()
+            .zip(&temp_data.outcome_tokens)
+            .map(|(outcome, token)| OutcomeInfo {
+                name: outcome.name.clone(),
+                token: token.to_string(),
+            })
+            .collect(),
+        duration: temp_data.duration,
+        collateral: CollateralInfo::Native {
+            denom: temp_data.denom.clone(),
         },
-        REPLY_YES_TOKEN_INIT,
-    );
-
-    // Create NO token
-    let no_token_init = cw20_base::msg::InstantiateMsg {
-        name: no_token_name,
-        symbol: no_token_symbol,
-        decimals: 18,
-        initial_balances: vec![],
-        mint: Some(cw20::MinterResponse {
-            minter: info.sender.to_string(),
-            cap: None,
-        }),
-        marketing: None,
+        oracle: temp_data.oracle.clone(),
+        optimistic: temp_data.optimistic.clone(),
+        scalar: temp_data.scalar.clone(),
+        epoch_schedule: temp_data.epoch_schedule.clone(),
+        exit_penalty: temp_data.exit_penalty.clone(),
+        limits: temp_data.limits.clone(),
+        protocol_fee: Some(Decimal::from_ratio(config.protocol_fee, BPS_DENOMINATOR)),
+        sale: temp_data.sale.clone(),
     };
-
-    let no_token_instantiate = SubMsg::reply_on_success(
-        WasmMsg::Instantiate {
-            admin: Some(info.sender.to_string()),
-            code_id: config.token_code_id,
-            msg: to_json_binary(&no_token_init)?,
+    // Core stays the poll's admin too, a creator able to migrate it could mint without limit
+    messages.push(SubMsg::reply_on_success(
+        WasmMsg::Instantiate2 {
+            admin: Some(env.contract.address.to_string()),
+            code_id: config.poll_code_id,
+            label: format!("Poll for {}", temp_data.question),
+            msg: to_json_binary(&poll_init)?,
             funds: vec![],
-            label: format!("NO Token for Poll {}", question),
+            salt: creation_salt(deps.as_ref(), &temp_data.creator, nonce, SALT_POLL)?,
         },
-        REPLY_NO_TOKEN_INIT,
-    );
+        encode_reply_id(pending_id, REPLY_POLL_INIT),
+    ));
 
     // Update market stats
     MARKET_STATS.update(deps.storage, |mut stats| -> StdResult<_> {
         stats.active_polls_count += 1;
         stats.total_polls_created += 1;
-        stats.total_unique_participants += 1;
         Ok(stats)
     })?;
 
-    if !UNIQUE_PARTICIPANTS.may_load(deps.storage, &info.sender)?.unwrap_or(false) {
-        UNIQUE_PARTICIPANTS.save(deps.storage, &info.sender, &true)?;
-        MARKET_STATS.update(deps.storage, |mut stats| -> StdResult<_> {
-            stats.total_unique_participants += 1;
-            Ok(stats)
-        })?;
-    }
-
     Ok(Response::new()
-        .add_submessage(yes_token_instantiate)
-        .add_submessage(no_token_instantiate)
+        .add_submessages(messages)
         .add_attribute("action", "create_poll")
-        .add_attribute("creator", info.sender))
+        .add_attribute("creator", info.sender)
+        .add_attribute("pending_id", pending_id.to_string())
+        .add_attribute("nonce", nonce.to_string())
+        .add_attribute("denom", temp_data.denom)
+        .add_attribute("fee_denom", fee_denom)
+        .add_attribute("poll_addr", predicted.poll))
+}
+
+/// Creation fee in `denom`, or `None` when the denom is not whitelisted
+fn creation_fee(deps: Deps, config: &Config, denom: &str) -> StdResult<Option<Uint128>> {
+    if denom == config.denom {
+        return Ok(Some(config.initial_fee));
+    }
+    COLLATERAL_DENOMS.may_load(deps.storage, denom)
+}
+
+struct PredictedAddresses {
+    outcome_tokens: Vec<Addr>,
+    poll: Addr,
+}
+
+/// Salt for one contract of a creation: `sha256(creator || nonce || slot)`, where the slot is
+/// the outcome index for tokens and `SALT_POLL` for the poll itself
+fn creation_salt(deps: Deps, creator: &Addr, nonce: u64, slot: u8) -> StdResult<Binary> {
+    let creator = deps.api.addr_canonicalize(creator.as_str())?;
+    let mut hasher = Sha256::new();
+    hasher.update(creator.as_slice());
+    hasher.update(nonce.to_be_bytes());
+    hasher.update([slot]);
+    Ok(Binary::from(hasher.finalize().to_vec()))
+}
+
+fn predict_address(
+    deps: Deps,
+    env: &Env,
+    code_id: u64,
+    salt: &Binary,
+) -> StdResult<Addr> {
+    let checksum = deps.querier.query_wasm_code_info(code_id)?.checksum;
+    let factory = deps.api.addr_canonicalize(env.contract.address.as_str())?;
+    let canonical = instantiate2_address(checksum.as_slice(), &factory, salt)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    // MockApi cannot humanize 32 byte addresses, tests fall back to the hex form
+    let addr = if cfg!(test) {
+        deps.api
+            .addr_humanize(&canonical)
+            .unwrap_or_else(|_| Addr::unchecked(HexBinary::from(canonical.as_slice()).to_hex()))
+    } else {
+        deps.api.addr_humanize(&canonical)?
+    };
+    Ok(addr)
+}
+
+fn predict_poll_addresses(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    creator: &Addr,
+    nonce: u64,
+    num_outcomes: usize,
+) -> StdResult<PredictedAddresses> {
+    let outcome_tokens = (0..num_outcomes as u8)
+        .map(|index| {
+            let salt = creation_salt(deps, creator, nonce, index)?;
+            predict_address(deps, env, config.token_code_id, &salt)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let salt = creation_salt(deps, creator, nonce, SALT_POLL)?;
+    let poll = predict_address(deps, env, config.poll_code_id, &salt)?;
+
+    Ok(PredictedAddresses { outcome_tokens, poll })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -196,10 +328,15 @@ pub fn execute(
             avatar,
             description,
             duration,
-            yes_token_name,
-            yes_token_symbol,
-            no_token_name,
-            no_token_symbol,
+            outcomes,
+            oracle,
+            optimistic,
+            scalar,
+            epoch_schedule,
+            exit_penalty,
+            limits,
+            sale,
+            denom,
         } => execute_create_poll(
             deps,
             env,
@@ -208,10 +345,15 @@ pub fn execute(
             avatar,
             description,
             duration,
-            yes_token_name,
-            yes_token_symbol,
-            no_token_name,
-            no_token_symbol,
+            outcomes,
+            oracle,
+            optimistic,
+            scalar,
+            epoch_schedule,
+            exit_penalty,
+            limits,
+            sale,
+            denom,
         ),
         ExecuteMsg::UpdatePollCodeId { code_id } => {
             execute_update_poll_code_id(deps, info, code_id)
@@ -221,7 +363,29 @@ pub fn execute(
         }
         ExecuteMsg::SetInitialFee { new_fee } => execute_set_initial_fee(deps, info, new_fee),
         ExecuteMsg::SetProtocolFee { new_fee } => execute_set_protocol_fee(deps, info, new_fee),
-        ExecuteMsg::WithdrawFees { to } => execute_withdraw_fees(deps, env, info, to),
+        ExecuteMsg::WithdrawFees { to } => execute_withdraw_fees(deps, info, to),
+        ExecuteMsg::UpdateResolvers { add, remove } => {
+            execute_update_resolvers(deps, info, add, remove)
+        }
+        ExecuteMsg::UpdateCollateralDenoms { add, remove } => {
+            execute_update_collateral_denoms(deps, info, add, remove)
+        }
+        ExecuteMsg::ResolvePoll {
+            poll_address,
+            winning_position,
+        } => execute_resolve_poll(deps, info, poll_address, winning_position),
+        ExecuteMsg::ResolveScalarPoll { poll_address, value } => {
+            execute_resolve_scalar_poll(deps, info, poll_address, value)
+        }
+        ExecuteMsg::RecordParticipation { user } => execute_record_participation(deps, info, user),
+        ExecuteMsg::ReportPollEvent { event } => execute_report_poll_event(deps, info, event),
+        ExecuteMsg::InvalidatePoll { poll_address } => {
+            execute_invalidate_poll(deps, info, poll_address)
+        }
+        ExecuteMsg::SettleDispute {
+            poll_address,
+            winning_position,
+        } => execute_settle_dispute(deps, info, poll_address, winning_position),
     }
 }
 
@@ -309,9 +473,10 @@ pub fn execute_set_protocol_fee(
         .add_attribute("new_fee", new_fee.to_string()))
 }
 
+/// Sends the fees collected in every denom. Anything else the contract holds, like forfeited
+/// bonds, stays put.
 pub fn execute_withdraw_fees(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
     to: String,
 ) -> Result<Response, ContractError> {
@@ -321,26 +486,340 @@ pub fn execute_withdraw_fees(
     }
 
     let to_addr = deps.api.addr_validate(&to)?;
-    
-    // Query contract's XION balance
-    let balance = deps.querier.query_balance(env.contract.address, "uxion")?;
-    let amount = balance.amount;
-
-    // Send XION tokens to recipient
-    let bank_msg = cosmwasm_std::BankMsg::Send {
-        to_address: to_addr.to_string(),
-        amount: vec![balance],
-    };
+    let fees = collected_fees(deps.storage)?;
+    if fees.is_empty() {
+        return Err(ContractError::NoFeesToWithdraw {});
+    }
+    for fee in &fees {
+        COLLECTED_FEES.remove(deps.storage, &fee.denom);
+    }
+    let amount = fees.iter().map(Coin::to_string).collect::<Vec<_>>().join(",");
 
     Ok(Response::new()
-        .add_message(bank_msg)
+        .add_message(BankMsg::Send {
+            to_address: to_addr.to_string(),
+            amount: fees,
+        })
         .add_attribute("action", "withdraw_fees")
         .add_attribute("to", to)
         .add_attribute("amount", amount))
 }
 
+fn add_collected_fee(storage: &mut dyn Storage, denom: &str, amount: Uint128) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    COLLECTED_FEES.update(storage, denom, |fees| -> StdResult<_> {
+        Ok(fees.unwrap_or_default() + amount)
+    })?;
+    Ok(())
+}
+
+/// Non-zero fees held per denom, in denom order
+fn collected_fees(storage: &dyn Storage) -> StdResult<Vec<Coin>> {
+    COLLECTED_FEES
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, amount) = item?;
+            Ok(Coin { denom, amount })
+        })
+        .collect()
+}
+
+pub fn execute_update_resolvers(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<String>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for addr in &add {
+        let addr = deps.api.addr_validate(addr)?;
+        if !config.resolvers.contains(&addr) {
+            config.resolvers.push(addr);
+        }
+    }
+    for addr in &remove {
+        let addr = deps.api.addr_validate(addr)?;
+        config.resolvers.retain(|r| r != addr);
+    }
+    CONFIG.save(deps.storage, &config)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_resolvers")
+        .add_attribute("added", add.join(","))
+        .add_attribute("removed", remove.join(",")))
+}
+
+pub fn execute_update_collateral_denoms(
+    deps: DepsMut,
+    info: MessageInfo,
+    add: Vec<DenomFee>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    for denom in add.iter().map(|entry| &entry.denom).chain(&remove) {
+        if *denom == config.denom {
+            return Err(ContractError::DefaultDenom {});
+        }
+        if denom.is_empty() {
+            return Err(ContractError::DenomNotWhitelisted { denom: denom.clone() });
+        }
+    }
+    for entry in &add {
+        COLLATERAL_DENOMS.save(deps.storage, &entry.denom, &entry.creation_fee)?;
+    }
+    for denom in &remove {
+        COLLATERAL_DENOMS.remove(deps.storage, denom);
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_collateral_denoms")
+        .add_attribute(
+            "added",
+            add.iter().map(|entry| entry.denom.as_str()).collect::<Vec<_>>().join(","),
+        )
+        .add_attribute("removed", remove.join(",")))
+}
+
+pub fn execute_resolve_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_address: String,
+    winning_position: u8,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    assert_resolver(&config, &info.sender)?;
+
+    let poll_addr = deps.api.addr_validate(&poll_address)?;
+    let poll_info = polls()
+        .may_load(deps.storage, &poll_addr)?
+        .ok_or_else(|| ContractError::PollNotFound(poll_address.clone()))?;
+
+    let resolve_msg = WasmMsg::Execute {
+        contract_addr: poll_addr.to_string(),
+        msg: to_json_binary(&PollExecuteMsg::ResolvePoll { winning_position })?,
+        funds: vec![],
+    };
+
+    let event = Event::new("poll_resolved")
+        .add_attribute("poll_addr", poll_addr.to_string())
+        .add_attribute("creator", poll_info.creator)
+        .add_attribute("question", poll_info.question)
+        .add_attribute("winning_position", winning_position.to_string())
+        .add_attribute("resolver", info.sender.to_string());
+
+    Ok(Response::new()
+        .add_message(resolve_msg)
+        .add_event(event)
+        .add_attribute("action", "resolve_poll")
+        .add_attribute("poll_addr", poll_addr))
+}
+
+pub fn execute_resolve_scalar_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_address: String,
+    value: Uint128,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    assert_resolver(&config, &info.sender)?;
+
+    let poll_addr = deps.api.addr_validate(&poll_address)?;
+    let poll_info = polls()
+        .may_load(deps.storage, &poll_addr)?
+        .ok_or_else(|| ContractError::PollNotFound(poll_address.clone()))?;
+
+    let resolve_msg = WasmMsg::Execute {
+        contract_addr: poll_addr.to_string(),
+        msg: to_json_binary(&PollExecuteMsg::ResolveScalar { value })?,
+        funds: vec![],
+    };
+
+    let event = Event::new("poll_resolved")
+        .add_attribute("poll_addr", poll_addr.to_string())
+        .add_attribute("creator", poll_info.creator)
+        .add_attribute("question", poll_info.question)
+        .add_attribute("value", value)
+        .add_attribute("resolver", info.sender.to_string());
+
+    Ok(Response::new()
+        .add_message(resolve_msg)
+        .add_event(event)
+        .add_attribute("action", "resolve_scalar_poll")
+        .add_attribute("poll_addr", poll_addr))
+}
+
+pub fn execute_invalidate_poll(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_address: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    assert_resolver(&config, &info.sender)?;
+
+    let poll_addr = deps.api.addr_validate(&poll_address)?;
+    let poll_info = polls()
+        .may_load(deps.storage, &poll_addr)?
+        .ok_or_else(|| ContractError::PollNotFound(poll_address.clone()))?;
+
+    let invalidate_msg = WasmMsg::Execute {
+        contract_addr: poll_addr.to_string(),
+        msg: to_json_binary(&PollExecuteMsg::InvalidatePoll {})?,
+        funds: vec![],
+    };
+
+    let event = Event::new("poll_invalidated")
+        .add_attribute("poll_addr", poll_addr.to_string())
+        .add_attribute("creator", poll_info.creator)
+        .add_attribute("question", poll_info.question)
+        .add_attribute("resolver", info.sender.to_string());
+
+    Ok(Response::new()
+        .add_message(invalidate_msg)
+        .add_event(event)
+        .add_attribute("action", "invalidate_poll")
+        .add_attribute("poll_addr", poll_addr))
+}
+
+/// Core is the default arbiter of optimistic polls, which leaves their disputes to the owner
+pub fn execute_settle_dispute(
+    deps: DepsMut,
+    info: MessageInfo,
+    poll_address: String,
+    winning_position: u8,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let poll_addr = deps.api.addr_validate(&poll_address)?;
+    let poll_info = polls()
+        .may_load(deps.storage, &poll_addr)?
+        .ok_or_else(|| ContractError::PollNotFound(poll_address.clone()))?;
+
+    let settle_msg = WasmMsg::Execute {
+        contract_addr: poll_addr.to_string(),
+        msg: to_json_binary(&PollExecuteMsg::SettleDispute { winning_position })?,
+        funds: vec![],
+    };
+
+    let event = Event::new("poll_dispute_settled")
+        .add_attribute("poll_addr", poll_addr.to_string())
+        .add_attribute("creator", poll_info.creator)
+        .add_attribute("question", poll_info.question)
+        .add_attribute("winning_position", winning_position.to_string())
+        .add_attribute("arbiter", info.sender.to_string());
+
+    Ok(Response::new()
+        .add_message(settle_msg)
+        .add_event(event)
+        .add_attribute("action", "settle_dispute")
+        .add_attribute("poll_addr", poll_addr))
+}
+
+/// Indexes `user` under the calling poll, counting them once across the whole factory
+pub fn execute_record_participation(
+    deps: DepsMut,
+    info: MessageInfo,
+    user: String,
+) -> Result<Response, ContractError> {
+    if !polls().has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    let user = deps.api.addr_validate(&user)?;
+
+    USER_POLLS.save(deps.storage, (&user, &info.sender), &true)?;
+    if !UNIQUE_PARTICIPANTS.has(deps.storage, &user) {
+        UNIQUE_PARTICIPANTS.save(deps.storage, &user, &true)?;
+        MARKET_STATS.update(deps.storage, |mut stats| -> StdResult<_> {
+            stats.total_unique_participants += 1;
+            Ok(stats)
+        })?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "record_participation")
+        .add_attribute("poll_addr", info.sender)
+        .add_attribute("user", user))
+}
+
+/// Applies a collateral or lifecycle change reported by the calling poll to the market stats
+pub fn execute_report_poll_event(
+    deps: DepsMut,
+    info: MessageInfo,
+    event: PollEvent,
+) -> Result<Response, ContractError> {
+    let mut poll = polls()
+        .may_load(deps.storage, &info.sender)?
+        .ok_or(ContractError::Unauthorized {})?;
+    let config = CONFIG.load(deps.storage)?;
+    let mut stats = MARKET_STATS.load(deps.storage)?;
+    let mut denom_stats = DENOM_STATS.may_load(deps.storage, &poll.denom)?.unwrap_or_default();
+
+    match &event {
+        PollEvent::Staked { amount } | PollEvent::Bought { amount } => {
+            poll.total_value_locked += amount;
+            denom_stats.total_value_locked += amount;
+            denom_stats.total_volume += amount;
+        }
+        PollEvent::Bonded { amount } => {
+            poll.total_value_locked += amount;
+            denom_stats.total_value_locked += amount;
+        }
+        // Polls staked in before reports existed pay out collateral that was never counted
+        PollEvent::Withdrawn { amount } => {
+            poll.total_value_locked = poll.total_value_locked.saturating_sub(*amount);
+            denom_stats.total_value_locked = denom_stats.total_value_locked.saturating_sub(*amount);
+        }
+        // Only credited as the fee itself comes along, stakes are reported net of it
+        PollEvent::FeeCollected { amount } => {
+            if info.funds != [Coin::new(amount.u128(), &poll.denom)] {
+                return Err(ContractError::InvalidFeeAmount {});
+            }
+            add_collected_fee(deps.storage, &poll.denom, *amount)?;
+        }
+        PollEvent::Resolved { invalid } => {
+            poll.status = if *invalid { PollStatus::Cancelled } else { PollStatus::Resolved };
+            stats.active_polls_count = stats.active_polls_count.saturating_sub(1);
+            stats.resolved_polls_count += 1;
+        }
+    }
+
+    polls().save(deps.storage, &info.sender, &poll)?;
+    DENOM_STATS.save(deps.storage, &poll.denom, &denom_stats)?;
+    if poll.denom == config.denom {
+        stats.total_value_locked = denom_stats.total_value_locked;
+        stats.total_volume = denom_stats.total_volume;
+    }
+    MARKET_STATS.save(deps.storage, &stats)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "report_poll_event")
+        .add_attribute("poll_addr", info.sender)
+        .add_attribute("denom", poll.denom)
+        .add_attribute("total_value_locked", denom_stats.total_value_locked))
+}
+
+/// The owner is always allowed to resolve, on top of the configured resolver set
+fn assert_resolver(config: &Config, sender: &Addr) -> Result<(), ContractError> {
+    if *sender != config.owner && !config.resolvers.contains(sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
         QueryMsg::GetPollCount {} => to_json_binary(&query_poll_count(deps)?),
@@ -352,9 +831,36 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_json_binary(&query_poll_details(deps, poll_address)?)
         }
         QueryMsg::GetMarketStats {} => to_json_binary(&query_market_stats(deps)?),
+        QueryMsg::GetDenomStats {} => to_json_binary(&query_denom_stats(deps)?),
+        QueryMsg::GetCollectedFees {} => to_json_binary(&CollectedFeesResponse {
+            fees: collected_fees(deps.storage)?,
+        }),
+        QueryMsg::GetResolvers {} => to_json_binary(&query_resolvers(deps)?),
+        QueryMsg::GetCollateralDenoms {} => to_json_binary(&query_collateral_denoms(deps)?),
+        QueryMsg::GetMintAuthority { poll_address } => {
+            to_json_binary(&query_mint_authority(deps, poll_address)?)
+        }
+        QueryMsg::PredictPollAddresses {
+            creator,
+            nonce,
+            num_outcomes,
+        } => to_json_binary(&query_predict_poll_addresses(deps, env, creator, nonce, num_outcomes)?),
         QueryMsg::ListActivePolls { start_after, limit } => {
-            to_json_binary(&query_active_polls(deps, start_after, limit)?)
+            to_json_binary(&query_active_polls(deps, env, start_after, limit)?)
         }
+        QueryMsg::GetUserPolls {
+            user,
+            status_filter,
+            start_after,
+            limit,
+        } => to_json_binary(&query_user_polls(deps, env, user, status_filter, start_after, limit)?),
+        QueryMsg::ListPolls {
+            status,
+            creator,
+            sort_by,
+            start_after,
+            limit,
+        } => to_json_binary(&query_list_polls(deps, env, status, creator, sort_by, start_after, limit)?),
     }
 }
 
@@ -389,12 +895,12 @@ fn query_poll_at(deps: Deps, index: u64) -> StdResult<PollResponse> {
 
 fn query_is_poll_from_factory(deps: Deps, poll_address: String) -> StdResult<bool> {
     let addr = deps.api.addr_validate(&poll_address)?;
-    Ok(POLLS.has(deps.storage, &addr))
+    Ok(polls().has(deps.storage, &addr))
 }
 
 fn query_poll_details(deps: Deps, poll_address: String) -> StdResult<PollDetailsResponse> {
     let addr = deps.api.addr_validate(&poll_address)?;
-    let poll_info = POLLS.may_load(deps.storage, &addr)?;
+    let poll_info = polls().may_load(deps.storage, &addr)?;
 
     Ok(PollDetailsResponse {
         exists: poll_info.is_some(),
@@ -406,28 +912,173 @@ pub fn query_market_stats(deps: Deps) -> StdResult<MarketStats> {
     MARKET_STATS.load(deps.storage)
 }
 
-fn query_active_polls(
+fn query_denom_stats(deps: Deps) -> StdResult<DenomStatsResponse> {
+    let denoms = DENOM_STATS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, stats) = item?;
+            Ok(DenomStatsEntry {
+                denom,
+                total_value_locked: stats.total_value_locked,
+                total_volume: stats.total_volume,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(DenomStatsResponse { denoms })
+}
+
+fn query_resolvers(deps: Deps) -> StdResult<ResolversResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ResolversResponse {
+        resolvers: config.resolvers.into_iter().map(|r| r.to_string()).collect(),
+    })
+}
+
+fn query_collateral_denoms(deps: Deps) -> StdResult<CollateralDenomsResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let default = DenomFee {
+        denom: config.denom,
+        creation_fee: config.initial_fee,
+    };
+    let denoms = std::iter::once(Ok(default))
+        .chain(
+            COLLATERAL_DENOMS
+                .range(deps.storage, None, None, Order::Ascending)
+                .map(|item| {
+                    let (denom, creation_fee) = item?;
+                    Ok(DenomFee { denom, creation_fee })
+                }),
+        )
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(CollateralDenomsResponse { denoms })
+}
+
+fn query_mint_authority(deps: Deps, poll_address: String) -> StdResult<MintAuthorityResponse> {
+    let poll_addr = deps.api.addr_validate(&poll_address)?;
+    let poll_info = polls()
+        .may_load(deps.storage, &poll_addr)?
+        .ok_or_else(|| StdError::not_found(format!("poll {}", poll_address)))?;
+
+    let tokens = poll_info
+        .outcome_tokens
+        .iter()
+        .map(|token| -> StdResult<_> {
+            let minter: Option<MinterResponse> =
+                deps.querier.query_wasm_smart(token, &Cw20QueryMsg::Minter {})?;
+            // The token keeps its cw-ownable owner under the "ownership" key
+            let owner = deps
+                .querier
+                .query_wasm_raw(token, b"ownership".as_slice())?
+                .map(from_json::<Ownership<Addr>>)
+                .transpose()?
+                .and_then(|ownership| ownership.owner);
+            Ok(TokenAuthority {
+                token: token.to_string(),
+                minter: minter.map(|minter| minter.minter),
+                owner: owner.map(String::from),
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let handed_over = tokens.iter().all(|token| {
+        token.minter.as_deref() == Some(poll_addr.as_str())
+            && token.owner.as_deref() == Some(poll_addr.as_str())
+    });
+
+    Ok(MintAuthorityResponse {
+        poll: poll_addr.to_string(),
+        tokens,
+        handed_over,
+    })
+}
+
+fn query_predict_poll_addresses(
+    deps: Deps,
+    env: Env,
+    creator: String,
+    nonce: Option<u64>,
+    num_outcomes: Option<u8>,
+) -> StdResult<PredictedAddressesResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let creator = deps.api.addr_validate(&creator)?;
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => CREATOR_NONCES.may_load(deps.storage, &creator)?.unwrap_or_default(),
+    };
+    let num_outcomes = num_outcomes.map(usize::from).unwrap_or(MIN_OUTCOMES);
+    if !(MIN_OUTCOMES..=MAX_OUTCOMES).contains(&num_outcomes) {
+        return Err(StdError::generic_err(
+            ContractError::InvalidOutcomeCount {
+                min: MIN_OUTCOMES,
+                max: MAX_OUTCOMES,
+            }
+            .to_string(),
+        ));
+    }
+
+    let predicted = predict_poll_addresses(deps, &env, &config, &creator, nonce, num_outcomes)?;
+    Ok(PredictedAddressesResponse {
+        nonce,
+        outcome_tokens: predicted.outcome_tokens.into_iter().map(String::from).collect(),
+        poll: predicted.poll.to_string(),
+    })
+}
+
+fn query_user_polls(
     deps: Deps,
+    env: Env,
+    user: String,
+    status_filter: Option<PollStatus>,
     start_after: Option<String>,
     limit: Option<u32>,
-) -> StdResult<Vec<PollResponse>> {
+) -> StdResult<UserPollsResponse> {
+    let user = deps.api.addr_validate(&user)?;
     let limit = limit.unwrap_or(10) as usize;
-    // let addr = start_after.map(|s| deps.api.addr_validate(&s)).transpose()?;
-    // let start = addr.map(|a| Bound::exclusive(&a));
+    let start = start_after.map(|s| deps.api.addr_validate(&s)).transpose()?;
+    let start = start.as_ref().map(Bound::exclusive);
 
-    let addr = start_after.map(|s| deps.api.addr_validate(&s)).transpose()?;
-    let start = addr.as_ref().map(|a| Bound::exclusive(a));
+    let mut user_polls = vec![];
+    for poll in USER_POLLS.prefix(&user).keys(deps.storage, start, None, Order::Ascending) {
+        let poll = poll?;
+        let status = polls().load(deps.storage, &poll)?.status_at(env.block.time.seconds());
+        if status_filter.as_ref().is_some_and(|filter| *filter != status) {
+            continue;
+        }
+        user_polls.push(UserPollEntry {
+            address: poll.to_string(),
+            status,
+        });
+        if user_polls.len() == limit {
+            break;
+        }
+    }
+    Ok(UserPollsResponse { polls: user_polls })
+}
 
+fn query_active_polls(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PollResponse>> {
+    let limit = limit.unwrap_or(10) as usize;
+    let now = env.block.time.seconds();
 
-    POLLS
-        .range(deps.storage, start, None, Order::Ascending)
-        .filter(|r| match r {
-            Ok((addr, _)) => {
-                let poll_config: PollConfig = deps.querier.query_wasm_smart(addr, &QueryMsg::GetConfig {}).unwrap();
-                !poll_config.is_resolved
-            },
-            Err(_) => false,
-        })
+    // Stored Active polls past their end time are Ended, so the range starts right after now
+    let start = match start_after {
+        Some(addr) => {
+            let addr = deps.api.addr_validate(&addr)?;
+            let poll = polls().load(deps.storage, &addr)?;
+            Bound::exclusive((poll.end_time, addr))
+        }
+        None => Bound::inclusive((now + 1, Addr::unchecked(""))),
+    };
+    polls()
+        .idx
+        .status_end_time
+        .sub_prefix(status_key(&PollStatus::Active))
+        .range(deps.storage, Some(start), None, Order::Ascending)
         .take(limit)
         .map(|item| {
             let (addr, _) = item?;
@@ -438,104 +1089,260 @@ fn query_active_polls(
         .collect()
 }
 
+/// Walks the index of `sort_by` and keeps the polls matching both filters
+fn query_list_polls(
+    deps: Deps,
+    env: Env,
+    status: Option<PollStatus>,
+    creator: Option<String>,
+    sort_by: Option<PollSortBy>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PollsResponse> {
+    let limit = limit.unwrap_or(10) as usize;
+    let creator = creator.map(|c| deps.api.addr_validate(&c)).transpose()?;
+    let sort_by = sort_by.unwrap_or(PollSortBy::Created);
+    let now = env.block.time.seconds();
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
-    match msg.id {
-        REPLY_YES_TOKEN_INIT => {
-            let result = msg.result.into_result().map_err(|_| ContractError::InstantiateFailed {})?;
-            let contract_address = result.events
-                .iter()
-                .flat_map(|event| event.attributes.iter())
-                .find(|attr| attr.key == "_contract_address")
-                .map(|attr| attr.value.clone())
-                .ok_or(ContractError::InstantiateFailed {})?;
-
-            let mut temp_data = TEMP_POLL_DATA.load(deps.storage)?;
-            temp_data.yes_token = Some(deps.api.addr_validate(&contract_address)?);
-            TEMP_POLL_DATA.save(deps.storage, &temp_data)?;
-            Ok(Response::new())
+    // Pages resume right after the sort key of the last poll returned
+    let after = match start_after {
+        Some(addr) => {
+            let addr = deps.api.addr_validate(&addr)?;
+            let poll = polls().load(deps.storage, &addr)?;
+            Some((poll, addr))
         }
-        REPLY_NO_TOKEN_INIT => {
-            let result = msg.result.into_result().map_err(|_| ContractError::InstantiateFailed {})?;
-            let contract_address = result.events
-                .iter()
-                .flat_map(|event| event.attributes.iter())
-                .find(|attr| attr.key == "_contract_address")
-                .map(|attr| attr.value.clone())
-                .ok_or(ContractError::InstantiateFailed {})?;
-
-            let mut temp_data = TEMP_POLL_DATA.load(deps.storage)?;
-            temp_data.no_token = Some(deps.api.addr_validate(&contract_address)?);
-            TEMP_POLL_DATA.save(deps.storage, &temp_data)?;
-            
-            // Now create the poll contract
-            let poll_init = PollInstantiateMsg {
-                capy_core: env.contract.address.to_string(),
-                poll_creator: temp_data.creator.to_string(),
-                yes_token: temp_data.yes_token.unwrap().to_string(),
-                no_token: temp_data.no_token.unwrap().to_string(),
-                duration: temp_data.duration,
-                denom: "uxion".to_string(),
-            };
+        None => None,
+    };
+    // A status filter walks the same order under the stored status. Active and Ended are both
+    // stored as Active and only told apart by the end time.
+    let key = status.as_ref().map(status_key);
+    let index = polls();
+    let iter: Box<dyn Iterator<Item = StdResult<(Addr, PollInfo)>>> = match sort_by {
+        PollSortBy::EndTime => {
+            let mut min = after.map(|(poll, addr)| Bound::exclusive((poll.end_time, addr)));
+            let mut max = None;
+            match status {
+                Some(PollStatus::Active) if min.is_none() => {
+                    min = Some(Bound::inclusive((now + 1, Addr::unchecked(""))));
+                }
+                Some(PollStatus::Ended) => max = Some(Bound::exclusive((now + 1, Addr::unchecked("")))),
+                _ => {}
+            }
+            match key {
+                Some(key) => index.idx.status_end_time.sub_prefix(key).range(deps.storage, min, max, Order::Ascending),
+                None => index.idx.end_time.range(deps.storage, min, max, Order::Ascending),
+            }
+        }
+        PollSortBy::Created => {
+            let max = after.map(|(poll, addr)| Bound::exclusive((poll.index, addr)));
+            match key {
+                Some(key) => index.idx.status_created.sub_prefix(key).range(deps.storage, None, max, Order::Descending),
+                None => index.idx.created.range(deps.storage, None, max, Order::Descending),
+            }
+        }
+        PollSortBy::Tvl => {
+            let max = after.map(|(poll, addr)| Bound::exclusive((poll.total_value_locked.u128(), addr)));
+            match key {
+                Some(key) => index.idx.status_tvl.sub_prefix(key).range(deps.storage, None, max, Order::Descending),
+                None => index.idx.tvl.range(deps.storage, None, max, Order::Descending),
+            }
+        }
+    };
 
-            let config = CONFIG.load(deps.storage)?;
-            let poll_instantiate = SubMsg::reply_on_success(
-                WasmMsg::Instantiate {
-                    admin: Some(temp_data.creator.to_string()),
-                    code_id: config.poll_code_id,
-                    msg: to_json_binary(&poll_init)?,
-                    funds: vec![],
-                    label: format!("Poll for {}", temp_data.question),
-                },
-                REPLY_POLL_INIT,
-            );
-            Ok(Response::new().add_submessage(poll_instantiate))
+    let mut entries = vec![];
+    for item in iter {
+        let (addr, poll) = item?;
+        let poll_status = poll.status_at(now);
+        if status.as_ref().is_some_and(|status| *status != poll_status)
+            || creator.as_ref().is_some_and(|creator| *creator != poll.creator)
+        {
+            continue;
         }
+        entries.push(PollEntry {
+            address: addr.to_string(),
+            creator: poll.creator.to_string(),
+            question: poll.question,
+            denom: poll.denom,
+            status: poll_status,
+            end_time: poll.end_time,
+            total_value_locked: poll.total_value_locked,
+        });
+        if entries.len() == limit {
+            break;
+        }
+    }
+    Ok(PollsResponse { polls: entries })
+}
+
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    let (pending_id, kind) = decode_reply_id(msg.id);
+    match kind {
         REPLY_POLL_INIT => {
-            let result = msg.result.into_result().map_err(|_| ContractError::InstantiateFailed {})?;
-            let contract_address = result.events
-                .iter()
-                .flat_map(|event| event.attributes.iter())
-                .find(|attr| attr.key == "_contract_address")
-                .map(|attr| attr.value.clone())
-                .ok_or(ContractError::InstantiateFailed {})?;
+            msg.result.into_result().map_err(|_| ContractError::InstantiateFailed {})?;
 
-            let temp_data = TEMP_POLL_DATA.load(deps.storage)?;
-            let poll_addr = deps.api.addr_validate(&contract_address)?;
+            let temp_data = load_pending_poll(deps.as_ref(), pending_id)?;
+            PENDING_POLLS.remove(deps.storage, pending_id);
+            let poll_addr = temp_data.poll_addr.ok_or(ContractError::InstantiateFailed {})?;
             
-            // Save poll info
+            // Save poll info, the poll counts its end time from the same block
+            let count = POLL_COUNT.load(deps.storage)?;
             let poll_info = PollInfo {
                 creator: temp_data.creator,
                 question: temp_data.question,
                 avatar: temp_data.avatar,
                 description: temp_data.description,
-                yes_token: temp_data.yes_token.unwrap(),
-                no_token: temp_data.no_token.unwrap(),
+                outcome_tokens: temp_data.outcome_tokens,
                 poll_addr: poll_addr.clone(),
+                denom: temp_data.denom,
+                index: count,
+                end_time: env.block.time.seconds() + temp_data.duration,
+                status: PollStatus::Active,
+                total_value_locked: Uint128::zero(),
             };
             
-            POLLS.save(deps.storage, &poll_addr, &poll_info)?;
-            let count = POLL_COUNT.load(deps.storage)?;
+            // The poll becomes sole minter and owner of its outcome tokens
+            let handover_msgs = poll_info
+                .outcome_tokens
+                .iter()
+                .map(|token| -> StdResult<_> {
+                    Ok(WasmMsg::Execute {
+                        contract_addr: token.to_string(),
+                        msg: to_json_binary(&Cw20ExecuteMsg::UpdateMinter {
+                            new_minter: Some(poll_addr.to_string()),
+                        })?,
+                        funds: vec![],
+                    })
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+
+            polls().save(deps.storage, &poll_addr, &poll_info)?;
             POLL_SEQUENCE.save(deps.storage, count, &poll_addr)?;
             POLL_COUNT.save(deps.storage, &(count + 1))?;
             
             Ok(Response::new()
+                .add_messages(handover_msgs)
                 .add_attribute("action", "create_poll_complete")
+                .add_attribute("pending_id", pending_id.to_string())
                 .add_attribute("poll_addr", poll_addr))
         }
         _ => Err(ContractError::UnknownReplyId { id: msg.id }),
     }
 }
 
+/// Fills in the lifecycle fields of polls stored before core tracked them, which builds their
+/// index entries, and recounts active and resolved polls. The polls' own state is the source.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let limit = msg.limit.unwrap_or(DEFAULT_MIGRATE_LIMIT).max(1) as usize;
+    let start = msg.start_after.map(Bound::exclusive);
+    // One more than a page tells whether another one follows
+    let sequence = POLL_SEQUENCE
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+    let next = sequence.get(limit).map(|_| sequence[limit - 1].0);
+
+    // Counts start over on the first page and add up over the next ones
+    let mut stats = MARKET_STATS.load(deps.storage)?;
+    if msg.start_after.is_none() {
+        stats.active_polls_count = 0;
+        stats.resolved_polls_count = 0;
+    }
+    for (index, addr) in sequence.into_iter().take(limit) {
+        let info: PollLifecycle = deps.querier.query_wasm_smart(&addr, &PollQueryMsg::GetPollInfo {})?;
+        let mut poll = match polls().may_load(deps.storage, &addr) {
+            Ok(Some(poll)) => poll,
+            // First-release entries, replaced without going through the indexes they never had
+            _ => {
+                let legacy = legacy::POLLS.load(deps.storage, &addr)?;
+                legacy::POLLS.remove(deps.storage, &addr);
+                PollInfo {
+                    creator: legacy.creator,
+                    question: legacy.question,
+                    avatar: legacy.avatar,
+                    description: legacy.description,
+                    outcome_tokens: vec![legacy.yes_token, legacy.no_token],
+                    poll_addr: legacy.poll_addr,
+                    denom: info
+                        .denom
+                        .clone()
+                        .or_else(|| info.collateral.as_ref().map(Collateral::to_string))
+                        .unwrap_or_else(|| config.denom.clone()),
+                    index,
+                    end_time: info.end_timestamp,
+                    status: PollStatus::Active,
+                    total_value_locked: Uint128::zero(),
+                }
+            },
+        };
+        poll.index = index;
+        poll.end_time = info.end_timestamp;
+        poll.status = if info.is_invalid {
+            PollStatus::Cancelled
+        } else if info.is_resolved {
+            PollStatus::Resolved
+        } else {
+            PollStatus::Active
+        };
+        if poll.status == PollStatus::Active {
+            stats.active_polls_count += 1;
+        } else {
+            stats.resolved_polls_count += 1;
+        }
+        polls().save(deps.storage, &addr, &poll)?;
+    }
+    MARKET_STATS.save(deps.storage, &stats)?;
+
+    let mut res = Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("active_polls", stats.active_polls_count.to_string())
+        .add_attribute("resolved_polls", stats.resolved_polls_count.to_string());
+    if let Some(next) = next {
+        res = res.add_attribute("next", next.to_string());
+    }
+    Ok(res)
+}
+
+/// The part of `GetPollInfo` every poll version answers with, first release included
+#[derive(serde::Deserialize)]
+struct PollLifecycle {
+    end_timestamp: u64,
+    is_resolved: bool,
+    #[serde(default)]
+    is_invalid: bool,
+    /// Collateral denom of first-release polls
+    #[serde(default)]
+    denom: Option<String>,
+    #[serde(default)]
+    collateral: Option<Collateral>,
+}
+
+fn load_pending_poll(deps: Deps, pending_id: u64) -> Result<TempPollData, ContractError> {
+    PENDING_POLLS
+        .may_load(deps.storage, pending_id)?
+        .ok_or(ContractError::UnknownPendingPoll { pending_id })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
-    use cosmwasm_std::{coins, Coin, OwnedDeps};
+    use cosmwasm_std::{coins, CodeInfoResponse, Coin, ContractResult, OwnedDeps, SystemError, SystemResult, WasmQuery};
 
     fn setup_contract() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
         let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::CodeInfo { code_id } => {
+                let response = CodeInfoResponse::new(*code_id, "deployer".to_string(), HexBinary::from([*code_id as u8; 32]));
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&response).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
         let msg = InstantiateMsg {
             initial_fee: Uint128::new(1000000),
             protocol_fee: 100,
@@ -577,14 +1384,30 @@ mod tests {
             question: "Test Poll?".to_string(),
             avatar: "avatar_url".to_string(),
             description: "Test Description".to_string(),
-            yes_token_name: "YES".to_string(),
-            yes_token_symbol: "YES".to_string(),
-            no_token_name: "NO".to_string(),
-            no_token_symbol: "NO".to_string(),
+            outcomes: vec![
+                OutcomeTokenInfo {
+                    name: "YES".to_string(),
+                    token_name: "YES".to_string(),
+                    token_symbol: "YES".to_string(),
+                },
+                OutcomeTokenInfo {
+                    name: "NO".to_string(),
+                    token_name: "NO".to_string(),
+                    token_symbol: "NO".to_string(),
+                },
+            ],
             duration: 1000,
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: None,
         };
 
         let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(2, res.messages.len());
+        assert_eq!(3, res.messages.len());
     }
 } 
\ No newline at end of file
@@ -38,4 +38,22 @@ pub enum ContractError {
     
     #[error("Poll already exists")]
     PollAlreadyExists {},
+
+    #[error("Poll not found: {0}")]
+    PollNotFound(String),
+
+    #[error("No poll creation pending with id {pending_id}")]
+    UnknownPendingPoll { pending_id: u64 },
+
+    #[error("Denom {denom} is not whitelisted")]
+    DenomNotWhitelisted { denom: String },
+
+    #[error("The default denom is managed through the config")]
+    DefaultDenom {},
+
+    #[error("Invalid number of outcomes: min {min}, max {max}")]
+    InvalidOutcomeCount { min: usize, max: usize },
+
+    #[error("No fees to withdraw")]
+    NoFeesToWithdraw {},
 } 
\ No newline at end of file
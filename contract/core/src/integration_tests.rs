@@ -1,14 +1,8 @@
-use super::*;
-use crate::msg::*;
-use crate::contract::{instantiate, execute, query};
+use crate::contract::{instantiate, execute, migrate, query, reply};
 use crate::error::ContractError;
-use crate::state::{REPLY_YES_TOKEN_INIT};
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{
-    coins, from_json, Addr, Coin, SubMsg, WasmMsg, Uint128,
-    Response, StdResult, to_json_binary,
-};
-use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use crate::state::{encode_reply_id, legacy, polls, MarketStats, PollInfo, MARKET_STATS, PENDING_POLLS, POLL_COUNT, POLL_SEQUENCE, REPLY_POLL_INIT};
+use cosmwasm_std::{Addr, Uint128};
+use xion_capypolls_poll::msg::{ExecuteMsg as PollExecuteMsg, InstantiateMsg as PollInstantiateMsg, PollEvent};
 
 const XION_DENOM: &str = "uxion";
 
@@ -16,13 +10,31 @@ const XION_DENOM: &str = "uxion";
 mod tests {
     use super::*;
     use crate::msg::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_json, Addr, Coin, SubMsg, WasmMsg, CosmosMsg};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{
+        from_json, to_json_binary, CodeInfoResponse, Coin, ContractResult, Deps, DepsMut, Env, Event, HexBinary, OwnedDeps, Reply, Response, StdResult, SubMsg,
+        SubMsgResponse, SubMsgResult, SystemError, SystemResult, WasmMsg, WasmQuery, CosmosMsg,
+    };
     use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
 
+    /// Mock dependencies that answer code info queries for the poll (1) and token (2) code ids
+    fn mock_dependencies_with_code_info() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies();
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::CodeInfo { code_id } if *code_id == 1 || *code_id == 2 => {
+                let checksum = HexBinary::from([*code_id as u8; 32]);
+                let response = CodeInfoResponse::new(*code_id, "deployer".to_string(), checksum);
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&response).unwrap()))
+            }
+            WasmQuery::CodeInfo { code_id } => SystemResult::Err(SystemError::NoSuchCode { code_id: *code_id }),
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        deps
+    }
+
     #[test]
     fn full_poll_creation_lifecycle() {
-        let mut deps = mock_dependencies();
+        let mut deps = mock_dependencies_with_code_info();
         let env = mock_env();
 
         // Setup contract
@@ -41,10 +53,26 @@ mod tests {
             avatar: "avatar_url".to_string(),
             description: "Test Description".to_string(),
             duration: 1000,
-            yes_token_name: "YES".to_string(),
-            yes_token_symbol: "YES".to_string(),
-            no_token_name: "NO".to_string(),
-            no_token_symbol: "NO".to_string(),
+            outcomes: vec![
+                OutcomeTokenInfo {
+                    name: "YES".to_string(),
+                    token_name: "YES".to_string(),
+                    token_symbol: "YES".to_string(),
+                },
+                OutcomeTokenInfo {
+                    name: "NO".to_string(),
+                    token_name: "NO".to_string(),
+                    token_symbol: "NO".to_string(),
+                },
+            ],
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: None,
         };
         let info = mock_info(
             "creator",
@@ -54,21 +82,38 @@ mod tests {
             }],
         );
 
+        let predicted: PredictedAddressesResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::PredictPollAddresses {
+                    creator: "creator".to_string(),
+                    nonce: None,
+                    num_outcomes: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(predicted.nonce, 0);
+
         let res = execute(deps.as_mut(), env.clone(), info.clone(), create_poll_msg).unwrap();
 
-        // Verify YES token creation message
-        assert_eq!(1, res.messages.len());
-        if let SubMsg { msg, id, .. } = &res.messages[0] {
-            assert_eq!(*id, REPLY_YES_TOKEN_INIT);
+        // Both tokens and the poll are instantiated at once, only the poll replies
+        assert_eq!(3, res.messages.len());
+        let SubMsg { msg, id, .. } = &res.messages[0];
+        {
+            assert_eq!(*id, 0);
             match msg.clone() {
-                CosmosMsg::Wasm(WasmMsg::Instantiate {
+                CosmosMsg::Wasm(WasmMsg::Instantiate2 {
                     admin,
                     code_id,
                     msg: init_msg,
                     funds,
                     label,
+                    ..
                 }) => {
-                    assert_eq!(admin.unwrap(), "creator");
+                    assert_eq!(admin.unwrap(), env.contract.address.as_str());
                     assert_eq!(code_id, 2);
                     assert!(funds.is_empty());
                     assert_eq!(label, "YES Token for Poll Test Poll?");
@@ -78,23 +123,52 @@ mod tests {
                     assert_eq!(init_msg.symbol, "YES");
                     assert_eq!(init_msg.decimals, 18);
                     assert!(init_msg.initial_balances.is_empty());
+                    // The factory holds mint authority until the poll exists
                     assert_eq!(
                         init_msg.mint.unwrap().minter,
-                        "creator"
+                        env.contract.address.as_str()
                     );
                 }
-                _ => panic!("Expected WasmMsg::Instantiate"),
+                _ => panic!("Expected WasmMsg::Instantiate2"),
+            }
+        }
+
+        // The poll is wired to the predicted token addresses
+        let SubMsg { msg, id, .. } = &res.messages[2];
+        assert_eq!(*id, encode_reply_id(0, REPLY_POLL_INIT));
+        match msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate2 { admin, code_id, msg, .. }) => {
+                // Only core can migrate the poll, never its creator
+                assert_eq!(admin.as_deref(), Some(env.contract.address.as_str()));
+                assert_eq!(*code_id, 1);
+                let poll_init: PollInstantiateMsg = from_json(msg).unwrap();
+                let tokens: Vec<_> = poll_init.outcomes.into_iter().map(|o| o.token).collect();
+                assert_eq!(tokens, predicted.outcome_tokens);
             }
+            _ => panic!("Expected WasmMsg::Instantiate2"),
         }
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "poll_addr" && attr.value == predicted.poll));
 
         // Query poll count
         let query_msg = QueryMsg::GetPollCount {};
-        let res: PollCountResponse = from_json(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        let res: PollCountResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
         assert_eq!(res.count, 0); // Count is updated after poll creation is complete
 
+        // Creating a poll is not participating in one
+        let stats: MarketStats = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetMarketStats {}).unwrap()).unwrap();
+        assert_eq!(stats.total_polls_created, 1);
+        assert_eq!(stats.total_unique_participants, 0);
+
+        // The creation fee is held for `WithdrawFees`
+        let res: CollectedFeesResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetCollectedFees {}).unwrap()).unwrap();
+        assert_eq!(res.fees, vec![Coin::new(1000000, XION_DENOM)]);
+
         // Query config
         let query_msg = QueryMsg::GetConfig {};
-        let res: ConfigResponse = from_json(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        let res: ConfigResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
         assert_eq!(res.owner, "owner");
         assert_eq!(res.initial_fee, Uint128::new(1000000));
         assert_eq!(res.protocol_fee, 100);
@@ -105,7 +179,7 @@ mod tests {
 
     #[test]
     fn test_fee_management() {
-        let mut deps = mock_dependencies();
+        let mut deps = mock_dependencies_with_code_info();
         let env = mock_env();
 
         // Setup contract
@@ -163,7 +237,7 @@ mod tests {
 
     #[test]
     fn test_code_id_management() {
-        let mut deps = mock_dependencies();
+        let mut deps = mock_dependencies_with_code_info();
         let env = mock_env();
 
         // Setup contract
@@ -209,7 +283,7 @@ mod tests {
 
     #[test]
     fn test_poll_queries() {
-        let mut deps = mock_dependencies();
+        let mut deps = mock_dependencies_with_code_info();
         let env = mock_env();
 
         // Setup contract
@@ -226,7 +300,7 @@ mod tests {
         let query_msg = QueryMsg::GetPollDetails {
             poll_address: "poll1".to_string(),
         };
-        let res: PollDetailsResponse = from_json(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        let res: PollDetailsResponse = from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
         assert!(!res.exists);
         assert!(res.description.is_none());
 
@@ -238,4 +312,1032 @@ mod tests {
             cosmwasm_std::StdError::generic_err("Index out of bounds")
         );
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_resolve_poll_through_core() {
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+
+        // Setup contract
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        let info = mock_info("owner", &[]);
+        instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
+
+        // Register a poll as if the creation reply chain had completed
+        let poll_addr = Addr::unchecked("poll1");
+        polls().save(deps.as_mut().storage, &poll_addr, &poll_info("poll1", 0)).unwrap();
+
+        // Only the owner can manage resolvers
+        let msg = ExecuteMsg::UpdateResolvers {
+            add: vec!["resolver".to_string()],
+            remove: vec![],
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let res: ResolversResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetResolvers {}).unwrap()).unwrap();
+        assert_eq!(res.resolvers, vec!["resolver".to_string()]);
+
+        // Accounts outside the resolver set cannot resolve
+        let resolve_msg = ExecuteMsg::ResolvePoll {
+            poll_address: "poll1".to_string(),
+            winning_position: 0,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), resolve_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        // Unknown polls are rejected
+        let msg = ExecuteMsg::ResolvePoll {
+            poll_address: "poll2".to_string(),
+            winning_position: 0,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("resolver", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::PollNotFound("poll2".to_string()));
+
+        // A resolver forwards the outcome to the poll and emits a core event
+        let res = execute(deps.as_mut(), env.clone(), mock_info("resolver", &[]), resolve_msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
+                assert_eq!(contract_addr, "poll1");
+                assert!(funds.is_empty());
+                let msg: PollExecuteMsg = from_json(msg).unwrap();
+                assert_eq!(msg, PollExecuteMsg::ResolvePoll { winning_position: 0 });
+            }
+            _ => panic!("Expected WasmMsg::Execute"),
+        }
+        assert_eq!(1, res.events.len());
+        assert_eq!(res.events[0].ty, "poll_resolved");
+        assert!(res.events[0]
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "resolver" && attr.value == "resolver"));
+
+        // Resolvers can also settle a poll as Invalid
+        let msg = ExecuteMsg::InvalidatePoll {
+            poll_address: "poll1".to_string(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("resolver", &[]), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                assert_eq!(contract_addr, "poll1");
+                let msg: PollExecuteMsg = from_json(msg).unwrap();
+                assert_eq!(msg, PollExecuteMsg::InvalidatePoll {});
+            }
+            _ => panic!("Expected WasmMsg::Execute"),
+        }
+        assert_eq!(res.events[0].ty, "poll_invalidated");
+
+        // Disputes left to core are settled by the owner alone
+        let msg = ExecuteMsg::SettleDispute {
+            poll_address: "poll1".to_string(),
+            winning_position: 1,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("resolver", &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                assert_eq!(contract_addr, "poll1");
+                let msg: PollExecuteMsg = from_json(msg).unwrap();
+                assert_eq!(msg, PollExecuteMsg::SettleDispute { winning_position: 1 });
+            }
+            _ => panic!("Expected WasmMsg::Execute"),
+        }
+        assert_eq!(res.events[0].ty, "poll_dispute_settled");
+
+        // Removing the resolver revokes access again
+        let msg = ExecuteMsg::UpdateResolvers {
+            add: vec![],
+            remove: vec!["resolver".to_string()],
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = ExecuteMsg::ResolvePoll {
+            poll_address: "poll1".to_string(),
+            winning_position: 1,
+        };
+        let err = execute(deps.as_mut(), env, mock_info("resolver", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+    }
+
+    /// A binary poll as the creation reply stores it, ending 1000 seconds after the mock block
+    fn poll_info(poll_addr: &str, index: u64) -> PollInfo {
+        PollInfo {
+            creator: Addr::unchecked("creator"),
+            question: "Test Poll?".to_string(),
+            avatar: "avatar_url".to_string(),
+            description: "Test Description".to_string(),
+            outcome_tokens: vec![Addr::unchecked("yes_token"), Addr::unchecked("no_token")],
+            poll_addr: Addr::unchecked(poll_addr),
+            denom: XION_DENOM.to_string(),
+            index,
+            end_time: mock_env().block.time.seconds() + 1000,
+            status: PollStatus::Active,
+            total_value_locked: Uint128::zero(),
+        }
+    }
+
+    fn instantiate_reply(id: u64, contract_address: &str) -> Reply {
+        Reply {
+            id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("instantiate")
+                    .add_attribute("_contract_address", contract_address)],
+                data: None,
+            }),
+        }
+    }
+
+    fn outcome(name: &str) -> OutcomeTokenInfo {
+        OutcomeTokenInfo {
+            name: name.to_string(),
+            token_name: format!("{} Token", name),
+            token_symbol: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn categorical_poll_creation() {
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        let fee = [Coin {
+            denom: XION_DENOM.to_string(),
+            amount: Uint128::new(1000000),
+        }];
+
+        // Outcome count is bounded
+        let msg = ExecuteMsg::CreatePoll {
+            question: "Who wins?".to_string(),
+            avatar: "avatar_url".to_string(),
+            description: "Test Description".to_string(),
+            duration: 1000,
+            outcomes: vec![outcome("RED")],
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &fee), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidOutcomeCount { min: 2, max: 16 });
+
+        let predicted: PredictedAddressesResponse = from_json(
+            query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::PredictPollAddresses {
+                    creator: "creator".to_string(),
+                    nonce: None,
+                    num_outcomes: Some(3),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            question: "Who wins?".to_string(),
+            avatar: "avatar_url".to_string(),
+            description: "Test Description".to_string(),
+            duration: 1000,
+            outcomes: vec![outcome("RED"), outcome("GREEN"), outcome("BLUE")],
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &fee), msg).unwrap();
+        assert_eq!(4, res.messages.len());
+        let labels: Vec<_> = res.messages[..3]
+            .iter()
+            .map(|msg| match &msg.msg {
+                CosmosMsg::Wasm(WasmMsg::Instantiate2 { label, .. }) => label.clone(),
+                _ => panic!("Expected WasmMsg::Instantiate2"),
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "RED Token for Poll Who wins?",
+                "GREEN Token for Poll Who wins?",
+                "BLUE Token for Poll Who wins?",
+            ]
+        );
+
+        // Registration uses the predicted addresses, no event parsing involved
+        reply(deps.as_mut(), env, instantiate_reply(encode_reply_id(0, REPLY_POLL_INIT), "ignored")).unwrap();
+        let poll_info = polls().load(deps.as_ref().storage, &Addr::unchecked(&predicted.poll)).unwrap();
+        let tokens: Vec<_> = poll_info.outcome_tokens.into_iter().map(String::from).collect();
+        assert_eq!(tokens, predicted.outcome_tokens);
+    }
+
+    #[test]
+    fn interleaved_poll_creations() {
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        let fee = [Coin {
+            denom: XION_DENOM.to_string(),
+            amount: Uint128::new(1000000),
+        }];
+        let create = |question: &str| ExecuteMsg::CreatePoll {
+            question: question.to_string(),
+            avatar: "avatar_url".to_string(),
+            description: "Test Description".to_string(),
+            duration: 1000,
+            outcomes: vec![outcome("YES"), outcome("NAY")],
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: None,
+        };
+
+        // Two creations by the same creator in one tx get their own pending ids and nonces
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &fee), create("First?")).unwrap();
+        assert_eq!(res.messages[2].id, encode_reply_id(0, REPLY_POLL_INIT));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &fee), create("Second?")).unwrap();
+        assert_eq!(res.messages[2].id, encode_reply_id(1, REPLY_POLL_INIT));
+
+        let predict = |nonce| -> PredictedAddressesResponse {
+            let msg = QueryMsg::PredictPollAddresses {
+                creator: "alice".to_string(),
+                nonce: Some(nonce),
+                num_outcomes: None,
+            };
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let (first, second) = (predict(0), predict(1));
+        assert_ne!(first.poll, second.poll);
+        assert_ne!(first.outcome_tokens, second.outcome_tokens);
+
+        // Replies arrive out of order, each one only touches its own creation
+        let poll_reply = |pending_id| instantiate_reply(encode_reply_id(pending_id, REPLY_POLL_INIT), "ignored");
+        reply(deps.as_mut(), env.clone(), poll_reply(1)).unwrap();
+        reply(deps.as_mut(), env.clone(), poll_reply(0)).unwrap();
+
+        let poll = polls().load(deps.as_ref().storage, &Addr::unchecked(&first.poll)).unwrap();
+        assert_eq!(poll.question, "First?");
+        let poll = polls().load(deps.as_ref().storage, &Addr::unchecked(&second.poll)).unwrap();
+        assert_eq!(poll.question, "Second?");
+
+        // Finished creations are cleaned up, stray replies are rejected
+        assert!(PENDING_POLLS.is_empty(deps.as_ref().storage));
+        let err = reply(deps.as_mut(), env, poll_reply(0)).unwrap_err();
+        assert_eq!(err, ContractError::UnknownPendingPoll { pending_id: 0 });
+    }
+
+    #[test]
+    fn mint_authority_handover() {
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        let msg = ExecuteMsg::CreatePoll {
+            question: "Test Poll?".to_string(),
+            avatar: "avatar_url".to_string(),
+            description: "Test Description".to_string(),
+            duration: 1000,
+            outcomes: vec![outcome("YES"), outcome("NAY")],
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: None,
+        };
+        let fee = [Coin {
+            denom: XION_DENOM.to_string(),
+            amount: Uint128::new(1000000),
+        }];
+        execute(deps.as_mut(), env.clone(), mock_info("creator", &fee), msg).unwrap();
+
+        // Registering the poll hands minter and ownership of both tokens to it
+        let res = reply(deps.as_mut(), env.clone(), instantiate_reply(encode_reply_id(0, REPLY_POLL_INIT), "ignored")).unwrap();
+        let poll_info = polls().range(deps.as_ref().storage, None, None, cosmwasm_std::Order::Ascending)
+            .next()
+            .unwrap()
+            .unwrap()
+            .1;
+        let poll = poll_info.poll_addr.to_string();
+        assert_eq!(2, res.messages.len());
+        for (sub_msg, token) in res.messages.iter().zip(&poll_info.outcome_tokens) {
+            match &sub_msg.msg {
+                CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                    assert_eq!(contract_addr, token.as_str());
+                    let msg: cw20::Cw20ExecuteMsg = from_json(msg).unwrap();
+                    assert_eq!(msg, cw20::Cw20ExecuteMsg::UpdateMinter { new_minter: Some(poll.clone()) });
+                }
+                _ => panic!("Expected WasmMsg::Execute"),
+            }
+        }
+
+        // Tokens answer as they would once the handover messages executed
+        let authority = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>| -> MintAuthorityResponse {
+            let msg = QueryMsg::GetMintAuthority { poll_address: poll.clone() };
+            from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+        };
+        let set_authority = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, holder: String| {
+            deps.querier.update_wasm(move |query| match query {
+                WasmQuery::Smart { .. } => {
+                    let minter = Some(cw20::MinterResponse { minter: holder.clone(), cap: None });
+                    SystemResult::Ok(ContractResult::Ok(to_json_binary(&minter).unwrap()))
+                }
+                WasmQuery::Raw { .. } => {
+                    let ownership = cw_ownable::Ownership {
+                        owner: Some(Addr::unchecked(&holder)),
+                        pending_owner: None,
+                        pending_expiry: None,
+                    };
+                    SystemResult::Ok(ContractResult::Ok(to_json_binary(&ownership).unwrap()))
+                }
+                _ => SystemResult::Err(SystemError::Unknown {}),
+            });
+        };
+
+        set_authority(&mut deps, env.contract.address.to_string());
+        let res = authority(&deps);
+        assert!(!res.handed_over);
+        assert_eq!(res.tokens[0].minter.as_deref(), Some(env.contract.address.as_str()));
+
+        set_authority(&mut deps, poll.clone());
+        let res = authority(&deps);
+        assert!(res.handed_over);
+        assert_eq!(res.tokens.len(), 2);
+        assert!(res.tokens.iter().all(|token| token.owner.as_deref() == Some(poll.as_str())));
+    }
+
+    #[test]
+    fn collateral_denom_whitelist() {
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+        const USDC: &str = "ibc/498A0751C798A0D9A389AA3691123DADA57DAA4FE165D5C75894505B876BA6E4";
+
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        let create = |denom: Option<&str>| ExecuteMsg::CreatePoll {
+            question: "Test Poll?".to_string(),
+            avatar: "avatar_url".to_string(),
+            description: "Test Description".to_string(),
+            duration: 1000,
+            outcomes: vec![outcome("YES"), outcome("NO")],
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            sale: None,
+            denom: denom.map(String::from),
+        };
+        let usdc_fee = [Coin::new(5_000_000, USDC)];
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &usdc_fee), create(None)).unwrap_err();
+        assert_eq!(err, ContractError::NoXionPayment {});
+
+        // Only the owner manages the whitelist, and the config denom stays out of it
+        let whitelist = ExecuteMsg::UpdateCollateralDenoms {
+            add: vec![DenomFee {
+                denom: USDC.to_string(),
+                creation_fee: Uint128::new(5_000_000),
+            }],
+            remove: vec![],
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), whitelist.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let msg = ExecuteMsg::UpdateCollateralDenoms {
+            add: vec![],
+            remove: vec![XION_DENOM.to_string()],
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::DefaultDenom {});
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), whitelist).unwrap();
+
+        let res: CollateralDenomsResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetCollateralDenoms {}).unwrap()).unwrap();
+        let denoms: Vec<_> = res.denoms.iter().map(|entry| (entry.denom.as_str(), entry.creation_fee.u128())).collect();
+        assert_eq!(denoms, vec![(XION_DENOM, 1_000_000), (USDC, 5_000_000)]);
+
+        // Fees are charged at the rate of the denom they are paid in
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[Coin::new(1_000_000, USDC)]), create(Some(USDC)))
+            .unwrap_err();
+        assert_eq!(err, ContractError::InvalidFeeAmount {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &usdc_fee), create(Some("uatom"))).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::DenomNotWhitelisted {
+                denom: "uatom".to_string()
+            }
+        );
+
+        // The fee comes alone, anything sent along would be stuck in core
+        let funds = [Coin::new(1_000_000, XION_DENOM), Coin::new(5_000_000, USDC)];
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &funds), create(None)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidFeeAmount {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), create(None)).unwrap_err();
+        assert_eq!(err, ContractError::NoXionPayment {});
+
+        // A USDC poll paid for in XION, and a XION poll paid for in USDC
+        for (fee, denom) in [(Coin::new(1_000_000, XION_DENOM), USDC), (usdc_fee[0].clone(), XION_DENOM)] {
+            let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[fee]), create(Some(denom))).unwrap();
+            let poll_init = match &res.messages[2].msg {
+                CosmosMsg::Wasm(WasmMsg::Instantiate2 { msg, .. }) => from_json::<PollInstantiateMsg>(msg).unwrap(),
+                _ => panic!("Expected WasmMsg::Instantiate2"),
+            };
+            assert_eq!(
+                poll_init.collateral,
+                xion_capypolls_poll::msg::CollateralInfo::Native {
+                    denom: denom.to_string()
+                }
+            );
+            // Default stake bounds are in XION and only back XION polls
+            let min_stake = poll_init.limits.and_then(|limits| limits.min_stake);
+            assert_eq!(min_stake, (denom == XION_DENOM).then(|| Uint128::new(1_000_000)));
+        }
+
+        // Removed denoms can no longer be used
+        let msg = ExecuteMsg::UpdateCollateralDenoms {
+            add: vec![],
+            remove: vec![USDC.to_string()],
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("owner", &[]), msg).unwrap();
+        let err = execute(deps.as_mut(), env, mock_info("creator", &usdc_fee), create(None)).unwrap_err();
+        assert_eq!(err, ContractError::NoXionPayment {});
+    }
+
+    #[test]
+    fn record_participation_and_user_polls() {
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        // poll2 is resolved, the others still run
+        for (index, poll) in ["poll1", "poll2", "poll3"].into_iter().enumerate() {
+            let mut poll_info = poll_info(poll, index as u64);
+            if poll == "poll2" {
+                poll_info.status = PollStatus::Resolved;
+            }
+            polls().save(deps.as_mut().storage, &Addr::unchecked(poll), &poll_info).unwrap();
+        }
+
+        let record = |user: &str| ExecuteMsg::RecordParticipation { user: user.to_string() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), record("user1")).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+
+        for (poll, user) in [("poll1", "user1"), ("poll1", "user2"), ("poll2", "user1"), ("poll3", "user1")] {
+            execute(deps.as_mut(), env.clone(), mock_info(poll, &[]), record(user)).unwrap();
+        }
+        let stats: MarketStats = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetMarketStats {}).unwrap()).unwrap();
+        assert_eq!(stats.total_unique_participants, 2);
+
+        let user_polls = |status_filter: Option<PollStatus>, start_after: Option<&str>, limit: Option<u32>| {
+            let msg = QueryMsg::GetUserPolls {
+                user: "user1".to_string(),
+                status_filter,
+                start_after: start_after.map(str::to_string),
+                limit,
+            };
+            let res: UserPollsResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+            res.polls.into_iter().map(|poll| poll.address).collect::<Vec<_>>()
+        };
+        assert_eq!(user_polls(None, None, None), vec!["poll1", "poll2", "poll3"]);
+        assert_eq!(user_polls(Some(PollStatus::Active), None, None), vec!["poll1", "poll3"]);
+        assert_eq!(user_polls(Some(PollStatus::Resolved), None, None), vec!["poll2"]);
+        assert_eq!(user_polls(Some(PollStatus::Active), None, Some(1)), vec!["poll1"]);
+        assert_eq!(user_polls(None, Some("poll1"), None), vec!["poll2", "poll3"]);
+    }
+
+    /// Registers a poll the way the creation reply does. Multi-test hands out sequential
+    /// addresses, so polls are deployed directly instead of through `CreatePoll`.
+    #[cosmwasm_schema::cw_serde]
+    struct RegisterPoll {
+        poll: String,
+        denom: String,
+    }
+
+    fn register_poll(deps: DepsMut, env: Env, msg: RegisterPoll) -> StdResult<Response> {
+        let count = POLL_COUNT.load(deps.storage)?;
+        let poll_info = PollInfo {
+            end_time: env.block.time.seconds() + 1000,
+            denom: msg.denom,
+            ..poll_info(&msg.poll, count)
+        };
+        polls().save(deps.storage, &poll_info.poll_addr, &poll_info)?;
+        POLL_SEQUENCE.save(deps.storage, count, &poll_info.poll_addr)?;
+        POLL_COUNT.save(deps.storage, &(count + 1))?;
+        MARKET_STATS.update(deps.storage, |mut stats| -> StdResult<_> {
+            stats.active_polls_count += 1;
+            stats.total_polls_created += 1;
+            Ok(stats)
+        })?;
+        Ok(Response::new())
+    }
+
+    #[test]
+    fn market_stats_multitest() {
+        use cosmwasm_std::Decimal;
+        use cw_multi_test::{App, ContractWrapper, Executor};
+        use xion_capypolls_poll::msg::{CollateralInfo, OptimisticInfo, OutcomeInfo};
+        use xion_capypolls_poll::state::{NO, YES};
+
+        const USDC: &str = "ibc/usdc";
+        let owner = Addr::unchecked("owner");
+        let mut app = App::new(|router, _, storage| {
+            for user in ["proposer", "staker1", "staker2"] {
+                let funds = vec![Coin::new(1_000, XION_DENOM), Coin::new(1_000, USDC)];
+                router.bank.init_balance(storage, &Addr::unchecked(user), funds).unwrap();
+            }
+        });
+
+        let core_code_id = app.store_code(Box::new(
+            ContractWrapper::new(execute, instantiate, query).with_reply(reply).with_sudo(register_poll),
+        ));
+        let poll_code_id = app.store_code(Box::new(
+            ContractWrapper::new(
+                xion_capypolls_poll::contract::execute,
+                xion_capypolls_poll::contract::instantiate,
+                xion_capypolls_poll::contract::query,
+            )
+            .with_reply(xion_capypolls_poll::contract::reply),
+        ));
+        let token_code_id = app.store_code(Box::new(ContractWrapper::new(
+            cw20_base::contract::execute,
+            cw20_base::contract::instantiate,
+            cw20_base::contract::query,
+        )));
+
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id,
+            token_code_id,
+        };
+        let core = app
+            .instantiate_contract(core_code_id, owner.clone(), &init_msg, &[], "core", None)
+            .unwrap();
+
+        let deploy_poll = |app: &mut App, denom: &str, optimistic: Option<OptimisticInfo>, protocol_fee: Option<Decimal>, register: bool| {
+            let outcomes = ["YES", "NAY"]
+                .into_iter()
+                .map(|symbol| {
+                    let token_init = Cw20InstantiateMsg {
+                        name: format!("{} Token", symbol),
+                        symbol: symbol.to_string(),
+                        decimals: 18,
+                        initial_balances: vec![],
+                        mint: Some(cw20::MinterResponse {
+                            minter: owner.to_string(),
+                            cap: Some(Uint128::new(xion_capypolls_poll::state::MAX_TOKEN_SUPPLY)),
+                        }),
+                        marketing: None,
+                    };
+                    let token = app
+                        .instantiate_contract(token_code_id, owner.clone(), &token_init, &[], symbol, None)
+                        .unwrap();
+                    OutcomeInfo {
+                        name: symbol.to_string(),
+                        token: token.to_string(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            let poll_init = PollInstantiateMsg {
+                capy_core: core.to_string(),
+                poll_creator: "creator".to_string(),
+                outcomes: outcomes.clone(),
+                duration: 1000,
+                collateral: CollateralInfo::Native {
+                    denom: denom.to_string(),
+                },
+                oracle: None,
+                optimistic,
+                scalar: None,
+                epoch_schedule: None,
+                exit_penalty: None,
+                limits: None,
+                protocol_fee,
+                sale: None,
+            };
+            let poll = app
+                .instantiate_contract(poll_code_id, owner.clone(), &poll_init, &[], "poll", None)
+                .unwrap();
+            for outcome in outcomes {
+                let handover = cw20::Cw20ExecuteMsg::UpdateMinter {
+                    new_minter: Some(poll.to_string()),
+                };
+                app.execute_contract(owner.clone(), Addr::unchecked(outcome.token), &handover, &[])
+                    .unwrap();
+            }
+            if register {
+                let register = RegisterPoll {
+                    poll: poll.to_string(),
+                    denom: denom.to_string(),
+                };
+                app.wasm_sudo(core.clone(), &register).unwrap();
+            }
+            poll
+        };
+        let optimistic = OptimisticInfo {
+            bond: Uint128::new(100),
+            challenge_period: 600,
+            arbiter: None,
+        };
+        let poll_a = deploy_poll(&mut app, XION_DENOM, Some(optimistic), Some(Decimal::percent(1)), true);
+        let poll_b = deploy_poll(&mut app, XION_DENOM, None, None, true);
+        let poll_c = deploy_poll(&mut app, USDC, None, None, true);
+
+        // Core's numbers have to match what the polls actually hold
+        let assert_stats = |app: &App, volume: (u128, u128), active: u64, resolved: u64| {
+            let held = |polls: &[&Addr], denom: &str| {
+                polls
+                    .iter()
+                    .map(|poll| app.wrap().query_balance(*poll, denom).unwrap().amount)
+                    .sum::<Uint128>()
+            };
+            let stats: MarketStats = app.wrap().query_wasm_smart(&core, &QueryMsg::GetMarketStats {}).unwrap();
+            let res: DenomStatsResponse = app.wrap().query_wasm_smart(&core, &QueryMsg::GetDenomStats {}).unwrap();
+            assert_eq!(
+                res.denoms,
+                vec![
+                    DenomStatsEntry {
+                        denom: USDC.to_string(),
+                        total_value_locked: held(&[&poll_c], USDC),
+                        total_volume: Uint128::new(volume.1),
+                    },
+                    DenomStatsEntry {
+                        denom: XION_DENOM.to_string(),
+                        total_value_locked: held(&[&poll_a, &poll_b], XION_DENOM),
+                        total_volume: Uint128::new(volume.0),
+                    },
+                ]
+            );
+            assert_eq!(stats.total_value_locked, held(&[&poll_a, &poll_b], XION_DENOM));
+            assert_eq!(stats.total_volume, Uint128::new(volume.0));
+            assert_eq!(stats.active_polls_count, active);
+            assert_eq!(stats.resolved_polls_count, resolved);
+            assert_eq!(stats.total_polls_created, 3);
+        };
+
+        let stake = |amount: u128, position: u8| PollExecuteMsg::Stake {
+            amount: Uint128::new(amount),
+            position,
+        };
+        let staker1 = Addr::unchecked("staker1");
+        let staker2 = Addr::unchecked("staker2");
+        app.execute_contract(staker1.clone(), poll_a.clone(), &stake(300, YES), &[Coin::new(300, XION_DENOM)])
+            .unwrap();
+        app.execute_contract(staker2.clone(), poll_a.clone(), &stake(200, NO), &[Coin::new(200, XION_DENOM)])
+            .unwrap();
+        app.execute_contract(staker1.clone(), poll_b.clone(), &stake(150, YES), &[Coin::new(150, XION_DENOM)])
+            .unwrap();
+        app.execute_contract(staker2.clone(), poll_c.clone(), &stake(400, NO), &[Coin::new(400, USDC)])
+            .unwrap();
+        assert_stats(&app, (645, 400), 3, 0);
+
+        // The exit penalty stays in the poll, only the payout leaves
+        let unstake = PollExecuteMsg::Unstake {
+            amount: Uint128::new(100),
+            position: NO,
+        };
+        app.execute_contract(staker2.clone(), poll_a.clone(), &unstake, &[]).unwrap();
+        let switch = PollExecuteMsg::SwitchPosition {
+            amount: Uint128::new(50),
+            from: YES,
+            to: NO,
+        };
+        app.execute_contract(staker1.clone(), poll_a.clone(), &switch, &[]).unwrap();
+        assert_stats(&app, (645, 400), 3, 0);
+
+        // Bonds count as locked while the proposal is open
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+        let proposer = Addr::unchecked("proposer");
+        let propose = PollExecuteMsg::ProposeOutcome { outcome: YES };
+        app.execute_contract(proposer.clone(), poll_a.clone(), &propose, &[Coin::new(100, XION_DENOM)])
+            .unwrap();
+        assert_stats(&app, (645, 400), 3, 0);
+
+        app.update_block(|block| block.time = block.time.plus_seconds(600));
+        app.execute_contract(proposer, poll_a.clone(), &PollExecuteMsg::FinalizeOutcome {}, &[])
+            .unwrap();
+        let invalidate = ExecuteMsg::InvalidatePoll {
+            poll_address: poll_b.to_string(),
+        };
+        app.execute_contract(owner.clone(), core.clone(), &invalidate, &[]).unwrap();
+        assert_stats(&app, (645, 400), 1, 2);
+
+        let list = |status: PollStatus| {
+            let msg = QueryMsg::ListPolls {
+                status: Some(status),
+                creator: None,
+                sort_by: None,
+                start_after: None,
+                limit: None,
+            };
+            let res: PollsResponse = app.wrap().query_wasm_smart(&core, &msg).unwrap();
+            res.polls.into_iter().map(|poll| poll.address).collect::<Vec<_>>()
+        };
+        assert_eq!(list(PollStatus::Resolved), vec![poll_a.to_string()]);
+        assert_eq!(list(PollStatus::Cancelled), vec![poll_b.to_string()]);
+        assert_eq!(list(PollStatus::Ended), vec![poll_c.to_string()]);
+        assert!(list(PollStatus::Active).is_empty());
+
+        for (staker, poll) in [(&staker1, &poll_a), (&staker2, &poll_a), (&staker1, &poll_b)] {
+            app.execute_contract(staker.clone(), poll.clone(), &PollExecuteMsg::WithdrawStake {}, &[])
+                .unwrap();
+        }
+        assert_stats(&app, (645, 400), 1, 2);
+
+        // Poll A sent 1% of its stakes to core, which withdraws nothing else it holds
+        let fees: CollectedFeesResponse = app.wrap().query_wasm_smart(&core, &QueryMsg::GetCollectedFees {}).unwrap();
+        assert_eq!(fees.fees, vec![Coin::new(5, XION_DENOM)]);
+        app.send_tokens(staker2.clone(), core.clone(), &[Coin::new(50, XION_DENOM)]).unwrap();
+        let withdraw = ExecuteMsg::WithdrawFees {
+            to: "treasury".to_string(),
+        };
+        app.execute_contract(owner.clone(), core.clone(), &withdraw, &[]).unwrap();
+        assert_eq!(app.wrap().query_balance("treasury", XION_DENOM).unwrap().amount, Uint128::new(5));
+        assert_eq!(app.wrap().query_balance(&core, XION_DENOM).unwrap().amount, Uint128::new(50));
+        let err = app.execute_contract(owner.clone(), core.clone(), &withdraw, &[]).unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::NoFeesToWithdraw {});
+
+        // Only registered polls may report
+        let report = ExecuteMsg::ReportPollEvent {
+            event: PollEvent::Staked { amount: Uint128::new(1) },
+        };
+        let err = app.execute_contract(staker1.clone(), core.clone(), &report, &[]).unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::Unauthorized {});
+
+        // A fee is only credited with the coins attached
+        let report = ExecuteMsg::ReportPollEvent {
+            event: PollEvent::FeeCollected { amount: Uint128::new(5) },
+        };
+        let err = app.execute_contract(poll_a.clone(), core.clone(), &report, &[]).unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::InvalidFeeAmount {});
+
+        // Core rejects every report of a poll it never registered, so such polls take no fee
+        let poll_d = deploy_poll(&mut app, XION_DENOM, None, Some(Decimal::percent(1)), false);
+        app.execute_contract(staker1, poll_d.clone(), &stake(100, YES), &[Coin::new(100, XION_DENOM)])
+            .unwrap();
+        assert_eq!(app.wrap().query_balance(&poll_d, XION_DENOM).unwrap().amount, Uint128::new(100));
+        let fees: CollectedFeesResponse = app.wrap().query_wasm_smart(&core, &QueryMsg::GetCollectedFees {}).unwrap();
+        assert!(fees.fees.is_empty());
+        assert_eq!(app.wrap().query_balance(&core, XION_DENOM).unwrap().amount, Uint128::new(50));
+    }
+
+    #[test]
+    fn list_polls_from_core_storage() {
+        let mut deps = mock_dependencies_with_code_info();
+        let mut env = mock_env();
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        // (address, creator, seconds until the end, tvl, status)
+        let setup = [
+            ("poll1", "alice", 300, 50, PollStatus::Active),
+            ("poll2", "bob", 100, 500, PollStatus::Active),
+            ("poll3", "alice", 200, 0, PollStatus::Resolved),
+            ("poll4", "bob", 400, 200, PollStatus::Cancelled),
+            ("poll5", "alice", 500, 200, PollStatus::Active),
+        ];
+        for (index, (poll, creator, ends_in, tvl, status)) in setup.into_iter().enumerate() {
+            let poll_info = PollInfo {
+                creator: Addr::unchecked(creator),
+                end_time: env.block.time.seconds() + ends_in,
+                total_value_locked: Uint128::new(tvl),
+                status,
+                ..poll_info(poll, index as u64)
+            };
+            polls().save(deps.as_mut().storage, &Addr::unchecked(poll), &poll_info).unwrap();
+        }
+        // poll2 has ended without a resolution
+        env.block.time = env.block.time.plus_seconds(150);
+
+        let list = |status: Option<PollStatus>, creator: Option<&str>, sort_by: Option<PollSortBy>, start_after: Option<&str>, limit: Option<u32>| {
+            let msg = QueryMsg::ListPolls {
+                status,
+                creator: creator.map(str::to_string),
+                sort_by,
+                start_after: start_after.map(str::to_string),
+                limit,
+            };
+            let res: PollsResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+            res.polls.into_iter().map(|poll| poll.address).collect::<Vec<_>>()
+        };
+        assert_eq!(list(None, None, None, None, None), vec!["poll5", "poll4", "poll3", "poll2", "poll1"]);
+        assert_eq!(list(None, None, Some(PollSortBy::EndTime), None, None), vec!["poll2", "poll3", "poll1", "poll4", "poll5"]);
+        assert_eq!(list(None, None, Some(PollSortBy::Tvl), None, None), vec!["poll2", "poll5", "poll4", "poll1", "poll3"]);
+
+        assert_eq!(list(Some(PollStatus::Active), None, None, None, None), vec!["poll5", "poll1"]);
+        assert_eq!(list(Some(PollStatus::Ended), None, None, None, None), vec!["poll2"]);
+        assert_eq!(list(Some(PollStatus::Resolved), None, None, None, None), vec!["poll3"]);
+        assert_eq!(list(Some(PollStatus::Cancelled), None, None, None, None), vec!["poll4"]);
+        assert_eq!(list(None, Some("alice"), Some(PollSortBy::EndTime), None, None), vec!["poll3", "poll1", "poll5"]);
+        assert_eq!(list(Some(PollStatus::Active), Some("alice"), Some(PollSortBy::Tvl), None, None), vec!["poll5", "poll1"]);
+
+        // Pages pick up after the sort key of the last poll, ties included
+        assert_eq!(list(None, None, Some(PollSortBy::Tvl), None, Some(2)), vec!["poll2", "poll5"]);
+        assert_eq!(list(None, None, Some(PollSortBy::Tvl), Some("poll5"), Some(2)), vec!["poll4", "poll1"]);
+        assert_eq!(list(None, None, Some(PollSortBy::EndTime), Some("poll1"), None), vec!["poll4", "poll5"]);
+        assert_eq!(list(None, None, None, Some("poll3"), None), vec!["poll2", "poll1"]);
+
+        // Status filters walk the same orders under the stored status
+        assert_eq!(list(Some(PollStatus::Active), None, Some(PollSortBy::EndTime), None, None), vec!["poll1", "poll5"]);
+        assert_eq!(list(Some(PollStatus::Ended), None, Some(PollSortBy::EndTime), None, None), vec!["poll2"]);
+        assert_eq!(list(Some(PollStatus::Active), None, Some(PollSortBy::EndTime), Some("poll1"), None), vec!["poll5"]);
+        assert_eq!(list(Some(PollStatus::Active), None, None, Some("poll5"), Some(1)), vec!["poll1"]);
+        assert_eq!(list(Some(PollStatus::Cancelled), None, Some(PollSortBy::Tvl), None, None), vec!["poll4"]);
+
+        // Polls still running, soonest ending first, ended ones waiting for resolution left out
+        let active = |deps: Deps, start_after: Option<&str>| {
+            let msg = QueryMsg::ListActivePolls {
+                start_after: start_after.map(str::to_string),
+                limit: None,
+            };
+            let res: Vec<PollResponse> = from_json(query(deps, env.clone(), msg).unwrap()).unwrap();
+            res.into_iter().map(|poll| poll.address).collect::<Vec<_>>()
+        };
+        assert_eq!(active(deps.as_ref(), None), vec!["poll1", "poll5"]);
+        assert_eq!(active(deps.as_ref(), Some("poll1")), vec!["poll5"]);
+
+        // A resolution moves the poll out of the active listing
+        let mut poll1 = polls().load(deps.as_ref().storage, &Addr::unchecked("poll1")).unwrap();
+        poll1.status = PollStatus::Resolved;
+        polls().save(deps.as_mut().storage, &Addr::unchecked("poll1"), &poll1).unwrap();
+        assert_eq!(active(deps.as_ref(), None), vec!["poll5"]);
+    }
+
+    #[test]
+    fn migrate_fills_in_poll_lifecycle() {
+        use xion_capypolls_poll::msg::PollInfoResponse;
+        use xion_capypolls_poll::state::{Collateral, ExitPenalty};
+
+        let mut deps = mock_dependencies_with_code_info();
+        let env = mock_env();
+        let init_msg = InstantiateMsg {
+            initial_fee: Uint128::new(1000000),
+            protocol_fee: 100,
+            poll_code_id: 1,
+            token_code_id: 2,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("owner", &[]), init_msg).unwrap();
+
+        // First-release entries and polls, binary ones with a token per side
+        for (index, poll) in ["poll1", "poll2"].into_iter().enumerate() {
+            let poll_addr = Addr::unchecked(poll);
+            let legacy = legacy::PollInfo {
+                creator: Addr::unchecked("creator"),
+                question: "Test Poll?".to_string(),
+                avatar: "avatar_url".to_string(),
+                description: "Test Description".to_string(),
+                yes_token: Addr::unchecked(format!("{poll}_yes")),
+                no_token: Addr::unchecked(format!("{poll}_no")),
+                poll_addr: poll_addr.clone(),
+            };
+            legacy::POLLS.save(deps.as_mut().storage, &poll_addr, &legacy).unwrap();
+            POLL_SEQUENCE.save(deps.as_mut().storage, index as u64, &poll_addr).unwrap();
+        }
+        MARKET_STATS
+            .update(deps.as_mut().storage, |mut stats| -> StdResult<_> {
+                stats.active_polls_count = 2;
+                Ok(stats)
+            })
+            .unwrap();
+        #[cosmwasm_schema::cw_serde]
+        struct LegacyPollInfoResponse {
+            end_timestamp: u64,
+            yes_token: Addr,
+            no_token: Addr,
+            total_staked: Uint128,
+            is_resolved: bool,
+            winning_position: Option<bool>,
+            denom: String,
+        }
+        // poll2 was migrated first and answers with the current response
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr == "poll1" => {
+                let info = LegacyPollInfoResponse {
+                    end_timestamp: 100,
+                    yes_token: Addr::unchecked("poll1_yes"),
+                    no_token: Addr::unchecked("poll1_no"),
+                    total_staked: Uint128::zero(),
+                    is_resolved: false,
+                    winning_position: None,
+                    denom: "uatom".to_string(),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&info).unwrap()))
+            }
+            WasmQuery::Smart { .. } => {
+                let info = PollInfoResponse {
+                    end_timestamp: 200,
+                    outcomes: vec![],
+                    total_staked: Uint128::zero(),
+                    is_resolved: true,
+                    winning_position: None,
+                    is_invalid: true,
+                    collateral: Collateral::Native {
+                        denom: XION_DENOM.to_string(),
+                    },
+                    oracle: None,
+                    optimistic: None,
+                    scalar: None,
+                    exit_penalty: ExitPenalty {
+                        start: Default::default(),
+                        end: Default::default(),
+                    },
+                    penalty_pot: Uint128::zero(),
+                    protocol_fee: Default::default(),
+                    sale: None,
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+
+        // A poll per page
+        let page = |start_after: Option<u64>| MigrateMsg {
+            start_after,
+            limit: Some(1),
+        };
+        let res = migrate(deps.as_mut(), env.clone(), page(None)).unwrap();
+        assert_eq!(res.attributes.last().unwrap(), ("next", "0"));
+        assert!(legacy::POLLS.may_load(deps.as_ref().storage, &Addr::unchecked("poll2")).unwrap().is_some());
+        let res = migrate(deps.as_mut(), env.clone(), page(Some(0))).unwrap();
+        assert!(res.attributes.iter().all(|attr| attr.key != "next"));
+
+        let poll1 = polls().load(deps.as_ref().storage, &Addr::unchecked("poll1")).unwrap();
+        assert_eq!(poll1.outcome_tokens, vec![Addr::unchecked("poll1_yes"), Addr::unchecked("poll1_no")]);
+        assert_eq!(poll1.denom, "uatom");
+        let poll2 = polls().load(deps.as_ref().storage, &Addr::unchecked("poll2")).unwrap();
+        assert_eq!((poll2.index, poll2.end_time, poll2.status), (1, 200, PollStatus::Cancelled));
+        assert_eq!(poll2.denom, XION_DENOM);
+        let msg = QueryMsg::ListPolls {
+            status: None,
+            creator: None,
+            sort_by: Some(PollSortBy::EndTime),
+            start_after: None,
+            limit: None,
+        };
+        let res: PollsResponse = from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.polls.iter().map(|poll| poll.address.as_str()).collect::<Vec<_>>(), vec!["poll1", "poll2"]);
+        let stats: MarketStats = from_json(query(deps.as_ref(), env, QueryMsg::GetMarketStats {}).unwrap()).unwrap();
+        assert_eq!((stats.active_polls_count, stats.resolved_polls_count), (1, 1));
+    }
+}
@@ -1,6 +1,8 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Uint128;
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Coin, Uint128};
+
+use xion_capypolls_poll::msg::{EpochScheduleInfo, ExitPenaltyInfo, OptimisticInfo, OracleInfo, PollEvent, SaleInfo, ScalarInfo};
+use xion_capypolls_poll::state::StakeLimits;
 
 use crate::state::MarketStats;
 
@@ -13,16 +15,29 @@ pub struct InstantiateMsg {
 }
 
 #[cw_serde]
+#[allow(clippy::large_enum_variant)]
 pub enum ExecuteMsg {
     CreatePoll {
         question: String,
         avatar: String,
         description: String,
         duration: u64,
-        yes_token_name: String,
-        yes_token_symbol: String,
-        no_token_name: String,
-        no_token_symbol: String,
+        /// Between 2 and 16 outcomes; a binary poll passes YES then NO
+        outcomes: Vec<OutcomeTokenInfo>,
+        oracle: Option<OracleInfo>,
+        optimistic: Option<OptimisticInfo>,
+        /// Scalar polls take exactly two outcomes, LONG then SHORT
+        scalar: Option<ScalarInfo>,
+        epoch_schedule: Option<EpochScheduleInfo>,
+        exit_penalty: Option<ExitPenaltyInfo>,
+        /// Falls back to the protocol's min and max stake on polls in the config denom
+        limits: Option<StakeLimits>,
+        /// Paid-list sale of the outcome tokens, priced in the poll's collateral
+        sale: Option<SaleInfo>,
+        /// Collateral of the poll, one of the whitelisted native denoms. Defaults to the config
+        /// denom. Polls created through core are always native; cw20 collateral is only available
+        /// when a poll is instantiated directly, since core collects and withdraws fees as bank coins.
+        denom: Option<String>,
     },
     UpdatePollCodeId {
         code_id: u64,
@@ -33,12 +48,71 @@ pub enum ExecuteMsg {
     SetInitialFee {
         new_fee: Uint128,
     },
+    /// Protocol fee in basis points, charged on every stake of polls created afterwards
     SetProtocolFee {
         new_fee: u64,
     },
+    /// Sends every creation and protocol fee collected so far to `to`
     WithdrawFees {
         to: String,
     },
+    UpdateResolvers {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Whitelists collateral denoms, IBC denoms included, with their creation fee
+    UpdateCollateralDenoms {
+        add: Vec<DenomFee>,
+        remove: Vec<String>,
+    },
+    ResolvePoll {
+        poll_address: String,
+        winning_position: u8,
+    },
+    ResolveScalarPoll {
+        poll_address: String,
+        value: Uint128,
+    },
+    InvalidatePoll {
+        poll_address: String,
+    },
+    /// Settles a disputed proposal on a poll whose arbiter is left to core. Owner only.
+    SettleDispute {
+        poll_address: String,
+        winning_position: u8,
+    },
+    /// Sent by a poll of this factory on the first stake of `user` in it
+    RecordParticipation {
+        user: String,
+    },
+    /// Sent by a poll of this factory when its collateral or lifecycle changes
+    ReportPollEvent {
+        event: PollEvent,
+    },
+}
+
+/// Polls are brought up to date in `POLL_SEQUENCE` order, a page per migration. Run it again
+/// with `start_after` set to the `next` attribute until that attribute is gone.
+#[cw_serde]
+#[derive(Default)]
+pub struct MigrateMsg {
+    #[serde(default)]
+    pub start_after: Option<u64>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct DenomFee {
+    pub denom: String,
+    pub creation_fee: Uint128,
+}
+
+#[cw_serde]
+pub struct OutcomeTokenInfo {
+    pub name: String,
+    pub token_name: String,
+    pub token_symbol: String,
 }
 
 #[cw_serde]
@@ -56,11 +130,100 @@ pub enum QueryMsg {
     GetPollDetails { poll_address: String },
     #[returns(MarketStats)]
     GetMarketStats {},
+    /// TVL and volume of every denom polls reported activity in
+    #[returns(DenomStatsResponse)]
+    GetDenomStats {},
+    /// Fees `WithdrawFees` would send, per denom
+    #[returns(CollectedFeesResponse)]
+    GetCollectedFees {},
+    #[returns(ResolversResponse)]
+    GetResolvers {},
+    /// Every denom polls can be created with, the config denom first
+    #[returns(CollateralDenomsResponse)]
+    GetCollateralDenoms {},
+    /// Current minter and owner of every outcome token of a poll
+    #[returns(MintAuthorityResponse)]
+    GetMintAuthority { poll_address: String },
+    /// Addresses a `CreatePoll` by `creator` will instantiate. `nonce` defaults to the
+    /// creator's next nonce and `num_outcomes` to a binary poll.
+    #[returns(PredictedAddressesResponse)]
+    PredictPollAddresses {
+        creator: String,
+        nonce: Option<u64>,
+        num_outcomes: Option<u8>,
+    },
+    /// Polls still open for staking, soonest ending first
     #[returns(Vec<PollResponse>)]
     ListActivePolls {
         start_after: Option<String>,
         limit: Option<u32>,
     },
+    /// Polls matching `status` and `creator`, in `sort_by` order. Defaults to newest first.
+    #[returns(PollsResponse)]
+    ListPolls {
+        status: Option<PollStatus>,
+        creator: Option<String>,
+        sort_by: Option<PollSortBy>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Polls `user` staked in, in address order
+    #[returns(UserPollsResponse)]
+    GetUserPolls {
+        user: String,
+        status_filter: Option<PollStatus>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum PollStatus {
+    #[default]
+    Active,
+    /// Past its end time and waiting for a resolution
+    Ended,
+    Resolved,
+    /// Settled as Invalid, every stake is refunded
+    Cancelled,
+}
+
+#[cw_serde]
+pub enum PollSortBy {
+    /// Soonest ending first
+    EndTime,
+    /// Newest first
+    Created,
+    /// Highest TVL first
+    Tvl,
+}
+
+#[cw_serde]
+pub struct PollsResponse {
+    pub polls: Vec<PollEntry>,
+}
+
+#[cw_serde]
+pub struct PollEntry {
+    pub address: String,
+    pub creator: String,
+    pub question: String,
+    pub denom: String,
+    pub status: PollStatus,
+    pub end_time: u64,
+    pub total_value_locked: Uint128,
+}
+
+#[cw_serde]
+pub struct UserPollsResponse {
+    pub polls: Vec<UserPollEntry>,
+}
+
+#[cw_serde]
+pub struct UserPollEntry {
+    pub address: String,
+    pub status: PollStatus,
 }
 
 #[cw_serde]
@@ -87,4 +250,53 @@ pub struct PollResponse {
 pub struct PollDetailsResponse {
     pub exists: bool,
     pub description: Option<String>,
-} 
\ No newline at end of file
+} 
+#[cw_serde]
+pub struct PredictedAddressesResponse {
+    pub nonce: u64,
+    /// In outcome order; a binary poll gets YES then NO
+    pub outcome_tokens: Vec<String>,
+    pub poll: String,
+}
+
+#[cw_serde]
+pub struct MintAuthorityResponse {
+    pub poll: String,
+    pub tokens: Vec<TokenAuthority>,
+    /// True once the poll is minter and owner of every outcome token
+    pub handed_over: bool,
+}
+
+#[cw_serde]
+pub struct TokenAuthority {
+    pub token: String,
+    pub minter: Option<String>,
+    pub owner: Option<String>,
+}
+
+#[cw_serde]
+pub struct CollateralDenomsResponse {
+    pub denoms: Vec<DenomFee>,
+}
+
+#[cw_serde]
+pub struct CollectedFeesResponse {
+    pub fees: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct DenomStatsResponse {
+    pub denoms: Vec<DenomStatsEntry>,
+}
+
+#[cw_serde]
+pub struct DenomStatsEntry {
+    pub denom: String,
+    pub total_value_locked: Uint128,
+    pub total_volume: Uint128,
+}
+
+#[cw_serde]
+pub struct ResolversResponse {
+    pub resolvers: Vec<String>,
+}
@@ -1,14 +1,21 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+use xion_capypolls_poll::msg::{EpochScheduleInfo, ExitPenaltyInfo, OptimisticInfo, OracleInfo, SaleInfo, ScalarInfo};
+use xion_capypolls_poll::state::StakeLimits;
+
+use crate::msg::{OutcomeTokenInfo, PollStatus};
 
 #[cw_serde]
 pub struct Config {
     pub owner: Addr,
+    #[serde(default)]
+    pub resolvers: Vec<Addr>,
     pub initial_fee: Uint128,
     pub protocol_fee: u64,
     pub poll_code_id: u64,
     pub token_code_id: u64,
+    /// Default collateral, always whitelisted with `initial_fee` as its creation fee
     pub denom: String,
 }
 
@@ -20,20 +27,121 @@ pub struct PollInfo {
     pub question: String,
     pub avatar: String,
     pub description: String,
-    pub yes_token: Addr,
-    pub no_token: Addr,
+    pub outcome_tokens: Vec<Addr>,
     pub poll_addr: Addr,
+    pub denom: String,
+    /// Position in creation order, see `POLL_SEQUENCE`
+    #[serde(default)]
+    pub index: u64,
+    #[serde(default)]
+    pub end_time: u64,
+    /// Active, Resolved or Cancelled. Ended is derived from `end_time` when read.
+    #[serde(default)]
+    pub status: PollStatus,
+    /// Stakes and bonds the poll holds, as reported through `ReportPollEvent`
+    #[serde(default)]
+    pub total_value_locked: Uint128,
+}
+
+impl PollInfo {
+    /// Lifecycle status at `now`
+    pub fn status_at(&self, now: u64) -> PollStatus {
+        match self.status {
+            PollStatus::Active if now >= self.end_time => PollStatus::Ended,
+            ref status => status.clone(),
+        }
+    }
+}
+
+/// Key of a stored status in the status indexes. Ended is never stored, such polls are kept
+/// as Active past their end time.
+pub fn status_key(status: &PollStatus) -> u8 {
+    match status {
+        PollStatus::Active | PollStatus::Ended => 0,
+        PollStatus::Resolved => 1,
+        PollStatus::Cancelled => 2,
+    }
+}
+
+pub struct PollIndexes<'a> {
+    pub created: MultiIndex<'a, u64, PollInfo, Addr>,
+    pub end_time: MultiIndex<'a, u64, PollInfo, Addr>,
+    pub tvl: MultiIndex<'a, u128, PollInfo, Addr>,
+    pub status_created: MultiIndex<'a, (u8, u64), PollInfo, Addr>,
+    pub status_end_time: MultiIndex<'a, (u8, u64), PollInfo, Addr>,
+    pub status_tvl: MultiIndex<'a, (u8, u128), PollInfo, Addr>,
+}
+
+impl<'a> IndexList<PollInfo> for PollIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PollInfo>> + '_> {
+        let v: Vec<&dyn Index<PollInfo>> = vec![
+            &self.created,
+            &self.end_time,
+            &self.tvl,
+            &self.status_created,
+            &self.status_end_time,
+            &self.status_tvl,
+        ];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Every poll of this factory, indexed by each `ListPolls` sort order, on its own and under
+/// the stored status
+pub fn polls<'a>() -> IndexedMap<'a, &'a Addr, PollInfo, PollIndexes<'a>> {
+    let indexes = PollIndexes {
+        created: MultiIndex::new(|_, poll| poll.index, "polls", "polls__created"),
+        end_time: MultiIndex::new(|_, poll| poll.end_time, "polls", "polls__end_time"),
+        tvl: MultiIndex::new(|_, poll| poll.total_value_locked.u128(), "polls", "polls__tvl"),
+        status_created: MultiIndex::new(
+            |_, poll| (status_key(&poll.status), poll.index),
+            "polls",
+            "polls__status_created",
+        ),
+        status_end_time: MultiIndex::new(
+            |_, poll| (status_key(&poll.status), poll.end_time),
+            "polls",
+            "polls__status_end_time",
+        ),
+        status_tvl: MultiIndex::new(
+            |_, poll| (status_key(&poll.status), poll.total_value_locked.u128()),
+            "polls",
+            "polls__status_tvl",
+        ),
+    };
+    IndexedMap::new("polls", indexes)
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
-pub const POLLS: Map<&Addr, PollInfo> = Map::new("polls");
 pub const POLL_COUNT: Item<u64> = Item::new("poll_count");
 pub const POLL_SEQUENCE: Map<u64, Addr> = Map::new("poll_sequence");
-pub const TEMP_POLL_DATA: Item<TempPollData> = Item::new("temp_poll_data");
-pub const REPLY_YES_TOKEN_INIT: u64 = 1;
-pub const REPLY_NO_TOKEN_INIT: u64 = 2;
+/// Extra collateral denoms polls may be created with, mapped to the creation fee in that denom
+pub const COLLATERAL_DENOMS: Map<&str, Uint128> = Map::new("collateral_denoms");
+/// In-flight creations keyed by pending id. An entry lives from `CreatePoll` until the poll
+/// instantiation reply; a failed step reverts the whole transaction, entry included.
+pub const PENDING_POLLS: Map<u64, TempPollData> = Map::new("pending_polls");
+pub const NEXT_PENDING_ID: Item<u64> = Item::new("next_pending_id");
+/// Next nonce per creator, mixed into the instantiate2 salts of their polls
+pub const CREATOR_NONCES: Map<&Addr, u64> = Map::new("creator_nonces");
 pub const REPLY_POLL_INIT: u64 = 3;
+/// Salt slot of the poll contract; outcome tokens use their index
+pub const SALT_POLL: u8 = u8::MAX;
+/// Low bits of a reply id carry the step, the rest carry the pending id
+const REPLY_KIND_BITS: u64 = 8;
+/// Users who staked in any poll of this factory
 pub const UNIQUE_PARTICIPANTS: Map<&Addr, bool> = Map::new("unique_participants");
+/// Polls each user staked in, keyed by `(user, poll)`
+pub const USER_POLLS: Map<(&Addr, &Addr), bool> = Map::new("user_polls");
+
+/// Encodes the pending creation and the step into one reply id
+pub fn encode_reply_id(pending_id: u64, kind: u64) -> u64 {
+    (pending_id << REPLY_KIND_BITS) | kind
+}
+
+/// Splits a reply id into `(pending_id, kind)`
+pub fn decode_reply_id(id: u64) -> (u64, u64) {
+    (id >> REPLY_KIND_BITS, id & ((1 << REPLY_KIND_BITS) - 1))
+}
 
 #[cw_serde]
 pub struct TempPollData {
@@ -41,19 +149,68 @@ pub struct TempPollData {
     pub question: String,
     pub avatar: String,
     pub description: String,
-    pub yes_token: Option<Addr>,
-    pub no_token: Option<Addr>,
+    pub outcomes: Vec<OutcomeTokenInfo>,
+    /// Predicted instantiate2 addresses, in the same order as `outcomes`
+    pub outcome_tokens: Vec<Addr>,
     pub poll_addr: Option<Addr>,
     pub duration: u64,
+    pub denom: String,
+    pub oracle: Option<OracleInfo>,
+    pub optimistic: Option<OptimisticInfo>,
+    pub scalar: Option<ScalarInfo>,
+    pub epoch_schedule: Option<EpochScheduleInfo>,
+    pub exit_penalty: Option<ExitPenaltyInfo>,
+    pub limits: Option<StakeLimits>,
+    pub sale: Option<SaleInfo>,
 }
 
 #[cw_serde]
 // #[derive(Default)]
 pub struct MarketStats {
+    /// Collateral held by polls in the config denom, see `DENOM_STATS` for the others
     pub total_value_locked: Uint128,
     pub active_polls_count: u64,
     pub total_polls_created: u64,
     pub total_unique_participants: u64,
+    /// Polls resolved or settled as Invalid
+    #[serde(default)]
+    pub resolved_polls_count: u64,
+    /// Collateral ever staked in the config denom
+    #[serde(default)]
+    pub total_volume: Uint128,
 }
 
-pub const MARKET_STATS: Item<MarketStats> = Item::new("market_stats"); 
\ No newline at end of file
+#[cw_serde]
+#[derive(Default)]
+pub struct DenomStats {
+    /// Stakes and bonds currently held by the polls
+    pub total_value_locked: Uint128,
+    /// Collateral ever staked
+    pub total_volume: Uint128,
+}
+
+pub const MARKET_STATS: Item<MarketStats> = Item::new("market_stats");
+/// Market stats per collateral denom, fed by the polls' `ReportPollEvent`
+pub const DENOM_STATS: Map<&str, DenomStats> = Map::new("denom_stats");
+/// Creation and protocol fees held for `WithdrawFees`, per denom
+pub const COLLECTED_FEES: Map<&str, Uint128> = Map::new("collected_fees"); 
+
+/// Storage layout of the first release, binary polls only, read by `migrate`
+pub mod legacy {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::Addr;
+    use cw_storage_plus::Map;
+
+    #[cw_serde]
+    pub struct PollInfo {
+        pub creator: Addr,
+        pub question: String,
+        pub avatar: String,
+        pub description: String,
+        pub yes_token: Addr,
+        pub no_token: Addr,
+        pub poll_addr: Addr,
+    }
+
+    pub const POLLS: Map<&Addr, PollInfo> = Map::new("polls");
+}
@@ -0,0 +1,69 @@
+use cosmwasm_std::{
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use cw2::set_contract_version;
+
+use crate::{
+    error::ContractError,
+    msg::{ExecuteMsg, InstantiateMsg, OutcomeResponse, QueryMsg},
+    state::{OUTCOMES, OWNER},
+};
+
+const CONTRACT_NAME: &str = "crates.io:xion-capypolls-mock-oracle";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    _msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    OWNER.save(deps.storage, &info.sender)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "instantiate")
+        .add_attribute("owner", info.sender))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let owner = OWNER.load(deps.storage)?;
+    if info.sender != owner {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    match msg {
+        ExecuteMsg::SetOutcome {
+            question_id,
+            outcome,
+        } => {
+            OUTCOMES.save(deps.storage, &question_id, &outcome)?;
+            Ok(Response::new()
+                .add_attribute("action", "set_outcome")
+                .add_attribute("question_id", question_id)
+                .add_attribute("outcome", outcome.to_string()))
+        }
+        ExecuteMsg::ClearOutcome { question_id } => {
+            OUTCOMES.remove(deps.storage, &question_id);
+            Ok(Response::new()
+                .add_attribute("action", "clear_outcome")
+                .add_attribute("question_id", question_id))
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Outcome { question_id } => to_json_binary(&OutcomeResponse {
+            outcome: OUTCOMES.may_load(deps.storage, &question_id)?,
+        }),
+    }
+}
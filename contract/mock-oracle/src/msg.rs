@@ -0,0 +1,28 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+
+#[cw_serde]
+pub struct InstantiateMsg {}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    SetOutcome {
+        question_id: String,
+        outcome: u8,
+    },
+    ClearOutcome {
+        question_id: String,
+    },
+}
+
+/// Mirrors the oracle interface queried by `xion_capypolls_poll` on `ResolveFromOracle`
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(OutcomeResponse)]
+    Outcome { question_id: String },
+}
+
+#[cw_serde]
+pub struct OutcomeResponse {
+    pub outcome: Option<u8>,
+}
@@ -0,0 +1,5 @@
+use cosmwasm_std::Addr;
+use cw_storage_plus::{Item, Map};
+
+pub const OWNER: Item<Addr> = Item::new("owner");
+pub const OUTCOMES: Map<&str, u8> = Map::new("outcomes");
@@ -1,34 +1,37 @@
 use cosmwasm_std::{
-    entry_point, to_binary, to_json_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, SubMsg, Uint128, WasmMsg
+    entry_point, from_json, to_json_binary, Addr, Binary, Coin, CosmosMsg, Decimal, Decimal256, Deps, DepsMut, Env, MessageInfo, Order, QuerierWrapper, Reply, Response, StdError, StdResult, Storage, SubMsg, Uint128, WasmMsg
 };
 use cw2::set_contract_version;
-use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, TokenInfoResponse};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, MinterResponse, TokenInfoResponse};
 
 use crate::{
     error::ContractError,
     msg::{
-        ActivitiesResponse, EpochInfoResponse, ExecuteMsg, InstantiateMsg, PollInfoResponse, QueryMsg, TotalStakedResponse, UserStakesResponse
+        ActivitiesResponse, ClaimableEpoch, ClaimableResponse, CollateralInfo, CoreExecuteMsg, CoreQueryMsg, EpochInfoResponse, ExitPenaltyInfo, EpochScheduleEntry, EpochScheduleInfo, EpochScheduleResponse, ExecuteMsg, InstantiateMsg, LimitsResponse, MigrateMsg, OracleQueryMsg, OutcomeResponse, PendingRewardsResponse, PollEvent, PollInfoResponse, PositionRewards, ProposalResponse, QueryMsg, ReceiveMsg, ScalarPayoutResponse, StakerEntry, SaleQuoteResponse, StakersResponse, TokenAllocationResponse, TotalStakedResponse, UserEpochRewards, UserPositionEntry, UserPositionResponse, UserStakesResponse
     },
     state::{
-        ActivityType, EpochInfo, PollActivity, PollConfig, Stake, ACTIVITIES, BATCH_SIZE, CURRENT_EPOCH, EPOCHS, EPOCH_DURATION, EPOCH_STAKERS, NUM_EPOCHS, POLL_CONFIG, TOTAL_NO_STAKED, TOTAL_YES_STAKED, USER_STAKES
+        allocation, legacy, ActivityType, Collateral, EpochInfo, CLAIMED_EPOCHS, CreatorVesting, EpochSchedule, PaidListSale, PriceCurve, ExitPenalty, OptimisticConfig, OracleConfig, Outcome, PollActivity, PollConfig, OutcomeRewards, Proposal, ProposalStatus, RewardIndex, ScalarConfig, Stake, StakeLimits, UserReward, ACTIVITY_COUNT, BATCH_SIZE, DEFAULT_EPOCH_DECAY_PERCENT, DEFAULT_EXIT_PENALTY_PERCENT, DEFAULT_NUM_EPOCHS, CREATOR_REWARD_PERCENTAGE, CREATOR_VESTING, DISTRIBUTION_PERCENTAGE, MINTED_REWARDS, LONG, MAX_EPOCHS, MAX_OUTCOMES, MAX_TOKEN_SUPPLY, MIN_OUTCOMES, PAID_LIST_PERCENTAGE, SHORT, CURRENT_EPOCH, EPOCHS, EPOCH_SCHEDULE, PENALTY_POT, POLL_CONFIG, PROPOSAL, REPLY_RECORD_PARTICIPATION, REPLY_REPORT_POLL_EVENT, OUTCOME_REWARDS, REWARD_INDEX, SALE_PURCHASES, SALE_SIDES, STAKE_COUNT, TOTAL_STAKED, activities, stakes, user_rewards
     },
 };
-use cw_storage_plus::Bound;
+use cw_storage_plus::{Bound, Item};
 
 const CONTRACT_NAME: &str = "crates.io:xion-capypolls-poll";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const MAX_DURATION: u64 = 30 * 24 * 60 * 60; // 30 days
-pub const MIN_DURATION: u64 = 1 * 24 * 60 * 60; // 1 day
+pub const MIN_DURATION: u64 = 24 * 60 * 60; // 1 day
 pub const MIN_STAKE_AMOUNT: u128 = 1_000_000; // 1 XION
 pub const MAX_STAKE_AMOUNT: u128 = 1_000_000_000_000_000; // 1M XION
+pub const RESOLUTION_GRACE_PERIOD: u64 = 7 * 24 * 60 * 60; // 7 days
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
+    _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     // Add token validation
     // let yes_token = deps.api.addr_validate(&msg.yes_token)?;
     // let no_token = deps.api.addr_validate(&msg.no_token)?;
@@ -51,38 +54,174 @@ pub fn instantiate(
         deps.api.addr_validate(&msg.poll_creator)?
     };
     
-    let yes_token = if cfg!(test) {
-        deps.api.addr_validate(&msg.yes_token).unwrap_or(Addr::unchecked(msg.yes_token.clone()))
-    } else {
-        deps.api.addr_validate(&msg.yes_token)?
-    };
-    
-    let no_token = if cfg!(test) {
-        deps.api.addr_validate(&msg.no_token).unwrap_or(Addr::unchecked(msg.no_token.clone()))
-    } else {
-        deps.api.addr_validate(&msg.no_token)?
+    if !(MIN_OUTCOMES..=MAX_OUTCOMES).contains(&msg.outcomes.len()) {
+        return Err(ContractError::InvalidOutcomeCount {
+            min: MIN_OUTCOMES,
+            max: MAX_OUTCOMES,
+        });
+    }
+
+    let outcomes = msg
+        .outcomes
+        .into_iter()
+        .map(|outcome| -> Result<_, ContractError> {
+            let token = if cfg!(test) {
+                deps.api.addr_validate(&outcome.token).unwrap_or(Addr::unchecked(outcome.token.clone()))
+            } else {
+                deps.api.addr_validate(&outcome.token)?
+            };
+            Ok(Outcome {
+                name: outcome.name,
+                token,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let collateral = match msg.collateral {
+        CollateralInfo::Native { denom } => Collateral::Native { denom },
+        CollateralInfo::Cw20 { address } => Collateral::Cw20 {
+            address: deps.api.addr_validate(&address)?,
+        },
     };
 
+    let oracle = msg
+        .oracle
+        .map(|oracle| -> Result<_, ContractError> {
+            Ok(OracleConfig {
+                address: deps.api.addr_validate(&oracle.address)?,
+                question_id: oracle.question_id,
+            })
+        })
+        .transpose()?;
+
+    let optimistic = msg
+        .optimistic
+        .map(|optimistic| -> Result<_, ContractError> {
+            Ok(OptimisticConfig {
+                bond: optimistic.bond,
+                challenge_period: optimistic.challenge_period,
+                arbiter: optimistic
+                    .arbiter
+                    .map(|arbiter| deps.api.addr_validate(&arbiter))
+                    .transpose()?,
+            })
+        })
+        .transpose()?;
+
+    let scalar = msg
+        .scalar
+        .map(|scalar| -> Result<_, ContractError> {
+            if scalar.lower_bound >= scalar.upper_bound {
+                return Err(ContractError::InvalidScalarBounds {});
+            }
+            // Oracles and proposals settle on an outcome index, scalar polls need a value
+            if outcomes.len() != 2 || oracle.is_some() || optimistic.is_some() {
+                return Err(ContractError::InvalidScalarOutcomes {});
+            }
+            Ok(ScalarConfig {
+                lower_bound: scalar.lower_bound,
+                upper_bound: scalar.upper_bound,
+                resolved_value: None,
+            })
+        })
+        .transpose()?;
+
+    let exit_penalty = msg.exit_penalty.unwrap_or(ExitPenaltyInfo {
+        start: Decimal::percent(DEFAULT_EXIT_PENALTY_PERCENT),
+        end: Decimal::percent(DEFAULT_EXIT_PENALTY_PERCENT),
+    });
+    if exit_penalty.start > Decimal::one() || exit_penalty.end > Decimal::one() {
+        return Err(ContractError::InvalidExitPenalty {});
+    }
+
+    let protocol_fee = msg.protocol_fee.unwrap_or_default();
+    if protocol_fee >= Decimal::one() {
+        return Err(ContractError::InvalidProtocolFee {});
+    }
+
+    let max_sale = allocation(PAID_LIST_PERCENTAGE);
+    let sale = msg
+        .sale
+        .map(|sale| -> Result<_, ContractError> {
+            let max_per_side = sale.max_per_side.unwrap_or(max_sale);
+            let valid_prices = match sale.curve {
+                PriceCurve::Flat { price } => !price.is_zero(),
+                PriceCurve::Linear { start_price, end_price } => !start_price.is_zero() && end_price >= start_price,
+            };
+            if !valid_prices || max_per_side.is_zero() || max_per_side > max_sale {
+                return Err(ContractError::InvalidSale { max: max_sale });
+            }
+            Ok(PaidListSale {
+                curve: sale.curve,
+                max_per_side,
+            })
+        })
+        .transpose()?;
+
+    let limits = msg.limits.unwrap_or_default();
+    let maximums = [limits.max_stake, limits.max_user_total, limits.max_side_total];
+    if maximums.iter().flatten().any(Uint128::is_zero)
+        || matches!((limits.min_stake, limits.max_stake), (Some(min), Some(max)) if min > max)
+    {
+        return Err(ContractError::InvalidStakeLimits {});
+    }
+
     // Set up poll config
     let config = PollConfig {
         capy_core,
         poll_creator,
-        yes_token,
-        no_token,
+        outcomes,
         end_timestamp: env.block.time.seconds() + msg.duration,
         total_staked: Uint128::zero(),
         is_resolved: false,
         winning_position: None,
-        denom: msg.denom.clone(),
+        is_invalid: false,
+        collateral,
+        oracle,
+        optimistic,
+        scalar,
+        exit_penalty: ExitPenalty {
+            start: exit_penalty.start,
+            end: exit_penalty.end,
+        },
+        limits,
+        protocol_fee,
+        sale,
     };
 
     // Save config
     POLL_CONFIG.save(deps.storage, &config)?;
+    PENALTY_POT.save(deps.storage, &Uint128::zero())?;
+    for position in 0..config.outcomes.len() as u8 {
+        TOTAL_STAKED.save(deps.storage, position, &Uint128::zero())?;
+    }
 
-    // Initialize other state
+    // Lay out every epoch up front so staking never hits a missing record
+    let schedule = epoch_schedule(msg.epoch_schedule, env.block.time.seconds(), msg.duration)?;
+    save_epochs(deps.storage, &schedule, config.end_timestamp)?;
+    EPOCH_SCHEDULE.save(deps.storage, &schedule)?;
+    CREATOR_VESTING.save(
+        deps.storage,
+        &CreatorVesting {
+            start_time: env.block.time.seconds(),
+            end_time: config.end_timestamp,
+            total: allocation(CREATOR_REWARD_PERCENTAGE),
+            claimed: Uint128::zero(),
+        },
+    )?;
     CURRENT_EPOCH.save(deps.storage, &1u64)?;
-    EPOCH_DURATION.save(deps.storage, &(msg.duration / 4))?; // 4 epochs
-    NUM_EPOCHS.save(deps.storage, &4u64)?;
+    STAKE_COUNT.save(deps.storage, &0u64)?;
+    ACTIVITY_COUNT.save(deps.storage, &0u64)?;
+    MINTED_REWARDS.save(deps.storage, &Uint128::zero())?;
+    REWARD_INDEX.save(
+        deps.storage,
+        &RewardIndex {
+            index: Decimal256::zero(),
+            last_update: schedule.start_time,
+            total_staked: Uint128::zero(),
+            epoch_ends: vec![],
+        },
+    )?;
 
     Ok(Response::new()
         .add_attribute("action", "instantiate")
@@ -90,16 +229,59 @@ pub fn instantiate(
         .add_attribute("poll_creator", msg.poll_creator))
 }
 
-fn validate_contract_address(deps: Deps, addr: &str) -> Result<Addr, ContractError> {
-    let addr = deps.api.addr_validate(addr)
-        .map_err(|_| ContractError::InvalidAddress(addr.to_string()))?;
-    
-    // Check if address has code
-    if deps.querier.query_wasm_smart::<bool>(&addr, &"{}").is_err() {
-        return Err(ContractError::AddressEmptyCode(addr.to_string()));
+/// Saves a fresh record for every epoch of `schedule`, the last one running until `end_timestamp`
+fn save_epochs(storage: &mut dyn Storage, schedule: &EpochSchedule, end_timestamp: u64) -> StdResult<()> {
+    let distributions = schedule.distributions()?;
+    for (epoch_number, total_distribution) in (1..=schedule.num_epochs).zip(distributions) {
+        let start_time = schedule.start_time + (epoch_number - 1) * schedule.epoch_duration;
+        let end_time = if epoch_number == schedule.num_epochs {
+            end_timestamp
+        } else {
+            start_time + schedule.epoch_duration
+        };
+        let epoch = EpochInfo {
+            start_time,
+            end_time,
+            total_distribution,
+            is_distributed: false,
+            total_epoch_staked: Uint128::zero(),
+            last_processed_index: 0,
+            num_stakers: 0,
+        };
+        EPOCHS.save(storage, epoch_number, &epoch)?;
     }
-    
-    Ok(addr)
+    Ok(())
+}
+
+fn epoch_schedule(
+    info: Option<EpochScheduleInfo>,
+    start_time: u64,
+    duration: u64,
+) -> Result<EpochSchedule, ContractError> {
+    let info = info.unwrap_or(EpochScheduleInfo {
+        num_epochs: DEFAULT_NUM_EPOCHS,
+        decay: Decimal::percent(DEFAULT_EPOCH_DECAY_PERCENT),
+        total_rewards: None,
+    });
+    if info.num_epochs == 0 || info.num_epochs > MAX_EPOCHS || duration < info.num_epochs {
+        return Err(ContractError::InvalidEpochCount { max: MAX_EPOCHS });
+    }
+    if info.decay.is_zero() || info.decay > Decimal::one() {
+        return Err(ContractError::InvalidEpochDecay {});
+    }
+    let max = allocation(DISTRIBUTION_PERCENTAGE);
+    let total_rewards = info.total_rewards.unwrap_or(max);
+    if total_rewards > max {
+        return Err(ContractError::EpochRewardsAboveAllocation { max });
+    }
+
+    Ok(EpochSchedule {
+        start_time,
+        num_epochs: info.num_epochs,
+        epoch_duration: duration / info.num_epochs,
+        decay: info.decay,
+        total_rewards,
+    })
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -115,9 +297,55 @@ pub fn execute(
             execute_distribute_epoch_rewards(deps, env, epoch_number)
         }
         ExecuteMsg::WithdrawStake {} => execute_withdraw_stake(deps, env, info),
+        ExecuteMsg::ClaimRewards {} => execute_claim_rewards(deps, env, info),
+        ExecuteMsg::ClaimEpochRewards { epochs } => execute_claim_epoch_rewards(deps, env, info, epochs),
+        ExecuteMsg::Unstake { amount, position } => execute_unstake(deps, env, info, amount, position),
+        ExecuteMsg::SwitchPosition { amount, from, to } => {
+            execute_switch_position(deps, env, info, amount, from, to)
+        }
         ExecuteMsg::ResolvePoll { winning_position } => {
             execute_resolve_poll(deps, env, info, winning_position)
         }
+        ExecuteMsg::ResolveScalar { value } => execute_resolve_scalar(deps, env, info, value),
+        ExecuteMsg::ResolveFromOracle {} => execute_resolve_from_oracle(deps, env),
+        ExecuteMsg::ProposeOutcome { outcome } => execute_propose_outcome(deps, env, info, outcome),
+        ExecuteMsg::DisputeOutcome {} => execute_dispute_outcome(deps, env, info),
+        ExecuteMsg::FinalizeOutcome {} => execute_finalize_outcome(deps, env),
+        ExecuteMsg::SettleDispute { winning_position } => {
+            execute_settle_dispute(deps, env, info, winning_position)
+        }
+        ExecuteMsg::InvalidatePoll {} => execute_invalidate_poll(deps, env, info),
+        ExecuteMsg::ClaimCreatorRewards {} => execute_claim_creator_rewards(deps, env, info),
+        ExecuteMsg::BuyTokens { position, amount } => execute_buy_tokens(deps, env, info, position, amount),
+        ExecuteMsg::SellTokens { position, amount } => execute_sell_tokens(deps, env, info, position, amount),
+        ExecuteMsg::RedeemPrize { position, amount } => execute_redeem_prize(deps, env, info, position, amount),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+    }
+}
+
+/// Handles cw20 collateral sent to the poll; `info.sender` is the token contract
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    match &config.collateral {
+        Collateral::Cw20 { address } if *address == info.sender => {}
+        _ => return Err(ContractError::InvalidCollateral {}),
+    }
+    let sender = deps.api.addr_validate(&wrapper.sender)?;
+
+    match from_json(&wrapper.msg)? {
+        ReceiveMsg::Stake { position } => stake(deps, env, config, sender, wrapper.amount, position),
+        ReceiveMsg::ProposeOutcome { outcome } => {
+            propose_outcome(deps, env, config, sender, wrapper.amount, outcome)
+        }
+        ReceiveMsg::DisputeOutcome {} => dispute_outcome(deps, env, config, sender, wrapper.amount),
+        ReceiveMsg::BuyTokens { position, amount } => {
+            buy_tokens(deps, env, config, sender, wrapper.amount, position, amount)
+        }
     }
 }
 
@@ -126,9 +354,33 @@ pub fn execute_stake(
     env: Env,
     info: MessageInfo,
     amount: Uint128,
-    position: bool,
+    position: u8,
 ) -> Result<Response, ContractError> {
     let config = POLL_CONFIG.load(deps.storage)?;
+    config.outcome_token(position)?;
+
+    // Validate native payment
+    let paid = config.collateral.native_payment(&info.funds)?;
+    if paid.is_zero() {
+        return Err(ContractError::NoXionPayment {});
+    }
+    if paid != amount {
+        return Err(ContractError::InvalidPaymentAmount {});
+    }
+
+    stake(deps, env, config, info.sender, amount, position)
+}
+
+/// Records a stake whose collateral has already been received
+fn stake(
+    deps: DepsMut,
+    env: Env,
+    config: PollConfig,
+    staker: Addr,
+    amount: Uint128,
+    position: u8,
+) -> Result<Response, ContractError> {
+    config.outcome_token(position)?;
     
     // Check if poll is still active
     if env.block.time.seconds() >= config.end_timestamp {
@@ -138,81 +390,307 @@ pub fn execute_stake(
         return Err(ContractError::PollAlreadyResolved {});
     }
 
-    // Validate XION payment
-    let payment = info.funds
-        .iter()
-        .find(|coin| coin.denom == "uxion")
-        .ok_or(ContractError::NoXionPayment {})?;
-
-    if payment.amount != amount {
-        return Err(ContractError::InvalidPaymentAmount {});
-    }
-
     // Calculate current epoch
-    let epoch_duration = EPOCH_DURATION.load(deps.storage)?;
-    let elapsed_time = env.block.time.seconds() - (config.end_timestamp - (epoch_duration * 4));
-    let current_epoch = (elapsed_time / epoch_duration) + 1;
+    let schedule = EPOCH_SCHEDULE.load(deps.storage)?;
+    let current_epoch = schedule.epoch_at(env.block.time.seconds());
     CURRENT_EPOCH.save(deps.storage, &current_epoch)?;
 
-    // Update total staked amounts
-    if position {
-        let total_yes = TOTAL_YES_STAKED.load(deps.storage)?;
-        TOTAL_YES_STAKED.save(deps.storage, &(total_yes + amount))?;
+    let limits = &config.limits;
+    if let Some(min) = limits.min_stake.filter(|min| amount < *min) {
+        return Err(ContractError::StakeBelowMinimum { min });
+    }
+    if let Some(max) = limits.max_stake.filter(|max| amount > *max) {
+        return Err(ContractError::StakeAboveMaximum { max });
+    }
+    // The protocol fee comes off the top, only the rest is staked
+    let fee = if pays_protocol_fee(&deps.querier, &env, &config) {
+        amount.mul_floor(config.protocol_fee)
     } else {
-        let total_no = TOTAL_NO_STAKED.load(deps.storage)?;
-        TOTAL_NO_STAKED.save(deps.storage, &(total_no + amount))?;
+        Uint128::zero()
+    };
+    let staked = amount - fee;
+    if let Some(max) = limits.max_user_total {
+        if user_total(deps.storage, &staker)? + staked > max {
+            return Err(ContractError::UserStakeLimit { max });
+        }
+    }
+    check_side_limit(deps.storage, limits, position, staked)?;
+
+    let mut messages = participation_hook(deps.storage, &config, &staker)?;
+    messages.push(report_event(&config, PollEvent::Staked { amount: staked })?);
+    if !fee.is_zero() {
+        messages.push(fee_msg(&config, fee)?);
+    }
+
+    let mut rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    add_stake(deps.storage, &mut rewards, &staker, staked, position, current_epoch)?;
+    REWARD_INDEX.save(deps.storage, &rewards)?;
+    record_activity(deps.storage, &env, &staker, ActivityType::Stake, staked, position)?;
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "stake")
+        .add_attribute("user", staker)
+        .add_attribute("amount", staked)
+        .add_attribute("fee", fee)
+        .add_attribute("position", position.to_string())
+        .add_attribute("epoch", current_epoch.to_string()))
+}
+
+fn check_side_limit(
+    storage: &dyn Storage,
+    limits: &StakeLimits,
+    position: u8,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    if let Some(max) = limits.max_side_total {
+        if TOTAL_STAKED.load(storage, position)? + amount > max {
+            return Err(ContractError::SideStakeLimit { position, max });
+        }
+    }
+    Ok(())
+}
+
+/// Active stake of `user` across every position
+fn user_total(storage: &dyn Storage, user: &Addr) -> StdResult<Uint128> {
+    let mut total = Uint128::zero();
+    for item in stakes().idx.user.sub_prefix(user.clone()).range(storage, None, None, Order::Ascending) {
+        let (_, stake) = item?;
+        if !stake.withdrawn {
+            total += stake.amount;
+        }
+    }
+    Ok(total)
+}
+
+/// Core keeps the list of polls each user is in, so it hears about the first activity of `user`
+fn participation_hook(storage: &dyn Storage, config: &PollConfig, user: &Addr) -> StdResult<Vec<SubMsg>> {
+    if has_activity(storage, user) {
+        return Ok(vec![]);
     }
+    let hook = WasmMsg::Execute {
+        contract_addr: config.capy_core.to_string(),
+        msg: to_json_binary(&CoreExecuteMsg::RecordParticipation {
+            user: user.to_string(),
+        })?,
+        funds: vec![],
+    };
+    Ok(vec![SubMsg::reply_on_error(hook, REPLY_RECORD_PARTICIPATION)])
+}
+
+/// Whether `user` did anything in this poll before
+fn has_activity(storage: &dyn Storage, user: &Addr) -> bool {
+    activities()
+        .idx
+        .user
+        .prefix(user.clone())
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+/// Whether `user` still holds a stake placed in `epoch`
+fn has_epoch_stake(storage: &dyn Storage, user: &Addr, epoch: u64) -> bool {
+    stakes()
+        .idx
+        .user
+        .prefix((user.clone(), epoch))
+        .keys_raw(storage, None, None, Order::Ascending)
+        .next()
+        .is_some()
+}
+
+/// Bumps the id counter `count` and returns the new id
+fn next_id(storage: &mut dyn Storage, count: &Item<u64>) -> StdResult<u64> {
+    let id = count.load(storage)? + 1;
+    count.save(storage, &id)?;
+    Ok(id)
+}
+
+/// Reward index brought up to `now`, capped at the end of the poll
+fn load_rewards(storage: &dyn Storage, config: &PollConfig, now: u64) -> StdResult<RewardIndex> {
+    let mut rewards = REWARD_INDEX.load(storage)?;
+    let epochs = EPOCHS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, epoch)| epoch))
+        .collect::<StdResult<Vec<_>>>()?;
+    rewards.advance(&epochs, now.min(config.end_timestamp))?;
+    Ok(rewards)
+}
 
-    // Update user stakes for current epoch
-    let mut stakes = USER_STAKES
-        .may_load(deps.storage, (&info.sender, current_epoch))?
+/// Reward position of `user` on `position`, settled against `rewards`
+fn load_user_reward(
+    storage: &dyn Storage,
+    rewards: &RewardIndex,
+    user: &Addr,
+    position: u8,
+) -> StdResult<UserReward> {
+    let mut reward = user_rewards()
+        .may_load(storage, (user, position))?
         .unwrap_or_default();
-    
-    stakes.push(Stake {
+    reward.settle(rewards)?;
+    Ok(reward)
+}
+
+/// Rewards of every staker of `position` together, settled against `rewards`. Must be loaded
+/// before the position's total stake changes.
+fn load_outcome_rewards(storage: &dyn Storage, rewards: &RewardIndex, position: u8) -> StdResult<OutcomeRewards> {
+    let mut aggregate = OUTCOME_REWARDS.may_load(storage, position)?.unwrap_or_default();
+    aggregate.settle(TOTAL_STAKED.load(storage, position)?, rewards)?;
+    Ok(aggregate)
+}
+
+/// Adds a stake to `epoch` and to every running total
+fn add_stake(
+    storage: &mut dyn Storage,
+    rewards: &mut RewardIndex,
+    staker: &Addr,
+    amount: Uint128,
+    position: u8,
+    epoch_number: u64,
+) -> StdResult<()> {
+    // Update total staked amounts
+    let aggregate = load_outcome_rewards(storage, rewards, position)?;
+    OUTCOME_REWARDS.save(storage, position, &aggregate)?;
+    TOTAL_STAKED.update(storage, position, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() + amount)
+    })?;
+
+    // Update epoch info, counting the staker if this is their first stake in the epoch
+    let mut epoch = EPOCHS.load(storage, epoch_number)?;
+    epoch.total_epoch_staked += amount;
+    if !has_epoch_stake(storage, staker, epoch_number) {
+        epoch.num_stakers += 1;
+    }
+    EPOCHS.save(storage, epoch_number, &epoch)?;
+
+    let id = next_id(storage, &STAKE_COUNT)?;
+    let stake = Stake {
+        user: staker.clone(),
+        epoch: epoch_number,
         amount,
         position,
         withdrawn: false,
-    });
-    
-    USER_STAKES.save(deps.storage, (&info.sender, current_epoch), &stakes)?;
+    };
+    stakes().save(storage, id, &stake)?;
+
+    // Start earning from now on
+    let mut reward = load_user_reward(storage, rewards, staker, position)?;
+    reward.staked += amount;
+    user_rewards().save(storage, (staker, position), &reward)?;
+    rewards.total_staked += amount;
+    Ok(())
+}
 
-    // Update epoch stakers if this is their first stake in the epoch
-    let mut stakers = EPOCH_STAKERS
-        .may_load(deps.storage, current_epoch)?
-        .unwrap_or_default();
-    
-    if !stakers.contains(&info.sender) {
-        stakers.push(info.sender.clone());
-        EPOCH_STAKERS.save(deps.storage, current_epoch, &stakers)?;
+/// Takes `amount` off the staker's `position`, newest stakes first. Rewards the amount earned
+/// so far are forfeited, unclaimed ones first; returns the claimed outcome tokens to burn.
+fn remove_stake(
+    storage: &mut dyn Storage,
+    rewards: &mut RewardIndex,
+    staker: &Addr,
+    amount: Uint128,
+    position: u8,
+) -> Result<Uint128, ContractError> {
+    let stakes = stakes();
+    let open = stakes
+        .idx
+        .user
+        .sub_prefix(staker.clone())
+        .range(storage, None, None, Order::Descending)
+        .filter(|item| match item {
+            Ok((_, stake)) => stake.position == position && !stake.withdrawn,
+            Err(_) => true,
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut remaining = amount;
+    for (id, mut stake) in open {
+        if remaining.is_zero() {
+            break;
+        }
+        let take = stake.amount.min(remaining);
+        remaining -= take;
+
+        let mut epoch = EPOCHS.load(storage, stake.epoch)?;
+        epoch.total_epoch_staked -= take;
+        if take == stake.amount {
+            stakes.remove(storage, id)?;
+            if !has_epoch_stake(storage, staker, stake.epoch) {
+                epoch.num_stakers -= 1;
+            }
+        } else {
+            stake.amount -= take;
+            stakes.save(storage, id, &stake)?;
+        }
+        EPOCHS.save(storage, stake.epoch, &epoch)?;
+    }
+    if !remaining.is_zero() {
+        return Err(ContractError::InsufficientStake {
+            available: amount - remaining,
+        });
     }
 
-    // Update epoch info
-    let mut epoch = EPOCHS.load(deps.storage, current_epoch)?;
-    epoch.total_epoch_staked += amount;
-    EPOCHS.save(deps.storage, current_epoch, &epoch)?;
+    let mut aggregate = load_outcome_rewards(storage, rewards, position)?;
+    TOTAL_STAKED.update(storage, position, |total| -> StdResult<_> {
+        Ok(total.unwrap_or_default() - amount)
+    })?;
+
+    let mut reward = load_user_reward(storage, rewards, staker, position)?;
+    let pending = reward.total_pending();
+    let burned = reward.unstake(amount);
+    user_rewards().save(storage, (staker, position), &reward)?;
+    aggregate.pending = aggregate.pending.saturating_sub(pending - reward.total_pending());
+    OUTCOME_REWARDS.save(storage, position, &aggregate)?;
+    rewards.total_staked -= amount;
+    MINTED_REWARDS.update(storage, |total| -> StdResult<_> { Ok(total.saturating_sub(burned)) })?;
+    Ok(burned)
+}
 
-    // Record activity
+fn record_activity(
+    storage: &mut dyn Storage,
+    env: &Env,
+    user: &Addr,
+    activity_type: ActivityType,
+    amount: Uint128,
+    position: u8,
+) -> StdResult<()> {
     let activity = PollActivity {
-        user: info.sender.clone(),
-        activity_type: ActivityType::Stake,
+        user: user.clone(),
+        activity_type,
         amount: Some(amount),
         position: Some(position),
         timestamp: env.block.time.seconds(),
         block_height: env.block.height,
     };
 
-    ACTIVITIES.update(deps.storage, env.block.height, |existing| -> StdResult<_> {
-        let mut activities = existing.unwrap_or_default();
-        activities.push(activity);
-        Ok(activities)
-    })?;
+    let id = next_id(storage, &ACTIVITY_COUNT)?;
+    activities().save(storage, id, &activity)
+}
 
-    Ok(Response::new()
-        .add_attribute("action", "stake")
-        .add_attribute("user", info.sender)
-        .add_attribute("amount", amount)
-        .add_attribute("position", position.to_string())
-        .add_attribute("epoch", current_epoch.to_string()))
+/// Pulls `amount` of `token` back from `owner` and burns it. The poll owns its outcome tokens,
+/// but the owner-only `Burn` only touches the poll's own balance, hence the allowance.
+fn burn_from_msgs(token: &Addr, owner: &Addr, env: &Env, amount: Uint128) -> StdResult<Vec<CosmosMsg>> {
+    if amount.is_zero() {
+        return Ok(vec![]);
+    }
+    Ok(vec![
+        WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::TransferFrom {
+                owner: owner.to_string(),
+                recipient: env.contract.address.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+        WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_json_binary(&Cw20ExecuteMsg::Burn { amount })?,
+            funds: vec![],
+        }
+        .into(),
+    ])
 }
 
 pub fn execute_withdraw_stake(
@@ -225,58 +703,172 @@ pub fn execute_withdraw_stake(
         return Err(ContractError::PollNotResolved {});
     }
 
-    let current_epoch = CURRENT_EPOCH.load(deps.storage)?;
-    let mut total_to_withdraw = Uint128::zero();
-
-    for epoch_num in 1..=current_epoch {
-        let mut stakes = USER_STAKES
-            .may_load(deps.storage, (&info.sender, epoch_num))?
-            .unwrap_or_default();
-        let mut updated = false;
-
-        for stake in &mut stakes {
-            if !stake.withdrawn {
-                total_to_withdraw += stake.amount;
-                stake.withdrawn = true;
-                updated = true;
-            }
-        }
-
-        if updated {
-            USER_STAKES.save(deps.storage, (&info.sender, epoch_num), &stakes)?;
-        }
+    let stakes = stakes();
+    let open = stakes
+        .idx
+        .user
+        .sub_prefix(info.sender.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, stake)) if stake.withdrawn))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut withdrawn = vec![Uint128::zero(); config.outcomes.len()];
+    for (id, mut stake) in open {
+        withdrawn[stake.position as usize] += stake.amount;
+        stake.withdrawn = true;
+        stakes.save(deps.storage, id, &stake)?;
     }
 
+    let mut total_to_withdraw = withdrawn.iter().sum::<Uint128>();
     if total_to_withdraw.is_zero() {
         return Err(ContractError::NoStakesToWithdraw {});
     }
 
-    // Send XION tokens back to user
-    //let msg = BankMsg::Send {
-    let bank_msg = BankMsg::Send {
-        to_address: info.sender.to_string(),
-        amount: vec![Coin {
-            //denom: "uxion".to_string(),
-            denom: config.denom,
-            amount: total_to_withdraw,
-        }],
-    };
+    // Withdrawn stake keeps what it earned but stops earning
+    let mut rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    for (position, amount) in withdrawn.into_iter().enumerate().filter(|(_, amount)| !amount.is_zero()) {
+        let mut reward = load_user_reward(deps.storage, &rewards, &info.sender, position as u8)?;
+        reward.staked -= amount;
+        user_rewards().save(deps.storage, (&info.sender, position as u8), &reward)?;
+        rewards.total_staked -= amount;
+    }
+    REWARD_INDEX.save(deps.storage, &rewards)?;
+
+    // Stakes are frozen once resolved, so every remaining staker gets a fixed share of the pot
+    let pot = PENALTY_POT.load(deps.storage)?;
+    if !pot.is_zero() {
+        let total_staked = (0..config.outcomes.len() as u8)
+            .map(|position| TOTAL_STAKED.load(deps.storage, position))
+            .sum::<StdResult<Uint128>>()?;
+        total_to_withdraw += pot.multiply_ratio(total_to_withdraw, total_staked);
+    }
+
+    // Send the collateral back to user
+    let transfer_msg = config.collateral.transfer_msg(&info.sender, total_to_withdraw)?;
     // Ok(Response::new()
     // .add_message(msg)
     // .add_attribute("action", "withdraw_stake")
     // .add_attribute("user", info.sender)
     // .add_attribute("amount", total_to_withdraw))
 
-    Ok(Response::new().add_message(bank_msg))
+    Ok(Response::new()
+        .add_message(transfer_msg)
+        .add_submessage(report_event(&config, PollEvent::Withdrawn { amount: total_to_withdraw })?))
+}
+
+/// Takes `amount` off the sender's `position` before the poll ends, see `remove_stake`.
+/// The exit penalty of the current epoch goes to the penalty pot, and outcome tokens the
+/// amount already earned are pulled back from the sender and burned.
+pub fn execute_unstake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    position: u8,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let token = config.outcome_token(position)?.clone();
+    if env.block.time.seconds() >= config.end_timestamp {
+        return Err(ContractError::PollEnded {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidPaymentAmount {});
+    }
+
+    let schedule = EPOCH_SCHEDULE.load(deps.storage)?;
+    let current_epoch = schedule.epoch_at(env.block.time.seconds());
+    CURRENT_EPOCH.save(deps.storage, &current_epoch)?;
+
+    let mut rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    let earned = remove_stake(deps.storage, &mut rewards, &info.sender, amount, position)?;
+    REWARD_INDEX.save(deps.storage, &rewards)?;
+    let penalty = amount.mul_floor(config.exit_penalty.rate(current_epoch, schedule.num_epochs));
+    PENALTY_POT.update(deps.storage, |pot| -> StdResult<_> { Ok(pot + penalty) })?;
+
+    record_activity(deps.storage, &env, &info.sender, ActivityType::Unstake, amount, position)?;
+
+    let mut messages = burn_from_msgs(&token, &info.sender, &env, earned)?;
+    let payout = amount - penalty;
+    let mut reports = vec![];
+    if !payout.is_zero() {
+        messages.push(config.collateral.transfer_msg(&info.sender, payout)?);
+        reports.push(report_event(&config, PollEvent::Withdrawn { amount: payout })?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(reports)
+        .add_attribute("action", "unstake")
+        .add_attribute("user", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("position", position.to_string())
+        .add_attribute("epoch", current_epoch.to_string())
+        .add_attribute("penalty", penalty)
+        .add_attribute("tokens_burned", earned))
+}
+
+/// Moves `amount` of stake from `from` to `to` without an exit penalty. The moved amount
+/// counts as a fresh stake in the current epoch, and outcome tokens it earned on the old side
+/// are pulled back and burned like on `Unstake`.
+pub fn execute_switch_position(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    amount: Uint128,
+    from: u8,
+    to: u8,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let token = config.outcome_token(from)?.clone();
+    config.outcome_token(to)?;
+    if from == to {
+        return Err(ContractError::SamePosition {});
+    }
+    if env.block.time.seconds() >= config.end_timestamp {
+        return Err(ContractError::PollEnded {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidPaymentAmount {});
+    }
+
+    let schedule = EPOCH_SCHEDULE.load(deps.storage)?;
+    let current_epoch = schedule.epoch_at(env.block.time.seconds());
+    CURRENT_EPOCH.save(deps.storage, &current_epoch)?;
+
+    let mut rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    let earned = remove_stake(deps.storage, &mut rewards, &info.sender, amount, from)?;
+    check_side_limit(deps.storage, &config.limits, to, amount)?;
+    add_stake(deps.storage, &mut rewards, &info.sender, amount, to, current_epoch)?;
+    REWARD_INDEX.save(deps.storage, &rewards)?;
+    record_activity(deps.storage, &env, &info.sender, ActivityType::Switch, amount, to)?;
+
+    Ok(Response::new()
+        .add_messages(burn_from_msgs(&token, &info.sender, &env, earned)?)
+        .add_attribute("action", "switch_position")
+        .add_attribute("user", info.sender)
+        .add_attribute("amount", amount)
+        .add_attribute("from", from.to_string())
+        .add_attribute("to", to.to_string())
+        .add_attribute("epoch", current_epoch.to_string())
+        .add_attribute("tokens_burned", earned))
 }
 
+/// Claims the rewards accrued so far by a batch of the epoch's stakers, once the epoch ended.
+/// Deprecated in favour of `ClaimRewards` and `ClaimEpochRewards`.
 pub fn execute_distribute_epoch_rewards(
     deps: DepsMut,
     env: Env,
     epoch_number: u64,
 ) -> Result<Response, ContractError> {
-    let current_epoch = CURRENT_EPOCH.load(deps.storage)?;
-    if epoch_number > current_epoch {
+    // `CURRENT_EPOCH` only moves on stakes, so it lags behind after a quiet period
+    let schedule = EPOCH_SCHEDULE.load(deps.storage)?;
+    if epoch_number > schedule.epoch_at(env.block.time.seconds()) {
         return Err(ContractError::EpochNotStarted {});
     }
 
@@ -288,168 +880,1564 @@ pub fn execute_distribute_epoch_rewards(
         return Err(ContractError::EpochNotEnded {});
     }
 
-    let stakers = EPOCH_STAKERS
-        .may_load(deps.storage, epoch_number)?
-        .unwrap_or_default();
+    // Scalar rewards are paid out by the resolved value, nothing can be minted before it
     let config = POLL_CONFIG.load(deps.storage)?;
+    if config.scalar.as_ref().is_some_and(|scalar| scalar.resolved_value.is_none()) {
+        return Ok(Response::new()
+            .add_attribute("action", "distribute_epoch_rewards")
+            .add_attribute("epoch", epoch_number.to_string()));
+    }
 
-    let start_index = epoch.last_processed_index;
-    let end_index = std::cmp::min(start_index + BATCH_SIZE, stakers.len() as u64);
+    // One more than a batch tells whether this batch is the last one
+    let batch = stakes()
+        .idx
+        .epoch
+        .prefix(epoch_number)
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(epoch.last_processed_index)),
+            None,
+            Order::Ascending,
+        )
+        .take(BATCH_SIZE as usize + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+    let rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
 
     let mut messages = Vec::new();
-
-    for i in start_index..end_index {
-        let staker = &stakers[i as usize];
-        let stakes = USER_STAKES
-            .may_load(deps.storage, (staker, epoch_number))?
-            .unwrap_or_default();
-
-        for stake in stakes {
-            let reward = epoch
-                .total_distribution
-                .multiply_ratio(stake.amount, epoch.total_epoch_staked);
-
-            let token = if stake.position {
-                config.yes_token.clone()
-            } else {
-                config.no_token.clone()
-            };
-
-            messages.push(SubMsg::new(create_mint_msg(
-                &token,
-                staker,
-                reward,
-            )?));
-        }
+    for (id, stake) in batch.iter().take(BATCH_SIZE as usize) {
+        messages.extend(claim_rewards(deps.storage, &config, &rewards, &stake.user, None)?);
+        mark_ended_epochs_claimed(deps.storage, &stake.user, env.block.time.seconds())?;
+        epoch.last_processed_index = *id;
     }
+    REWARD_INDEX.save(deps.storage, &rewards)?;
 
-    epoch.last_processed_index = end_index;
-    if end_index as usize == stakers.len() {
+    if batch.len() <= BATCH_SIZE as usize {
         epoch.is_distributed = true;
     }
     EPOCHS.save(deps.storage, epoch_number, &epoch)?;
 
     Ok(Response::new()
-        .add_submessages(messages)
+        .add_messages(messages)
         .add_attribute("action", "distribute_epoch_rewards")
         .add_attribute("epoch", epoch_number.to_string()))
 }
 
-pub fn execute_resolve_poll(
+pub fn execute_claim_rewards(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    let messages = claim_rewards(deps.storage, &config, &rewards, &info.sender, None)?;
+    if messages.is_empty() {
+        return Err(ContractError::NoRewardsToClaim {});
+    }
+    REWARD_INDEX.save(deps.storage, &rewards)?;
+    mark_ended_epochs_claimed(deps.storage, &info.sender, env.block.time.seconds())?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "claim_rewards")
+        .add_attribute("user", info.sender))
+}
+
+/// Mints the caller's rewards of ended epochs, each epoch at most once
+pub fn execute_claim_epoch_rewards(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    winning_position: bool,
+    epochs: Vec<u64>,
 ) -> Result<Response, ContractError> {
-    let mut config = POLL_CONFIG.load(deps.storage)?;
-    if env.block.time.seconds() < config.end_timestamp {
-        return Err(ContractError::PollStillActive {});
+    for (i, &epoch_number) in epochs.iter().enumerate() {
+        let epoch = EPOCHS
+            .may_load(deps.storage, epoch_number)?
+            .ok_or(ContractError::EpochNotStarted {})?;
+        if env.block.time.seconds() <= epoch.end_time {
+            return Err(ContractError::EpochNotEnded {});
+        }
+        if epochs[..i].contains(&epoch_number) || CLAIMED_EPOCHS.has(deps.storage, (&info.sender, epoch_number)) {
+            return Err(ContractError::EpochAlreadyClaimed { epoch: epoch_number });
+        }
     }
-    if config.is_resolved {
-        return Err(ContractError::PollAlreadyResolved {});
+
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    let messages = claim_rewards(deps.storage, &config, &rewards, &info.sender, Some(&epochs))?;
+    if messages.is_empty() {
+        return Err(ContractError::NoRewardsToClaim {});
     }
-    if info.sender != config.capy_core {
-        return Err(ContractError::Unauthorized {});
+    REWARD_INDEX.save(deps.storage, &rewards)?;
+    for &epoch_number in &epochs {
+        CLAIMED_EPOCHS.save(deps.storage, (&info.sender, epoch_number), &true)?;
     }
 
-    config.is_resolved = true;
-    config.winning_position = Some(winning_position);
-    POLL_CONFIG.save(deps.storage, &config)?;
+    let epochs = epochs.iter().map(u64::to_string).collect::<Vec<_>>().join(",");
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "claim_epoch_rewards")
+        .add_attribute("user", info.sender)
+        .add_attribute("epochs", epochs))
+}
 
-    // Implement blitz mechanism
-    let losing_token = if winning_position {
-        config.no_token
-    } else {
-        config.yes_token
+/// Records every ended epoch as claimed by `user`, once a claim drained all of them
+fn mark_ended_epochs_claimed(storage: &mut dyn Storage, user: &Addr, now: u64) -> StdResult<()> {
+    let ended = EPOCHS
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(true, |(_, epoch)| now > epoch.end_time))
+        .map(|item| item.map(|(epoch_number, _)| epoch_number))
+        .collect::<StdResult<Vec<_>>>()?;
+    for epoch_number in ended {
+        CLAIMED_EPOCHS.save(storage, (user, epoch_number), &true)?;
+    }
+    Ok(())
+}
+
+/// Mints the unclaimed rewards of `user` on every outcome, only those of `epochs` when given.
+/// Scalar rewards are split by where the value landed, so they wait for the resolution.
+fn claim_rewards(
+    storage: &mut dyn Storage,
+    config: &PollConfig,
+    rewards: &RewardIndex,
+    user: &Addr,
+    epochs: Option<&[u64]>,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let scalar_value = match &config.scalar {
+        Some(scalar) => Some(scalar.resolved_value.ok_or(ContractError::PollNotResolved {})?),
+        None => None,
     };
 
-    // Calculate tokens to mint for 95% price drop
-    let token_info: TokenInfoResponse = deps.querier.query_wasm_smart(
-        losing_token.clone(),
-        &Cw20QueryMsg::TokenInfo {},
+    let mut messages = Vec::new();
+    let mut minted = Uint128::zero();
+    for position in 0..config.outcomes.len() as u8 {
+        let Some(mut reward) = user_rewards().may_load(storage, (user, position))? else {
+            continue;
+        };
+        reward.settle(rewards)?;
+        let mut amount = Uint128::zero();
+        for (i, pending) in reward.pending.iter_mut().enumerate() {
+            if epochs.is_none_or(|epochs| epochs.contains(&(i as u64 + 1))) {
+                amount += std::mem::take(pending);
+            }
+        }
+        let mut aggregate = load_outcome_rewards(storage, rewards, position)?;
+        aggregate.pending = aggregate.pending.saturating_sub(amount);
+        OUTCOME_REWARDS.save(storage, position, &aggregate)?;
+        if let (Some(scalar), Some(value)) = (&config.scalar, scalar_value) {
+            amount = amount.mul_floor(scalar.payout(position, value));
+        }
+        reward.claimed += amount;
+        user_rewards().save(storage, (user, position), &reward)?;
+
+        if !amount.is_zero() {
+            messages.push(create_mint_msg(config.outcome_token(position)?, user, amount)?);
+            minted += amount;
+        }
+    }
+    MINTED_REWARDS.update(storage, |total| -> StdResult<_> { Ok(total + minted) })?;
+    Ok(messages)
+}
+
+/// Mints the creator allocation vested since the last claim, of every outcome token
+pub fn execute_claim_creator_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    if info.sender != config.poll_creator {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut vesting = CREATOR_VESTING.load(deps.storage)?;
+    let amount = vesting.vested(env.block.time.seconds()) - vesting.claimed;
+    if amount.is_zero() {
+        return Err(ContractError::NoRewardsToClaim {});
+    }
+    vesting.claimed += amount;
+    CREATOR_VESTING.save(deps.storage, &vesting)?;
+
+    let messages = config
+        .outcomes
+        .iter()
+        .map(|outcome| create_mint_msg(&outcome.token, &config.poll_creator, amount))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "claim_creator_rewards")
+        .add_attribute("creator", config.poll_creator)
+        .add_attribute("amount", amount))
+}
+
+pub fn execute_buy_tokens(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position: u8,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let paid = config.collateral.native_payment(&info.funds)?;
+    buy_tokens(deps, env, config, info.sender, paid, position, amount)
+}
+
+/// Mints `amount` outcome tokens of `position` off the paid-list sale to `buyer`, who paid
+/// `paid` for them. The payment goes to the prize pool.
+fn buy_tokens(
+    deps: DepsMut,
+    env: Env,
+    config: PollConfig,
+    buyer: Addr,
+    paid: Uint128,
+    position: u8,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let sale = config.sale.as_ref().ok_or(ContractError::NoSale {})?;
+    let token = config.outcome_token(position)?;
+    if env.block.time.seconds() >= config.end_timestamp {
+        return Err(ContractError::PollEnded {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidPaymentAmount {});
+    }
+
+    let mut side = SALE_SIDES.may_load(deps.storage, position)?.unwrap_or_default();
+    let available = sale.max_per_side - side.sold;
+    if amount > available {
+        return Err(ContractError::SaleCapExceeded { position, available });
+    }
+    let cost = sale.buy_cost(side.sold, amount)?;
+    if paid != cost {
+        return Err(ContractError::InvalidSalePayment { cost });
+    }
+    side.sold += amount;
+    side.proceeds += cost;
+    SALE_SIDES.save(deps.storage, position, &side)?;
+    SALE_PURCHASES.update(deps.storage, (&buyer, position), |purchased| -> StdResult<_> {
+        Ok(purchased.unwrap_or_default() + amount)
+    })?;
+
+    let mut messages = participation_hook(deps.storage, &config, &buyer)?;
+    messages.push(SubMsg::new(create_mint_msg(token, &buyer, amount)?));
+    messages.push(report_event(&config, PollEvent::Bought { amount: cost })?);
+    record_activity(deps.storage, &env, &buyer, ActivityType::Buy, amount, position)?;
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("action", "buy_tokens")
+        .add_attribute("user", buyer)
+        .add_attribute("position", position.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("cost", cost))
+}
+
+/// Burns `amount` outcome tokens of `position` and refunds them at the sale's price. Only
+/// tokens the sender bought on the sale can be sold back, rewards and creator tokens can't.
+pub fn execute_sell_tokens(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position: u8,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let sale = config.sale.as_ref().ok_or(ContractError::NoSale {})?;
+    let token = config.outcome_token(position)?;
+    if env.block.time.seconds() >= config.end_timestamp {
+        return Err(ContractError::PollEnded {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidPaymentAmount {});
+    }
+
+    let purchased = SALE_PURCHASES
+        .may_load(deps.storage, (&info.sender, position))?
+        .unwrap_or_default();
+    if amount > purchased {
+        return Err(ContractError::SellBackExceeded { position, purchased });
+    }
+    SALE_PURCHASES.save(deps.storage, (&info.sender, position), &(purchased - amount))?;
+    let mut side = SALE_SIDES.load(deps.storage, position)?;
+    let refund = sale.sell_refund(side.sold, amount)?.min(side.proceeds);
+    side.sold -= amount;
+    side.proceeds -= refund;
+    SALE_SIDES.save(deps.storage, position, &side)?;
+    record_activity(deps.storage, &env, &info.sender, ActivityType::Sell, amount, position)?;
+
+    let mut messages = burn_from_msgs(token, &info.sender, &env, amount)?;
+    let mut reports = vec![];
+    if !refund.is_zero() {
+        messages.push(config.collateral.transfer_msg(&info.sender, refund)?);
+        reports.push(report_event(&config, PollEvent::Withdrawn { amount: refund })?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessages(reports)
+        .add_attribute("action", "sell_tokens")
+        .add_attribute("user", info.sender)
+        .add_attribute("position", position.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("refund", refund))
+}
+
+/// Burns `amount` outcome tokens of `position` for the same share of the side's prize pool
+pub fn execute_redeem_prize(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    position: u8,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    if config.sale.is_none() {
+        return Err(ContractError::NoSale {});
+    }
+    let token = config.outcome_token(position)?;
+    if !config.is_resolved {
+        return Err(ContractError::PollNotResolved {});
+    }
+
+    let pool = prize_pool(deps.storage, &config, position)?;
+    let mut side = SALE_SIDES.may_load(deps.storage, position)?.unwrap_or_default();
+    let prize = if config.is_invalid {
+        // Only buyers get the side's proceeds back, pro rata to the tokens they kept
+        let purchased = SALE_PURCHASES
+            .may_load(deps.storage, (&info.sender, position))?
+            .unwrap_or_default();
+        if amount > purchased {
+            return Err(ContractError::SellBackExceeded { position, purchased });
+        }
+        SALE_PURCHASES.save(deps.storage, (&info.sender, position), &(purchased - amount))?;
+        if amount.is_zero() {
+            Uint128::zero()
+        } else {
+            pool.multiply_ratio(amount, side.sold)
+        }
+    } else if side.redeemable.is_zero() {
+        Uint128::zero()
+    } else {
+        // Every token redeems at the same rate, however much was minted or burned since
+        pool.multiply_ratio(amount, side.redeemable)
+    }
+    .min(pool - side.redeemed);
+    if prize.is_zero() {
+        return Err(ContractError::NoPrizeToRedeem {});
+    }
+    side.redeemed += prize;
+    SALE_SIDES.save(deps.storage, position, &side)?;
+    record_activity(deps.storage, &env, &info.sender, ActivityType::Redeem, amount, position)?;
+
+    let mut messages = burn_from_msgs(token, &info.sender, &env, amount)?;
+    messages.push(config.collateral.transfer_msg(&info.sender, prize)?);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_submessage(report_event(&config, PollEvent::Withdrawn { amount: prize })?)
+        .add_attribute("action", "redeem_prize")
+        .add_attribute("user", info.sender)
+        .add_attribute("position", position.to_string())
+        .add_attribute("amount", amount)
+        .add_attribute("prize", prize))
+}
+
+/// Sale proceeds owed to the holders of `position` on a resolved poll, redeemed ones included.
+/// Invalid polls give every side its own proceeds back, otherwise the winners share all of them.
+fn prize_pool(storage: &dyn Storage, config: &PollConfig, position: u8) -> StdResult<Uint128> {
+    let sides = (0..config.outcomes.len() as u8)
+        .map(|position| Ok(SALE_SIDES.may_load(storage, position)?.unwrap_or_default()))
+        .collect::<StdResult<Vec<_>>>()?;
+    let side = &sides[position as usize];
+    let total = sides.iter().map(|side| side.proceeds).sum::<Uint128>();
+
+    let owed = match &config.scalar {
+        _ if config.is_invalid => side.proceeds,
+        Some(scalar) => scalar
+            .resolved_value
+            .map_or(Uint128::zero(), |value| total.mul_floor(scalar.payout(position, value))),
+        None if config.winning_position == Some(position) => total,
+        None => Uint128::zero(),
+    };
+    Ok(owed)
+}
+
+/// Fixes every side's `SaleSide::redeemable` as the poll resolves, before the blitz mints
+/// anything: the supply out now, the creator allocation not claimed yet and the epoch rewards
+/// not minted yet, scaled like their claims on scalar polls.
+fn snapshot_redeemable(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    config: &PollConfig,
+    now: u64,
+) -> StdResult<()> {
+    if config.sale.is_none() {
+        return Ok(());
+    }
+    let vesting = CREATOR_VESTING.load(storage)?;
+    let rewards = load_rewards(storage, config, now)?;
+    for (position, outcome) in config.outcomes.iter().enumerate() {
+        let position = position as u8;
+        let token_info: TokenInfoResponse = querier.query_wasm_smart(&outcome.token, &Cw20QueryMsg::TokenInfo {})?;
+        let mut unminted = load_outcome_rewards(storage, &rewards, position)?.pending;
+        if let Some(scalar @ ScalarConfig { resolved_value: Some(value), .. }) = &config.scalar {
+            unminted = unminted.mul_floor(scalar.payout(position, *value));
+        }
+        let mut side = SALE_SIDES.may_load(storage, position)?.unwrap_or_default();
+        side.redeemable = token_info.total_supply + vesting.total - vesting.claimed + unminted;
+        SALE_SIDES.save(storage, position, &side)?;
+    }
+    Ok(())
+}
+
+pub fn execute_resolve_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    winning_position: u8,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    if info.sender != config.capy_core {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    resolve(deps, env, config, winning_position)
+}
+
+/// Settles a scalar poll on `value`. Each side is blitzed by the share of the range it lost.
+pub fn execute_resolve_scalar(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    value: Uint128,
+) -> Result<Response, ContractError> {
+    let mut config = POLL_CONFIG.load(deps.storage)?;
+    if info.sender != config.capy_core {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut scalar = config.scalar.clone().ok_or(ContractError::NotScalarPoll {})?;
+    if env.block.time.seconds() < config.end_timestamp {
+        return Err(ContractError::PollStillActive {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+
+    scalar.resolved_value = Some(value);
+    config.is_resolved = true;
+    config.scalar = Some(scalar.clone());
+    POLL_CONFIG.save(deps.storage, &config)?;
+    snapshot_redeemable(deps.storage, &deps.querier, &config, env.block.time.seconds())?;
+
+    let public = deps.api.addr_validate("public")?;
+    let reserved = unminted_allocation(deps.storage)?;
+    let mut messages = vec![];
+    for position in [LONG, SHORT] {
+        let loss = Decimal::one() - scalar.payout(position, value);
+        messages.extend(blitz_msg(
+            &deps.querier,
+            config.outcome_token(position)?,
+            &public,
+            loss,
+            reserved,
+        )?);
+    }
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_submessage(report_event(&config, PollEvent::Resolved { invalid: false })?)
+        .add_attribute("action", "resolve_scalar")
+        .add_attribute("value", value)
+        .add_attribute("long_payout", scalar.long_payout(value).to_string())
+        .add_attribute("short_payout", scalar.short_payout(value).to_string()))
+}
+
+pub fn execute_resolve_from_oracle(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    if env.block.time.seconds() < config.end_timestamp {
+        return Err(ContractError::PollStillActive {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+
+    let oracle = config.oracle.clone().ok_or(ContractError::NoOracleConfigured {})?;
+    let res: OutcomeResponse = deps.querier.query_wasm_smart(
+        &oracle.address,
+        &OracleQueryMsg::Outcome {
+            question_id: oracle.question_id,
+        },
     )?;
+    let winning_position = res.outcome.ok_or(ContractError::OracleOutcomeUnavailable {})?;
 
-    let current_supply = token_info.total_supply;
-    let tokens_to_mint = current_supply.checked_mul(Uint128::from(19u128))
-        .map_err(|_| ContractError::FailedInnerCall {})?;
+    let res = resolve(deps, env, config, winning_position)?;
+    Ok(res.add_attribute("oracle", oracle.address))
+}
 
-    let messages = vec![SubMsg::new(create_mint_msg(
-        &losing_token,
-        &deps.api.addr_validate("public")?,
-        tokens_to_mint,
-    )?)];
+pub fn execute_propose_outcome(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    outcome: u8,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let bond = config.collateral.native_payment(&info.funds)?;
+    propose_outcome(deps, env, config, info.sender, bond, outcome)
+}
+
+fn propose_outcome(
+    deps: DepsMut,
+    env: Env,
+    config: PollConfig,
+    proposer: Addr,
+    bond: Uint128,
+    outcome: u8,
+) -> Result<Response, ContractError> {
+    config.outcome_token(outcome)?;
+    if env.block.time.seconds() < config.end_timestamp {
+        return Err(ContractError::PollStillActive {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+    let optimistic = config
+        .optimistic
+        .clone()
+        .ok_or(ContractError::OptimisticResolutionDisabled {})?;
+    if PROPOSAL.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::OutcomeAlreadyProposed {});
+    }
+    validate_bond(bond, &config, &optimistic)?;
+
+    let proposal = Proposal {
+        proposer: proposer.clone(),
+        outcome,
+        bond: optimistic.bond,
+        proposed_at: env.block.time.seconds(),
+        disputer: None,
+        status: ProposalStatus::Proposed,
+    };
+    PROPOSAL.save(deps.storage, &proposal)?;
+
+    Ok(Response::new()
+        .add_submessages(bond_report(&config, bond)?)
+        .add_attribute("action", "propose_outcome")
+        .add_attribute("proposer", proposer)
+        .add_attribute("outcome", outcome.to_string())
+        .add_attribute(
+            "challenge_period_end",
+            (proposal.proposed_at + optimistic.challenge_period).to_string(),
+        ))
+}
+
+pub fn execute_dispute_outcome(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let bond = config.collateral.native_payment(&info.funds)?;
+    dispute_outcome(deps, env, config, info.sender, bond)
+}
+
+fn dispute_outcome(
+    deps: DepsMut,
+    env: Env,
+    config: PollConfig,
+    disputer: Addr,
+    bond: Uint128,
+) -> Result<Response, ContractError> {
+    let optimistic = config
+        .optimistic
+        .clone()
+        .ok_or(ContractError::OptimisticResolutionDisabled {})?;
+    let mut proposal = PROPOSAL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoOutcomeProposed {})?;
+
+    match proposal.status {
+        ProposalStatus::Proposed => {}
+        ProposalStatus::Disputed => return Err(ContractError::OutcomeDisputed {}),
+        ProposalStatus::Finalized => return Err(ContractError::OutcomeAlreadyFinalized {}),
+    }
+    if env.block.time.seconds() >= proposal.proposed_at + optimistic.challenge_period {
+        return Err(ContractError::ChallengePeriodEnded {});
+    }
+    validate_bond(bond, &config, &optimistic)?;
+
+    proposal.disputer = Some(disputer.clone());
+    proposal.status = ProposalStatus::Disputed;
+    PROPOSAL.save(deps.storage, &proposal)?;
+
+    Ok(Response::new()
+        .add_submessages(bond_report(&config, bond)?)
+        .add_attribute("action", "dispute_outcome")
+        .add_attribute("disputer", disputer))
+}
+
+pub fn execute_finalize_outcome(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let optimistic = config
+        .optimistic
+        .clone()
+        .ok_or(ContractError::OptimisticResolutionDisabled {})?;
+    let proposal = PROPOSAL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoOutcomeProposed {})?;
+
+    match proposal.status {
+        ProposalStatus::Proposed => {}
+        ProposalStatus::Disputed => return Err(ContractError::OutcomeDisputed {}),
+        ProposalStatus::Finalized => return Err(ContractError::OutcomeAlreadyFinalized {}),
+    }
+    if env.block.time.seconds() < proposal.proposed_at + optimistic.challenge_period {
+        return Err(ContractError::ChallengePeriodActive {});
+    }
+
+    resolve(deps, env, config, proposal.outcome)
+}
+
+pub fn execute_settle_dispute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    winning_position: u8,
+) -> Result<Response, ContractError> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let optimistic = config
+        .optimistic
+        .clone()
+        .ok_or(ContractError::OptimisticResolutionDisabled {})?;
+    let arbiter = optimistic.arbiter.unwrap_or_else(|| config.capy_core.clone());
+    if info.sender != arbiter {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let proposal = PROPOSAL
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoOutcomeProposed {})?;
+    match proposal.status {
+        ProposalStatus::Disputed => {}
+        ProposalStatus::Proposed => return Err(ContractError::OutcomeNotDisputed {}),
+        ProposalStatus::Finalized => return Err(ContractError::OutcomeAlreadyFinalized {}),
+    }
+
+    resolve(deps, env, config, winning_position)
+}
+
+/// Settles the poll as Invalid. `capy_core` may do so once the poll has ended; anyone else
+/// only after `RESOLUTION_GRACE_PERIOD` has passed without a resolution.
+pub fn execute_invalidate_poll(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let mut config = POLL_CONFIG.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    if now < config.end_timestamp {
+        return Err(ContractError::PollStillActive {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+    let grace_period_end = config.end_timestamp + RESOLUTION_GRACE_PERIOD;
+    if info.sender != config.capy_core && now < grace_period_end {
+        return Err(ContractError::GracePeriodActive {
+            ends_at: grace_period_end,
+        });
+    }
+
+    config.is_resolved = true;
+    config.is_invalid = true;
+    POLL_CONFIG.save(deps.storage, &config)?;
+
+    // Return every bond of an open proposal, nobody was right
+    let mut bond_msgs = vec![];
+    let mut reports = vec![];
+    if let Some(mut proposal) = PROPOSAL.may_load(deps.storage)? {
+        if proposal.status != ProposalStatus::Finalized && !proposal.bond.is_zero() {
+            let mut refunds = vec![proposal.proposer.clone()];
+            refunds.extend(proposal.disputer.clone());
+            let refunded = proposal.bond * Uint128::from(refunds.len() as u128);
+            bond_msgs = refunds
+                .into_iter()
+                .map(|to| config.collateral.transfer_msg(&to, proposal.bond))
+                .collect::<StdResult<_>>()?;
+            reports.push(report_event(&config, PollEvent::Withdrawn { amount: refunded })?);
+        }
+        if proposal.status != ProposalStatus::Finalized {
+            proposal.status = ProposalStatus::Finalized;
+            PROPOSAL.save(deps.storage, &proposal)?;
+        }
+    }
+    reports.push(report_event(&config, PollEvent::Resolved { invalid: true })?);
+
+    Ok(Response::new()
+        .add_messages(bond_msgs)
+        .add_submessages(reports)
+        .add_attribute("action", "invalidate_poll")
+        .add_attribute("invalidated_by", info.sender))
+}
+
+fn validate_bond(
+    paid: Uint128,
+    config: &PollConfig,
+    optimistic: &OptimisticConfig,
+) -> Result<(), ContractError> {
+    if paid != optimistic.bond {
+        return Err(ContractError::InvalidBond {
+            expected: optimistic.bond,
+            denom: config.collateral.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Pays out or slashes the bonds of an open proposal once the final outcome is known.
+/// The side that matched the outcome takes both bonds of a dispute, while an undisputed
+/// proposal that was overridden forfeits its bond to `capy_core`.
+fn settle_proposal(
+    config: &PollConfig,
+    proposal: &Proposal,
+    winning_position: u8,
+) -> StdResult<Vec<SubMsg>> {
+    let (recipient, amount) = match (&proposal.status, &proposal.disputer) {
+        (ProposalStatus::Disputed, Some(disputer)) => {
+            let winner = if proposal.outcome == winning_position {
+                &proposal.proposer
+            } else {
+                disputer
+            };
+            (winner, proposal.bond + proposal.bond)
+        }
+        (ProposalStatus::Proposed, _) if proposal.outcome == winning_position => {
+            (&proposal.proposer, proposal.bond)
+        }
+        (ProposalStatus::Proposed, _) => (&config.capy_core, proposal.bond),
+        _ => return Ok(vec![]),
+    };
+    if amount.is_zero() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![
+        SubMsg::new(config.collateral.transfer_msg(recipient, amount)?),
+        report_event(config, PollEvent::Withdrawn { amount })?,
+    ])
+}
+
+/// Settles the poll on `winning_position` and blitzes every losing token
+fn resolve(
+    deps: DepsMut,
+    env: Env,
+    mut config: PollConfig,
+    winning_position: u8,
+) -> Result<Response, ContractError> {
+    if config.scalar.is_some() {
+        return Err(ContractError::ScalarPoll {});
+    }
+    config.outcome_token(winning_position)?;
+    if env.block.time.seconds() < config.end_timestamp {
+        return Err(ContractError::PollStillActive {});
+    }
+    if config.is_resolved {
+        return Err(ContractError::PollAlreadyResolved {});
+    }
+
+    config.is_resolved = true;
+    config.winning_position = Some(winning_position);
+    POLL_CONFIG.save(deps.storage, &config)?;
+    snapshot_redeemable(deps.storage, &deps.querier, &config, env.block.time.seconds())?;
+
+    // Settle any open optimistic proposal against the final outcome
+    let mut bond_msgs = vec![];
+    if let Some(mut proposal) = PROPOSAL.may_load(deps.storage)? {
+        bond_msgs = settle_proposal(&config, &proposal, winning_position)?;
+        proposal.status = ProposalStatus::Finalized;
+        PROPOSAL.save(deps.storage, &proposal)?;
+    }
+
+    // Implement blitz mechanism on every losing outcome
+    let public = deps.api.addr_validate("public")?;
+    let reserved = unminted_allocation(deps.storage)?;
+    let mut messages = vec![];
+    for (position, outcome) in config.outcomes.iter().enumerate() {
+        if position as u8 == winning_position {
+            continue;
+        }
+        messages.extend(blitz_msg(&deps.querier, &outcome.token, &public, Decimal::one(), reserved)?);
+    }
 
     Ok(Response::new()
         .add_submessages(messages)
+        .add_submessages(bond_msgs)
+        .add_submessage(report_event(&config, PollEvent::Resolved { invalid: false })?)
         .add_attribute("action", "resolve_poll")
         .add_attribute("winning_position", winning_position.to_string()))
 }
 
+/// Mints enough `token` to `public` to knock its price down by `loss` of the full 95% blitz.
+/// On capped tokens `reserved` stays clear of it, see `unminted_allocation`.
+fn blitz_msg(
+    querier: &QuerierWrapper,
+    token: &Addr,
+    public: &Addr,
+    loss: Decimal,
+    reserved: Uint128,
+) -> Result<Option<SubMsg>, ContractError> {
+    if loss.is_zero() {
+        return Ok(None);
+    }
+
+    // Calculate tokens to mint for 95% price drop
+    let token_info: TokenInfoResponse = querier.query_wasm_smart(
+        token.clone(),
+        &Cw20QueryMsg::TokenInfo {},
+    )?;
+
+    let current_supply = token_info.total_supply;
+    let mut tokens_to_mint = current_supply.checked_mul(Uint128::from(19u128))
+        .map_err(|_| ContractError::FailedInnerCall {})?
+        .mul_floor(loss);
+
+    // Capped tokens can only be diluted up to their cap
+    let minter: Option<MinterResponse> = querier.query_wasm_smart(token.clone(), &Cw20QueryMsg::Minter {})?;
+    if let Some(cap) = minter.and_then(|minter| minter.cap) {
+        let room = cap.saturating_sub(current_supply).saturating_sub(reserved);
+        if room.is_zero() {
+            return Ok(None);
+        }
+        tokens_to_mint = tokens_to_mint.min(room);
+    }
+
+    Ok(Some(SubMsg::new(create_mint_msg(token, public, tokens_to_mint)?)))
+}
+
+/// What every outcome token may still have to mint once resolved: the creator allocation not
+/// claimed yet and the epoch rewards not minted yet. Rewards are counted against the whole
+/// distribution on each token, since any one of them could end up with all of it.
+fn unminted_allocation(storage: &dyn Storage) -> StdResult<Uint128> {
+    let vesting = CREATOR_VESTING.load(storage)?;
+    let schedule = EPOCH_SCHEDULE.load(storage)?;
+    let minted = MINTED_REWARDS.may_load(storage)?.unwrap_or_default();
+    Ok(vesting.total - vesting.claimed + schedule.total_rewards.saturating_sub(minted))
+}
+
+/// Reports `event` to core's market stats
+fn report_event(config: &PollConfig, event: PollEvent) -> StdResult<SubMsg> {
+    let msg = WasmMsg::Execute {
+        contract_addr: config.capy_core.to_string(),
+        msg: to_json_binary(&CoreExecuteMsg::ReportPollEvent { event })?,
+        funds: vec![],
+    };
+    Ok(SubMsg::reply_on_error(msg, REPLY_REPORT_POLL_EVENT))
+}
+
+/// Whether stakes pay the protocol fee. Core only accounts for native fees, and only from the
+/// polls it registered.
+fn pays_protocol_fee(querier: &QuerierWrapper, env: &Env, config: &PollConfig) -> bool {
+    if config.protocol_fee.is_zero() || !matches!(config.collateral, Collateral::Native { .. }) {
+        return false;
+    }
+    let msg = CoreQueryMsg::IsPollFromFactory {
+        poll_address: env.contract.address.to_string(),
+    };
+    // A poll instantiated without core may name anything as `capy_core`
+    querier.query_wasm_smart(&config.capy_core, &msg).unwrap_or(false)
+}
+
+/// Sends `fee` to core along with its `FeeCollected` report. Unlike the other reports it must
+/// not fail on its own, or core would hold a fee it never credited.
+fn fee_msg(config: &PollConfig, fee: Uint128) -> StdResult<SubMsg> {
+    let msg = WasmMsg::Execute {
+        contract_addr: config.capy_core.to_string(),
+        msg: to_json_binary(&CoreExecuteMsg::ReportPollEvent {
+            event: PollEvent::FeeCollected { amount: fee },
+        })?,
+        funds: vec![Coin::new(fee.u128(), config.collateral.to_string())],
+    };
+    Ok(SubMsg::new(msg))
+}
+
+/// Reports a bond received, if any
+fn bond_report(config: &PollConfig, bond: Uint128) -> StdResult<Vec<SubMsg>> {
+    if bond.is_zero() {
+        return Ok(vec![]);
+    }
+    Ok(vec![report_event(config, PollEvent::Bonded { amount: bond })?])
+}
+
+/// Core hooks only feed its portfolio index and market stats, so a failing core never blocks
+/// the poll. Their own changes are reverted with them.
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        REPLY_RECORD_PARTICIPATION => Ok(Response::new()
+            .add_attribute("action", "record_participation_failed")),
+        REPLY_REPORT_POLL_EVENT => Ok(Response::new()
+            .add_attribute("action", "report_poll_event_failed")),
+        id => Err(StdError::generic_err(format!("Unknown reply id {id}")).into()),
+    }
+}
+
+/// Moves stakes and activities out of the per-epoch and per-block vectors of older versions
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // Polls of the first release have no epoch schedule
+    if EPOCH_SCHEDULE.may_load(deps.storage)?.is_none() {
+        migrate_legacy_poll(deps.storage, env.block.time.seconds())?;
+    }
+
+    // Reward positions saved before they were indexed by position
+    let reward_positions = user_rewards()
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    if MINTED_REWARDS.may_load(deps.storage)?.is_none() {
+        let minted = reward_positions.iter().map(|(_, reward)| reward.claimed).sum::<Uint128>();
+        MINTED_REWARDS.save(deps.storage, &minted)?;
+    }
+    for ((user, position), reward) in reward_positions {
+        user_rewards().replace(deps.storage, (&user, position), Some(&reward), None)?;
+    }
+
+    // Polls created before the creator allocation vest it over their whole lifetime
+    if CREATOR_VESTING.may_load(deps.storage)?.is_none() {
+        let config = POLL_CONFIG.load(deps.storage)?;
+        let first_epoch = EPOCHS.load(deps.storage, 1)?;
+        CREATOR_VESTING.save(
+            deps.storage,
+            &CreatorVesting {
+                start_time: first_epoch.start_time,
+                end_time: config.end_timestamp,
+                total: allocation(CREATOR_REWARD_PERCENTAGE),
+                claimed: Uint128::zero(),
+            },
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("stakes", STAKE_COUNT.load(deps.storage)?.to_string())
+        .add_attribute("activities", ACTIVITY_COUNT.load(deps.storage)?.to_string()))
+}
+
+/// Moves a binary poll of the first release onto the current layout. Its epochs paid out in
+/// one go once ended, each split between the stakes placed in it, so whatever was emitted
+/// before the migration is credited that way and the reward index streams the rest.
+fn migrate_legacy_poll(storage: &mut dyn Storage, now: u64) -> Result<(), ContractError> {
+    let legacy_config = legacy::POLL_CONFIG.load(storage)?;
+    let config = PollConfig {
+        capy_core: legacy_config.capy_core,
+        poll_creator: legacy_config.poll_creator,
+        outcomes: vec![
+            Outcome {
+                name: "YES".to_string(),
+                token: legacy_config.yes_token,
+            },
+            Outcome {
+                name: "NO".to_string(),
+                token: legacy_config.no_token,
+            },
+        ],
+        end_timestamp: legacy_config.end_timestamp,
+        total_staked: legacy_config.total_staked,
+        is_resolved: legacy_config.is_resolved,
+        winning_position: legacy_config.winning_position.map(legacy::position),
+        is_invalid: false,
+        collateral: Collateral::Native {
+            denom: legacy_config.denom,
+        },
+        oracle: None,
+        optimistic: None,
+        scalar: None,
+        exit_penalty: ExitPenalty {
+            start: Decimal::percent(DEFAULT_EXIT_PENALTY_PERCENT),
+            end: Decimal::percent(DEFAULT_EXIT_PENALTY_PERCENT),
+        },
+        limits: StakeLimits::default(),
+        protocol_fee: Decimal::zero(),
+        sale: None,
+    };
+    POLL_CONFIG.save(storage, &config)?;
+
+    // Epoch numbers were counted back from the end of the poll
+    let num_epochs = legacy::NUM_EPOCHS.may_load(storage)?.unwrap_or(DEFAULT_NUM_EPOCHS);
+    let epoch_duration = legacy::EPOCH_DURATION.load(storage)?;
+    let schedule = EpochSchedule {
+        start_time: config.end_timestamp - epoch_duration * num_epochs,
+        num_epochs,
+        epoch_duration,
+        decay: Decimal::percent(DEFAULT_EPOCH_DECAY_PERCENT),
+        total_rewards: allocation(DISTRIBUTION_PERCENTAGE),
+    };
+    let legacy_epochs = EPOCHS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    save_epochs(storage, &schedule, config.end_timestamp)?;
+    EPOCH_SCHEDULE.save(storage, &schedule)?;
+    for position in 0..config.outcomes.len() as u8 {
+        TOTAL_STAKED.save(storage, position, &Uint128::zero())?;
+    }
+
+    let mut legacy_stakes = legacy::USER_STAKES
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    // Oldest epochs first, so ids keep increasing with time
+    legacy_stakes.sort_by_key(|((_, epoch_number), _)| *epoch_number);
+
+    let mut stake_count = 0;
+    let mut active = Uint128::zero();
+    for ((user, epoch_number), user_stakes) in legacy_stakes {
+        legacy::USER_STAKES.remove(storage, (&user, epoch_number));
+        let user_stakes = user_stakes
+            .into_iter()
+            .filter(|stake| !stake.amount.is_zero())
+            .collect::<Vec<_>>();
+        if user_stakes.is_empty() {
+            continue;
+        }
+
+        let mut epoch = EPOCHS.load(storage, epoch_number)?;
+        epoch.num_stakers += 1;
+        for stake in user_stakes {
+            let position = legacy::position(stake.position);
+            stake_count += 1;
+            let stake = Stake {
+                user: user.clone(),
+                epoch: epoch_number,
+                amount: stake.amount,
+                position,
+                withdrawn: stake.withdrawn,
+            };
+            stakes().save(storage, stake_count, &stake)?;
+            epoch.total_epoch_staked += stake.amount;
+            TOTAL_STAKED.update(storage, position, |total| -> StdResult<_> {
+                Ok(total.unwrap_or_default() + stake.amount)
+            })?;
+
+            // Withdrawn stakes keep their rewards but no longer earn
+            let mut reward = user_rewards().may_load(storage, (&user, position))?.unwrap_or_default();
+            if !stake.withdrawn {
+                reward.staked += stake.amount;
+                active += stake.amount;
+            }
+            user_rewards().save(storage, (&user, position), &reward)?;
+        }
+        EPOCHS.save(storage, epoch_number, &epoch)?;
+    }
+    STAKE_COUNT.save(storage, &stake_count)?;
+
+    // The crank minted ended epochs to the stakers it went through, by staker rather than by
+    // stake id, so unfinished epochs start over and those stakers have nothing left there
+    let emitted_until = now.min(config.end_timestamp);
+    let mut ended = 0;
+    for epoch_number in 1..=num_epochs {
+        let mut epoch = EPOCHS.load(storage, epoch_number)?;
+        let legacy_epoch = legacy_epochs.iter().find(|(number, _)| *number == epoch_number);
+        let paid = match legacy_epoch {
+            Some((_, legacy_epoch)) if legacy_epoch.is_distributed => {
+                epoch.is_distributed = true;
+                EPOCHS.save(storage, epoch_number, &epoch)?;
+                legacy::EPOCH_STAKERS.may_load(storage, epoch_number)?.unwrap_or_default()
+            },
+            Some((_, legacy_epoch)) => {
+                let stakers = legacy::EPOCH_STAKERS.may_load(storage, epoch_number)?.unwrap_or_default();
+                stakers.into_iter().take(legacy_epoch.last_processed_index as usize).collect()
+            },
+            None => vec![],
+        };
+        legacy::EPOCH_STAKERS.remove(storage, epoch_number);
+        if emitted_until >= epoch.end_time {
+            ended += 1;
+        }
+        if emitted_until <= epoch.start_time || epoch.total_epoch_staked.is_zero() {
+            continue;
+        }
+
+        let emitted = epoch.total_distribution.multiply_ratio(
+            emitted_until.min(epoch.end_time) - epoch.start_time,
+            epoch.end_time - epoch.start_time,
+        );
+        let epoch_stakes = stakes()
+            .idx
+            .epoch
+            .prefix(epoch_number)
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        let i = epoch_number as usize - 1;
+        for (_, stake) in epoch_stakes {
+            let amount = emitted.multiply_ratio(stake.amount, epoch.total_epoch_staked);
+            let mut reward = user_rewards().load(storage, (&stake.user, stake.position))?;
+            reward.pending.resize(reward.pending.len().max(i + 1), Uint128::zero());
+            reward.earned.resize(reward.earned.len().max(i + 1), Uint128::zero());
+            reward.earned[i] += amount;
+            if paid.contains(&stake.user) {
+                reward.claimed += amount;
+                CLAIMED_EPOCHS.save(storage, (&stake.user, epoch_number), &true)?;
+            } else {
+                reward.pending[i] += amount;
+                let mut aggregate = OUTCOME_REWARDS.may_load(storage, stake.position)?.unwrap_or_default();
+                aggregate.pending += amount;
+                OUTCOME_REWARDS.save(storage, stake.position, &aggregate)?;
+            }
+            user_rewards().save(storage, (&stake.user, stake.position), &reward)?;
+        }
+    }
+    REWARD_INDEX.save(
+        storage,
+        &RewardIndex {
+            index: Decimal256::zero(),
+            last_update: emitted_until.max(schedule.start_time),
+            total_staked: active,
+            epoch_ends: vec![Decimal256::zero(); ended],
+        },
+    )?;
+
+    let legacy_activities = legacy::ACTIVITIES
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut activity_count = 0;
+    for (height, block_activities) in legacy_activities {
+        legacy::ACTIVITIES.remove(storage, height);
+        for activity in block_activities {
+            activity_count += 1;
+            let activity = PollActivity {
+                user: activity.user,
+                activity_type: activity.activity_type,
+                amount: activity.amount,
+                position: activity.position.map(legacy::position),
+                timestamp: activity.timestamp,
+                block_height: activity.block_height,
+            };
+            activities().save(storage, activity_count, &activity)?;
+        }
+    }
+    ACTIVITY_COUNT.save(storage, &activity_count)?;
+
+    legacy::TOTAL_YES_STAKED.remove(storage);
+    legacy::TOTAL_NO_STAKED.remove(storage);
+    legacy::EPOCH_DURATION.remove(storage);
+    legacy::NUM_EPOCHS.remove(storage);
+    PENALTY_POT.save(storage, &Uint128::zero())?;
+    if CURRENT_EPOCH.may_load(storage)?.is_none() {
+        CURRENT_EPOCH.save(storage, &schedule.epoch_at(now))?;
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetPollInfo {} => to_json_binary(&query_poll_info(deps)?),
-        QueryMsg::GetEpochInfo { epoch_number } => to_binary(&query_epoch_info(deps, epoch_number)?),
+        QueryMsg::GetEpochInfo { epoch_number } => to_json_binary(&query_epoch_info(deps, epoch_number)?),
+        QueryMsg::GetEpochSchedule {} => to_json_binary(&query_epoch_schedule(deps, env)?),
         QueryMsg::GetUserStakesForEpoch { user, epoch_number } => {
-            to_binary(&query_user_stakes(deps, user, epoch_number)?)
+            to_json_binary(&query_user_stakes(deps, user, epoch_number)?)
         },
-        QueryMsg::GetTotalStaked {} => to_binary(&query_total_staked(deps)?),
+        QueryMsg::GetUserPosition { user } => to_json_binary(&query_user_position(deps, env, user)?),
+        QueryMsg::ListStakers {
+            position,
+            start_after,
+            limit,
+        } => to_json_binary(&query_stakers(deps, position, start_after, limit)?),
+        QueryMsg::GetTotalStaked {} => to_json_binary(&query_total_staked(deps)?),
+        QueryMsg::GetLimits { user } => to_json_binary(&query_limits(deps, user)?),
+        QueryMsg::GetPendingRewards { user } => to_json_binary(&query_pending_rewards(deps, env, user)?),
+        QueryMsg::GetClaimable { user } => to_json_binary(&query_claimable(deps, env, user)?),
+        QueryMsg::GetProposal {} => to_json_binary(&query_proposal(deps)?),
+        QueryMsg::GetScalarPayout { value } => to_json_binary(&query_scalar_payout(deps, value)?),
+        QueryMsg::GetTokenAllocation {} => to_json_binary(&query_token_allocation(deps, env)?),
+        QueryMsg::GetSaleQuote { position, amount } => to_json_binary(&query_sale_quote(deps, position, amount)?),
         QueryMsg::GetActivities { start_after, limit } => {
-            to_binary(&query_activities(deps, start_after, limit)?)
+            to_json_binary(&query_activities(deps, start_after, limit)?)
         },
     }
 }
 
+fn query_token_allocation(deps: Deps, env: Env) -> StdResult<TokenAllocationResponse> {
+    let vesting = CREATOR_VESTING.load(deps.storage)?;
+    let schedule = EPOCH_SCHEDULE.load(deps.storage)?;
+    Ok(TokenAllocationResponse {
+        max_supply: Uint128::new(MAX_TOKEN_SUPPLY),
+        creator: vesting.total,
+        creator_vested: vesting.vested(env.block.time.seconds()),
+        creator_claimed: vesting.claimed,
+        creator_vesting_end: vesting.end_time,
+        distribution: schedule.total_rewards,
+        paid_list: allocation(PAID_LIST_PERCENTAGE),
+    })
+}
+
+fn query_sale_quote(deps: Deps, position: u8, amount: Uint128) -> StdResult<SaleQuoteResponse> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let sale = config
+        .sale
+        .as_ref()
+        .ok_or_else(|| StdError::generic_err(ContractError::NoSale {}.to_string()))?;
+    config
+        .outcome_token(position)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let side = SALE_SIDES.may_load(deps.storage, position)?.unwrap_or_default();
+    let available = sale.max_per_side - side.sold;
+    Ok(SaleQuoteResponse {
+        sold: side.sold,
+        available,
+        cost: (amount <= available)
+            .then(|| sale.buy_cost(side.sold, amount))
+            .transpose()?,
+        refund: (amount <= side.sold)
+            .then(|| Ok::<_, StdError>(sale.sell_refund(side.sold, amount)?.min(side.proceeds)))
+            .transpose()?,
+        prize_pool: config
+            .is_resolved
+            .then(|| Ok::<_, StdError>(prize_pool(deps.storage, &config, position)? - side.redeemed))
+            .transpose()?,
+    })
+}
+
 fn query_epoch_info(deps: Deps, epoch_number: u64) -> StdResult<EpochInfoResponse> {
     let epoch = EPOCHS.load(deps.storage, epoch_number)?;
-    let stakers = EPOCH_STAKERS
-        .may_load(deps.storage, epoch_number)?
-        .unwrap_or_default();
 
-    Ok(EpochInfoResponse {
-        start_time: epoch.start_time,
-        end_time: epoch.end_time,
-        total_distribution: epoch.total_distribution,
-        is_distributed: epoch.is_distributed,
-        num_stakers: stakers.len() as u64,
+    Ok(EpochInfoResponse {
+        start_time: epoch.start_time,
+        end_time: epoch.end_time,
+        total_distribution: epoch.total_distribution,
+        is_distributed: epoch.is_distributed,
+        num_stakers: epoch.num_stakers,
+    })
+}
+
+fn query_epoch_schedule(deps: Deps, env: Env) -> StdResult<EpochScheduleResponse> {
+    let schedule = EPOCH_SCHEDULE.load(deps.storage)?;
+    let epochs = EPOCHS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (epoch_number, epoch) = item?;
+            Ok(EpochScheduleEntry {
+                epoch_number,
+                start_time: epoch.start_time,
+                end_time: epoch.end_time,
+                total_distribution: epoch.total_distribution,
+                is_distributed: epoch.is_distributed,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(EpochScheduleResponse {
+        num_epochs: schedule.num_epochs,
+        epoch_duration: schedule.epoch_duration,
+        decay: schedule.decay,
+        total_rewards: schedule.total_rewards,
+        current_epoch: schedule.epoch_at(env.block.time.seconds()),
+        epochs,
+    })
+}
+
+fn query_user_stakes(
+    deps: Deps,
+    user: String,
+    epoch_number: u64,
+) -> StdResult<UserStakesResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let stakes = stakes()
+        .idx
+        .user
+        .prefix((user_addr, epoch_number))
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, stake)| stake))
+        .collect::<StdResult<_>>()?;
+
+    Ok(UserStakesResponse { stakes })
+}
+
+fn query_poll_info(deps: Deps) -> StdResult<PollInfoResponse> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    Ok(PollInfoResponse {
+        end_timestamp: config.end_timestamp,
+        outcomes: config.outcomes,
+        total_staked: config.total_staked,
+        is_resolved: config.is_resolved,
+        winning_position: config.winning_position,
+        is_invalid: config.is_invalid,
+        collateral: config.collateral,
+        oracle: config.oracle,
+        optimistic: config.optimistic,
+        scalar: config.scalar,
+        exit_penalty: config.exit_penalty,
+        penalty_pot: PENALTY_POT.load(deps.storage)?,
+        protocol_fee: config.protocol_fee,
+        sale: config.sale,
+    })
+}
+
+fn query_proposal(deps: Deps) -> StdResult<ProposalResponse> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let proposal = PROPOSAL.may_load(deps.storage)?;
+    let challenge_period_end = match (&proposal, &config.optimistic) {
+        (Some(proposal), Some(optimistic)) => Some(proposal.proposed_at + optimistic.challenge_period),
+        _ => None,
+    };
+
+    Ok(ProposalResponse {
+        proposal,
+        challenge_period_end,
+    })
+}
+
+fn query_scalar_payout(deps: Deps, value: Option<Uint128>) -> StdResult<ScalarPayoutResponse> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let scalar = config
+        .scalar
+        .ok_or_else(|| StdError::generic_err(ContractError::NotScalarPoll {}.to_string()))?;
+
+    let value = match value.or(scalar.resolved_value) {
+        Some(value) => value,
+        None => implied_scalar_value(deps.storage, &scalar)?,
+    };
+
+    Ok(ScalarPayoutResponse {
+        lower_bound: scalar.lower_bound,
+        upper_bound: scalar.upper_bound,
+        value,
+        is_resolved: scalar.resolved_value.is_some(),
+        long_payout: scalar.long_payout(value),
+        short_payout: scalar.short_payout(value),
+    })
+}
+
+/// The market's own estimate of a scalar value: the LONG share of all stakes
+fn implied_scalar_value(storage: &dyn Storage, scalar: &ScalarConfig) -> StdResult<Uint128> {
+    let long = TOTAL_STAKED.load(storage, LONG)?;
+    let short = TOTAL_STAKED.load(storage, SHORT)?;
+    let total = long + short;
+    let range = scalar.upper_bound - scalar.lower_bound;
+    if total.is_zero() {
+        Ok(scalar.lower_bound + range.multiply_ratio(1u128, 2u128))
+    } else {
+        Ok(scalar.lower_bound + range.multiply_ratio(long, total))
+    }
+}
+
+/// Share of each outcome token's value left after the blitz, which dilutes a token by
+/// `1 + 19 * loss`. Open polls are weighed by the odds implied by the stakes.
+fn blitz_retention(storage: &dyn Storage, config: &PollConfig) -> StdResult<Vec<Decimal>> {
+    let retained = |loss: Decimal| Decimal::one() / (Decimal::one() + loss * Decimal::from_ratio(19u128, 1u128));
+    let positions = 0..config.outcomes.len() as u8;
+
+    if config.is_invalid {
+        return Ok(positions.map(|_| Decimal::one()).collect());
+    }
+    if let Some(scalar) = &config.scalar {
+        let value = match scalar.resolved_value {
+            Some(value) => value,
+            None => implied_scalar_value(storage, scalar)?,
+        };
+        return Ok(positions
+            .map(|position| retained(Decimal::one() - scalar.payout(position, value)))
+            .collect());
+    }
+    if let Some(winner) = config.winning_position {
+        return Ok(positions
+            .map(|position| retained(if position == winner { Decimal::zero() } else { Decimal::one() }))
+            .collect());
+    }
+
+    let totals = positions
+        .clone()
+        .map(|position| TOTAL_STAKED.load(storage, position))
+        .collect::<StdResult<Vec<_>>>()?;
+    let total: Uint128 = totals.iter().sum();
+    let losing = retained(Decimal::one());
+    Ok(totals
+        .iter()
+        .map(|staked| {
+            let odds = if total.is_zero() {
+                Decimal::from_ratio(1u128, config.outcomes.len() as u128)
+            } else {
+                Decimal::from_ratio(*staked, total)
+            };
+            odds + (Decimal::one() - odds) * losing
+        })
+        .collect())
+}
+
+fn query_user_position(deps: Deps, env: Env, user: String) -> StdResult<UserPositionResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    let retention = blitz_retention(deps.storage, &config)?;
+
+    let mut staked = vec![Uint128::zero(); config.outcomes.len()];
+    let mut withdrawn = Uint128::zero();
+    for item in stakes().idx.user.sub_prefix(user.clone()).range(deps.storage, None, None, Order::Ascending) {
+        let (_, stake) = item?;
+        if stake.withdrawn {
+            withdrawn += stake.amount;
+        } else {
+            staked[stake.position as usize] += stake.amount;
+        }
+    }
+    // Unstaked amounts left the stakes for good, only their activity remembers them
+    for item in activities().idx.user.prefix(user.clone()).range(deps.storage, None, None, Order::Ascending) {
+        let (_, activity) = item?;
+        if activity.activity_type == ActivityType::Unstake {
+            withdrawn += activity.amount.unwrap_or_default();
+        }
+    }
+
+    let rewards = (0..config.outcomes.len() as u8)
+        .map(|position| load_user_reward(deps.storage, &rewards, &user, position))
+        .collect::<StdResult<Vec<_>>>()?;
+    let positions = rewards
+        .iter()
+        .enumerate()
+        .map(|(position, reward)| {
+            let pending = reward.total_pending();
+            UserPositionEntry {
+                position: position as u8,
+                staked: staked[position],
+                claimed: reward.claimed,
+                pending,
+                blitz_value: (reward.claimed + pending).mul_floor(retention[position]),
+            }
+        })
+        .collect();
+
+    let num_epochs = rewards.iter().map(|reward| reward.earned.len()).max().unwrap_or_default();
+    let epochs = (0..num_epochs)
+        .map(|i| UserEpochRewards {
+            epoch_number: i as u64 + 1,
+            earned: rewards.iter().map(|reward| reward.earned.get(i).copied().unwrap_or_default()).collect(),
+            pending: rewards.iter().map(|reward| reward.pending.get(i).copied().unwrap_or_default()).collect(),
+        })
+        .collect();
+
+    Ok(UserPositionResponse {
+        positions,
+        withdrawn,
+        epochs,
     })
 }
 
-fn query_user_stakes(
+fn query_stakers(
     deps: Deps,
-    user: String,
-    epoch_number: u64,
-) -> StdResult<UserStakesResponse> {
-    let user_addr = deps.api.addr_validate(&user)?;
-    let stakes = USER_STAKES
-        .may_load(deps.storage, (&user_addr, epoch_number))?
-        .unwrap_or_default();
+    position: u8,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<StakersResponse> {
+    let limit = limit.unwrap_or(30) as usize;
+    let start = match start_after {
+        Some(user) => Some(Bound::exclusive((deps.api.addr_validate(&user)?, position))),
+        None => None,
+    };
 
-    Ok(UserStakesResponse { stakes })
+    let stakers = user_rewards()
+        .idx
+        .position
+        .prefix(position)
+        .range(deps.storage, start, None, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((_, reward)) if reward.staked.is_zero()))
+        .take(limit)
+        .map(|item| {
+            let ((user, _), reward) = item?;
+            Ok(StakerEntry {
+                user: user.to_string(),
+                staked: reward.staked,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+
+    Ok(StakersResponse { stakers })
 }
 
-fn query_poll_info(deps: Deps) -> StdResult<PollInfoResponse> {
+fn query_total_staked(deps: Deps) -> StdResult<TotalStakedResponse> {
     let config = POLL_CONFIG.load(deps.storage)?;
-    Ok(PollInfoResponse {
-        end_timestamp: config.end_timestamp,
-        yes_token: config.yes_token,
-        no_token: config.no_token,
-        total_staked: config.total_staked,
-        is_resolved: config.is_resolved,
-        winning_position: config.winning_position,
-        denom: config.denom,
+    let totals = (0..config.outcomes.len() as u8)
+        .map(|position| TOTAL_STAKED.load(deps.storage, position))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TotalStakedResponse {
+        totals,
+        collateral: config.collateral,
     })
 }
 
-fn query_total_staked(deps: Deps) -> StdResult<TotalStakedResponse> {
+fn query_pending_rewards(deps: Deps, env: Env, user: String) -> StdResult<PendingRewardsResponse> {
+    let user = deps.api.addr_validate(&user)?;
     let config = POLL_CONFIG.load(deps.storage)?;
-    let total_yes = TOTAL_YES_STAKED.load(deps.storage)?;
-    let total_no = TOTAL_NO_STAKED.load(deps.storage)?;
+    let rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+
+    let mut positions = Vec::new();
+    for (position, outcome) in config.outcomes.iter().enumerate() {
+        let reward = load_user_reward(deps.storage, &rewards, &user, position as u8)?;
+        positions.push(PositionRewards {
+            position: position as u8,
+            token: outcome.token.to_string(),
+            staked: reward.staked,
+            pending: reward.total_pending(),
+            claimed: reward.claimed,
+        });
+    }
+    Ok(PendingRewardsResponse { rewards: positions })
+}
 
-    Ok(TotalStakedResponse {
-        total_yes,
-        total_no,
-        denom: config.denom,
+fn query_claimable(deps: Deps, env: Env, user: String) -> StdResult<ClaimableResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let rewards = load_rewards(deps.storage, &config, env.block.time.seconds())?;
+    let positions = (0..config.outcomes.len() as u8)
+        .map(|position| load_user_reward(deps.storage, &rewards, &user, position))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut epochs = Vec::new();
+    for item in EPOCHS.range(deps.storage, None, None, Order::Ascending) {
+        let (epoch_number, epoch) = item?;
+        if epoch.start_time > env.block.time.seconds() {
+            break;
+        }
+        let amounts = positions
+            .iter()
+            .map(|reward| reward.pending.get(epoch_number as usize - 1).copied().unwrap_or_default())
+            .collect();
+        epochs.push(ClaimableEpoch {
+            epoch_number,
+            is_ended: env.block.time.seconds() > epoch.end_time,
+            is_claimed: CLAIMED_EPOCHS.has(deps.storage, (&user, epoch_number)),
+            amounts,
+        });
+    }
+    Ok(ClaimableResponse { epochs })
+}
+
+fn query_limits(deps: Deps, user: Option<String>) -> StdResult<LimitsResponse> {
+    let config = POLL_CONFIG.load(deps.storage)?;
+    let limits = config.limits;
+    let side_room = (0..config.outcomes.len() as u8)
+        .map(|position| -> StdResult<_> {
+            let total = TOTAL_STAKED.load(deps.storage, position)?;
+            Ok(limits.max_side_total.map(|max| max.saturating_sub(total)))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    let user_room = match (user, limits.max_user_total) {
+        (Some(user), Some(max)) => {
+            let user = deps.api.addr_validate(&user)?;
+            Some(max.saturating_sub(user_total(deps.storage, &user)?))
+        }
+        _ => None,
+    };
+
+    Ok(LimitsResponse {
+        min_stake: limits.min_stake,
+        max_stake: limits.max_stake,
+        max_user_total: limits.max_user_total,
+        max_side_total: limits.max_side_total,
+        side_room,
+        user_room,
     })
 }
 
@@ -459,201 +2447,1252 @@ fn query_activities(
     limit: Option<u32>,
 ) -> StdResult<ActivitiesResponse> {
     let limit = limit.unwrap_or(30) as usize;
-    let start = start_after.map(|s| Bound::exclusive(s));
+    // Past every id of the `start_after` block
+    let start = start_after.map(|height| Bound::exclusive((height, u64::MAX)));
 
-    let activities = ACTIVITIES
+    let activities = activities()
+        .idx
+        .height
         .range(deps.storage, start, None, Order::Descending)
         .take(limit)
-        .flat_map(|item| item.map(|(_, acts)| acts))
-        .flatten()
-        .collect();
+        .map(|item| item.map(|(_, activity)| activity))
+        .collect::<StdResult<_>>()?;
 
     Ok(ActivitiesResponse { activities })
 }
 
-fn calculate_epoch_distribution(epoch_number: u64) -> Uint128 {
-    let distribution = match epoch_number {
-        1 => 3657,
-        2 => 2743,
-        3 => 2058,
-        4 => 1542,
-        _ => 0u128,
+fn create_mint_msg(token: &Addr, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    let msg = Cw20ExecuteMsg::Mint {
+        recipient: recipient.to_string(),
+        amount,
     };
-    Uint128::from(distribution)
+    Ok(WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: to_json_binary(&msg)?,
+        funds: vec![],
+    }
+    .into())
 }
 
-fn create_mint_msg(token: &Addr, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
-    let msg = Cw20ExecuteMsg::Mint {
-        recipient: recipient.to_string(),
-        amount,
-    };
-    Ok(WasmMsg::Execute {
-        contract_addr: token.to_string(),
-        msg: to_binary(&msg)?,
-        funds: vec![],
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{from_json, BankMsg, Coin, ContractResult, OwnedDeps, Record, SystemError, SystemResult, WasmQuery};
+    use std::cell::Cell;
+    use crate::msg::{OutcomeInfo, SaleInfo};
+    use crate::state::{NO, YES};
+
+    const XION_DENOM: &str = "uxion";
+
+    fn binary_outcomes(yes_token: &str, no_token: &str) -> Vec<OutcomeInfo> {
+        vec![
+            OutcomeInfo {
+                name: "YES".to_string(),
+                token: yes_token.to_string(),
+            },
+            OutcomeInfo {
+                name: "NO".to_string(),
+                token: no_token.to_string(),
+            },
+        ]
+    }
+
+    /// Market stats report sent to `capy_core`
+    fn report(event: PollEvent) -> SubMsg {
+        let msg = WasmMsg::Execute {
+            contract_addr: "capy_core".to_string(),
+            msg: to_json_binary(&CoreExecuteMsg::ReportPollEvent { event }).unwrap(),
+            funds: vec![],
+        };
+        SubMsg::reply_on_error(msg, REPLY_REPORT_POLL_EVENT)
+    }
+
+    fn setup_contract() -> (OwnedDeps<MockStorage, MockApi, MockQuerier>, Env) {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("creator", &[]);
+
+        let msg = InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
+        };
+
+        // Initialize contract
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // Outcome tokens only need to answer supply queries for the blitz
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } if matches!(from_json(msg), Ok(Cw20QueryMsg::Minter {})) => {
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&None::<MinterResponse>).unwrap()))
+            }
+            WasmQuery::Smart { .. } => {
+                let token_info = TokenInfoResponse {
+                    name: "Outcome Token".to_string(),
+                    symbol: "OUT".to_string(),
+                    decimals: 18,
+                    total_supply: Uint128::new(1_000),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&token_info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+
+        (deps, env)
+    }
+
+    #[test]
+    fn test_proper_initialization() {
+        let (deps, _) = setup_contract();
+        
+        let config = POLL_CONFIG.load(deps.as_ref().storage).unwrap();
+        assert_eq!(config.capy_core.as_str(), "capy_core");
+        assert_eq!(config.poll_creator.as_str(), "creator");
+        assert_eq!(config.outcomes.len(), 2);
+        assert_eq!(config.outcomes[YES as usize].token.as_str(), "yes_token");
+        assert_eq!(config.outcomes[NO as usize].token.as_str(), "no_token");
+        assert!(!config.is_resolved);
+        assert_eq!(config.total_staked, Uint128::zero());
+        assert_eq!(
+            config.collateral,
+            Collateral::Native {
+                denom: XION_DENOM.to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stake() {
+        let (mut deps, env) = setup_contract();
+        
+        // Test successful stake
+        let info = mock_info(
+            "user1",
+            &[Coin {
+                denom: XION_DENOM.to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        assert_eq!(6, res.attributes.len());
+        assert_eq!(
+            res.attributes[0],
+            ("action", "stake")
+        );
+        assert_eq!(res.attributes[3], ("fee", "0"));
+        assert_eq!(res.attributes[5], ("epoch", "1"));
+
+        // Test insufficient payment
+        let info = mock_info(
+            "user2",
+            &[Coin {
+                denom: XION_DENOM.to_string(),
+                amount: Uint128::new(50),
+            }],
+        );
+
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidPaymentAmount {});
+
+        // Test wrong denom
+        let info = mock_info(
+            "user3",
+            &[Coin {
+                denom: "invalid".to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
+        assert_eq!(err, ContractError::NoXionPayment {});
+    }
+
+    #[test]
+    fn test_withdraw_stake() {
+        let (mut deps, mut env) = setup_contract();
+        
+        // First stake some XION
+        let info = mock_info(
+            "user1",
+            &[Coin {
+                denom: XION_DENOM.to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Move time forward and resolve poll
+        env.block.time = env.block.time.plus_seconds(1001);
+        let resolve_info = mock_info("capy_core", &[]);
+        let msg = ExecuteMsg::ResolvePoll {
+            winning_position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), resolve_info, msg).unwrap();
+
+        // Test withdrawal
+        let msg = ExecuteMsg::WithdrawStake {};
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        assert_eq!(2, res.messages.len());
+        assert_eq!(res.messages[1], report(PollEvent::Withdrawn { amount: Uint128::new(100) }));
+        match &res.messages[0].msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                assert_eq!(to_address, "user1");
+                assert_eq!(
+                    amount,
+                    &vec![Coin {
+                        denom: XION_DENOM.to_string(),
+                        amount: Uint128::new(100),
+                    }]
+                );
+            }
+            _ => panic!("Expected BankMsg::Send"),
+        }
+    }
+
+    #[test]
+    fn test_invalidate_poll() {
+        let (mut deps, mut env) = setup_contract();
+
+        let info = mock_info(
+            "user1",
+            &[Coin {
+                denom: XION_DENOM.to_string(),
+                amount: Uint128::new(100),
+            }],
+        );
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: NO,
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let err = execute(deps.as_mut(), env.clone(), mock_info("capy_core", &[]), ExecuteMsg::InvalidatePoll {}).unwrap_err();
+        assert_eq!(err, ContractError::PollStillActive {});
+
+        // Only capy_core may invalidate during the grace period
+        env.block.time = env.block.time.plus_seconds(1001);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::InvalidatePoll {}).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::GracePeriodActive {
+                ends_at: mock_env().block.time.seconds() + 1000 + RESOLUTION_GRACE_PERIOD,
+            }
+        );
+
+        // Past the grace period anyone can cancel an unresolved poll, and nothing gets minted
+        env.block.time = env.block.time.plus_seconds(RESOLUTION_GRACE_PERIOD);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::InvalidatePoll {}).unwrap();
+        assert_eq!(res.messages, vec![report(PollEvent::Resolved { invalid: true })]);
+
+        let poll_info = query_poll_info(deps.as_ref()).unwrap();
+        assert!(poll_info.is_resolved);
+        assert!(poll_info.is_invalid);
+        assert_eq!(poll_info.winning_position, None);
+
+        let msg = ExecuteMsg::ResolvePoll {
+            winning_position: YES,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("capy_core", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::PollAlreadyResolved {});
+
+        // The staker gets the full stake back
+        let res = execute(deps.as_mut(), env, mock_info("user1", &[]), ExecuteMsg::WithdrawStake {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user1".to_string(),
+                amount: vec![Coin {
+                    denom: XION_DENOM.to_string(),
+                    amount: Uint128::new(100),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_cw20_collateral() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let msg = InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Cw20 {
+                address: "usdc_token".to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        // Native funds are refused, collateral has to come through the token's Send
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), msg).unwrap_err();
+        assert_eq!(err, ContractError::Cw20CollateralRequired {});
+
+        let receive = |amount: u128| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "user1".to_string(),
+                amount: Uint128::new(amount),
+                msg: to_json_binary(&ReceiveMsg::Stake { position: YES }).unwrap(),
+            })
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("fake_token", &[]), receive(100)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidCollateral {});
+
+        let res = execute(deps.as_mut(), env.clone(), mock_info("usdc_token", &[]), receive(100)).unwrap();
+        assert_eq!(res.attributes[1], ("user", "user1"));
+        assert_eq!(res.attributes[2], ("amount", "100"));
+        let totals = query_total_staked(deps.as_ref()).unwrap();
+        assert_eq!(totals.totals, vec![Uint128::new(100), Uint128::zero()]);
+
+        // Mock the YES token for the blitz of the NO side
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } if matches!(from_json(msg), Ok(Cw20QueryMsg::Minter {})) => {
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&None::<MinterResponse>).unwrap()))
+            }
+            WasmQuery::Smart { .. } => {
+                let token_info = TokenInfoResponse {
+                    name: "Outcome Token".to_string(),
+                    symbol: "OUT".to_string(),
+                    decimals: 18,
+                    total_supply: Uint128::new(1_000),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&token_info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        env.block.time = env.block.time.plus_seconds(1001);
+        let msg = ExecuteMsg::ResolvePoll {
+            winning_position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("capy_core", &[]), msg).unwrap();
+
+        // The stake goes back out as a cw20 transfer
+        let res = execute(deps.as_mut(), env, mock_info("user1", &[]), ExecuteMsg::WithdrawStake {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "usdc_token".to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "user1".to_string(),
+                    amount: Uint128::new(100),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_unstake() {
+        let (mut deps, mut env) = setup_contract();
+
+        let penalty = ExitPenalty {
+            start: Decimal::percent(10),
+            end: Decimal::percent(2),
+        };
+        assert_eq!(penalty.rate(1, 4), Decimal::percent(10));
+        assert_eq!(penalty.rate(3, 5), Decimal::percent(6));
+        assert_eq!(penalty.rate(4, 4), Decimal::percent(2));
+
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), msg).unwrap();
+
+        let unstake = |amount: u128, position: u8| ExecuteMsg::Unstake {
+            amount: Uint128::new(amount),
+            position,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), unstake(50, NO)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientStake { available: Uint128::zero() });
+
+        // Nothing distributed yet, so nothing to burn and the epoch total shrinks
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), unstake(40, YES)).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user1".to_string(),
+                amount: vec![Coin::new(38, XION_DENOM)],
+            })
+        );
+        assert_eq!(res.attributes[5], ("penalty", "2"));
+        assert_eq!(res.attributes[6], ("tokens_burned", "0"));
+        assert_eq!(EPOCHS.load(deps.as_ref().storage, 1).unwrap().total_epoch_staked, Uint128::new(60));
+        let stakes = query_user_stakes(deps.as_ref(), "user1".to_string(), 1).unwrap().stakes;
+        assert_eq!(stakes[0].amount, Uint128::new(60));
+
+        env.block.time = env.block.time.plus_seconds(1001);
+        let err = execute(deps.as_mut(), env, mock_info("user1", &[]), unstake(10, YES)).unwrap_err();
+        assert_eq!(err, ContractError::PollEnded {});
     }
-    .into())
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
-    use cosmwasm_std::{coins, Coin, OwnedDeps};
+    #[test]
+    fn test_switch_position() {
+        let (mut deps, mut env) = setup_contract();
 
-    const XION_DENOM: &str = "uxion";
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), msg).unwrap();
 
-    fn setup_contract() -> (OwnedDeps<MockStorage, MockApi, MockQuerier>, Env) {
+        let switch = |amount: u128, from: u8, to: u8| ExecuteMsg::SwitchPosition {
+            amount: Uint128::new(amount),
+            from,
+            to,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), switch(30, YES, YES)).unwrap_err();
+        assert_eq!(err, ContractError::SamePosition {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), switch(30, NO, YES)).unwrap_err();
+        assert_eq!(err, ContractError::InsufficientStake { available: Uint128::zero() });
+
+        // The switched amount becomes a stake of the second epoch
+        env.block.time = env.block.time.plus_seconds(300);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), switch(30, YES, NO)).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.attributes[5], ("epoch", "2"));
+
+        let totals = query_total_staked(deps.as_ref()).unwrap();
+        assert_eq!(totals.totals, vec![Uint128::new(70), Uint128::new(30)]);
+        assert_eq!(EPOCHS.load(deps.as_ref().storage, 1).unwrap().total_epoch_staked, Uint128::new(70));
+        assert_eq!(EPOCHS.load(deps.as_ref().storage, 2).unwrap().total_epoch_staked, Uint128::new(30));
+        let stakes = query_user_stakes(deps.as_ref(), "user1".to_string(), 2).unwrap().stakes;
+        assert_eq!(
+            stakes,
+            vec![Stake {
+                user: Addr::unchecked("user1"),
+                epoch: 2,
+                amount: Uint128::new(30),
+                position: NO,
+                withdrawn: false,
+            }]
+        );
+        assert_eq!(query_epoch_info(deps.as_ref(), 2).unwrap().num_stakers, 1);
+
+        let activities = query_activities(deps.as_ref(), None, None).unwrap().activities;
+        let switch = activities.iter().find(|activity| activity.activity_type == ActivityType::Switch).unwrap();
+        assert_eq!((switch.amount, switch.position), (Some(Uint128::new(30)), Some(NO)));
+    }
+
+    #[test]
+    fn test_protocol_fee() {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let info = mock_info("creator", &[]);
-
-        let msg = InstantiateMsg {
+        let init_msg = |protocol_fee| InstantiateMsg {
             capy_core: "capy_core".to_string(),
             poll_creator: "creator".to_string(),
-            yes_token: "yes_token".to_string(),
-            no_token: "no_token".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
             duration: 1000,
-            denom: XION_DENOM.to_string(),
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: Some(protocol_fee),
+            sale: None,
         };
+        let err = instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg(Decimal::one())).unwrap_err();
+        assert_eq!(err, ContractError::InvalidProtocolFee {});
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg(Decimal::percent(2))).unwrap();
 
-        // Initialize contract
-        let res = instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
-        
-        // Initialize first epoch
-        let epoch_duration = 250; // 1000 / 4
-        let epoch = EpochInfo {
-            start_time: env.block.time.seconds(),
-            end_time: env.block.time.seconds() + epoch_duration,
-            total_distribution: calculate_epoch_distribution(1),
-            is_distributed: false,
-            total_epoch_staked: Uint128::zero(),
-            last_processed_index: 0,
+        // Core could never credit the fee of a poll it didn't register, so none is taken
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(1_000),
+            position: YES,
         };
-        EPOCHS.save(deps.as_mut().storage, 1u64, &epoch).unwrap();
-        TOTAL_YES_STAKED.save(deps.as_mut().storage, &Uint128::zero()).unwrap();
-        TOTAL_NO_STAKED.save(deps.as_mut().storage, &Uint128::zero()).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(1_000, XION_DENOM)]), msg.clone()).unwrap();
+        assert_eq!(res.messages[1..], [report(PollEvent::Staked { amount: Uint128::new(1_000) })]);
 
-        (deps, env)
+        // Once registered, the fee goes to core along with its report, only the rest is staked
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "capy_core" => {
+                assert_eq!(
+                    from_json::<CoreQueryMsg>(msg).unwrap(),
+                    CoreQueryMsg::IsPollFromFactory { poll_address: MOCK_CONTRACT_ADDR.to_string() }
+                );
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&true).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user2", &[Coin::new(1_000, XION_DENOM)]), msg).unwrap();
+        let fee = WasmMsg::Execute {
+            contract_addr: "capy_core".to_string(),
+            msg: to_json_binary(&CoreExecuteMsg::ReportPollEvent {
+                event: PollEvent::FeeCollected { amount: Uint128::new(20) },
+            })
+            .unwrap(),
+            funds: vec![Coin::new(20, XION_DENOM)],
+        };
+        assert_eq!(
+            res.messages[1..],
+            [report(PollEvent::Staked { amount: Uint128::new(980) }), SubMsg::new(fee)]
+        );
+        let totals = query_total_staked(deps.as_ref()).unwrap().totals;
+        assert_eq!(totals, vec![Uint128::new(1_980), Uint128::zero()]);
+        let position = query_user_position(deps.as_ref(), env, "user2".to_string()).unwrap();
+        assert_eq!(position.positions[YES as usize].staked, Uint128::new(980));
     }
 
     #[test]
-    fn test_proper_initialization() {
-        let (deps, _) = setup_contract();
-        
-        let config = POLL_CONFIG.load(deps.as_ref().storage).unwrap();
-        assert_eq!(config.capy_core.as_str(), "capy_core");
-        assert_eq!(config.poll_creator.as_str(), "creator");
-        assert_eq!(config.yes_token.as_str(), "yes_token");
-        assert_eq!(config.no_token.as_str(), "no_token");
-        assert!(!config.is_resolved);
-        assert_eq!(config.total_staked, Uint128::zero());
-        assert_eq!(config.denom, XION_DENOM);
+    fn test_creator_rewards() {
+        let (mut deps, mut env) = setup_contract();
+        let total = Uint128::new(MAX_TOKEN_SUPPLY / 100);
+        let mint = |token: &str, amount: Uint128| {
+            SubMsg::new(create_mint_msg(&Addr::unchecked(token), &Addr::unchecked("creator"), amount).unwrap())
+        };
+
+        let allocation: TokenAllocationResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetTokenAllocation {}).unwrap()).unwrap();
+        assert_eq!(
+            allocation,
+            TokenAllocationResponse {
+                max_supply: Uint128::new(MAX_TOKEN_SUPPLY),
+                creator: total,
+                creator_vested: Uint128::zero(),
+                creator_claimed: Uint128::zero(),
+                creator_vesting_end: env.block.time.seconds() + 1000,
+                distribution: Uint128::new(MAX_TOKEN_SUPPLY / 100 * 69),
+                paid_list: Uint128::new(MAX_TOKEN_SUPPLY / 100 * 30),
+            }
+        );
+
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimCreatorRewards {}).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), ExecuteMsg::ClaimCreatorRewards {}).unwrap_err();
+        assert_eq!(err, ContractError::NoRewardsToClaim {});
+
+        // Halfway through, half of every outcome token's allocation has vested
+        env.block.time = env.block.time.plus_seconds(500);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), ExecuteMsg::ClaimCreatorRewards {}).unwrap();
+        assert_eq!(res.messages, vec![mint("yes_token", total / Uint128::new(2)), mint("no_token", total / Uint128::new(2))]);
+        let err = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), ExecuteMsg::ClaimCreatorRewards {}).unwrap_err();
+        assert_eq!(err, ContractError::NoRewardsToClaim {});
+
+        // Nothing more vests after the poll ends
+        env.block.time = env.block.time.plus_seconds(5000);
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), ExecuteMsg::ClaimCreatorRewards {}).unwrap();
+        assert_eq!(res.messages, vec![mint("yes_token", total / Uint128::new(2)), mint("no_token", total / Uint128::new(2))]);
+        let allocation: TokenAllocationResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetTokenAllocation {}).unwrap()).unwrap();
+        assert_eq!(allocation.creator_claimed, total);
+
+        // Epochs can't stream more than the distribution share
+        let msg = InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: Some(EpochScheduleInfo {
+                num_epochs: 4,
+                decay: Decimal::one(),
+                total_rewards: Some(Uint128::new(MAX_TOKEN_SUPPLY)),
+            }),
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
+        };
+        let err = instantiate(mock_dependencies().as_mut(), env, mock_info("creator", &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::EpochRewardsAboveAllocation { max: allocation.distribution }
+        );
     }
 
     #[test]
-    fn test_stake() {
+    fn test_blitz_capped_supply() {
+        let (mut deps, mut env) = setup_contract();
+        // A staker on both sides, so the losing token owes epoch rewards after the blitz
+        for position in [YES, NO] {
+            let msg = ExecuteMsg::Stake {
+                amount: Uint128::new(100),
+                position,
+            };
+            execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), msg).unwrap();
+        }
+
+        // The losing token sits at a tenth of its cap, short of what a full blitz mints once
+        // the creator and epoch allocations are kept aside
+        let supply = Uint128::new(MAX_TOKEN_SUPPLY / 10);
+        deps.querier.update_wasm(move |query| match query {
+            WasmQuery::Smart { msg, .. } if matches!(from_json(msg), Ok(Cw20QueryMsg::Minter {})) => {
+                let minter = MinterResponse {
+                    minter: MOCK_CONTRACT_ADDR.to_string(),
+                    cap: Some(Uint128::new(MAX_TOKEN_SUPPLY)),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&Some(minter)).unwrap()))
+            }
+            WasmQuery::Smart { .. } => {
+                let token_info = TokenInfoResponse {
+                    name: "Outcome Token".to_string(),
+                    symbol: "OUT".to_string(),
+                    decimals: 18,
+                    total_supply: supply,
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&token_info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
+        env.block.time = env.block.time.plus_seconds(1001);
+        let msg = ExecuteMsg::ResolvePoll {
+            winning_position: YES,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("capy_core", &[]), msg).unwrap();
+        let room = Uint128::new(MAX_TOKEN_SUPPLY)
+            - supply
+            - allocation(CREATOR_REWARD_PERCENTAGE)
+            - allocation(DISTRIBUTION_PERCENTAGE);
+        let blitz = create_mint_msg(&Addr::unchecked("no_token"), &Addr::unchecked("public"), room).unwrap();
+        assert_eq!(res.messages[0], SubMsg::new(blitz));
+
+        // Rewards and the creator allocation still fit under the cap, on both tokens at once
+        let mut minted = room;
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+        assert_eq!(res.messages.len(), 2);
+        let creator_res = execute(deps.as_mut(), env, mock_info("creator", &[]), ExecuteMsg::ClaimCreatorRewards {}).unwrap();
+        for msg in res.messages.iter().chain(&creator_res.messages) {
+            let CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) = &msg.msg else {
+                panic!("unexpected message {msg:?}");
+            };
+            if let (true, Cw20ExecuteMsg::Mint { amount, .. }) = (contract_addr == "no_token", from_json(msg).unwrap()) {
+                minted += amount;
+            }
+        }
+        assert!(supply + minted <= Uint128::new(MAX_TOKEN_SUPPLY));
+    }
+
+    #[test]
+    fn test_paid_list_sale() {
         let (mut deps, env) = setup_contract();
-        
-        // Test successful stake
-        let info = mock_info(
-            "user1",
-            &[Coin {
+        let buy = ExecuteMsg::BuyTokens {
+            position: YES,
+            amount: Uint128::new(3),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(2, XION_DENOM)]), buy.clone()).unwrap_err();
+        assert_eq!(err, ContractError::NoSale {});
+
+        let max = allocation(PAID_LIST_PERCENTAGE);
+        let init_msg = |curve: PriceCurve, max_per_side: Option<Uint128>| InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Native {
                 denom: XION_DENOM.to_string(),
-                amount: Uint128::new(100),
-            }],
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: Some(SaleInfo { curve, max_per_side }),
+        };
+        let half = PriceCurve::Flat { price: Decimal::percent(50) };
+        let invalid = [
+            init_msg(PriceCurve::Flat { price: Decimal::zero() }, None),
+            init_msg(
+                PriceCurve::Linear {
+                    start_price: Decimal::percent(200),
+                    end_price: Decimal::one(),
+                },
+                None,
+            ),
+            init_msg(half.clone(), Some(Uint128::zero())),
+            init_msg(half.clone(), Some(max + Uint128::one())),
+        ];
+        for msg in invalid {
+            let err = instantiate(mock_dependencies().as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap_err();
+            assert_eq!(err, ContractError::InvalidSale { max });
+        }
+
+        // Buyers pay rounded up, sellers get refunds rounded down
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg(half, None)).unwrap();
+        let quote = |deps: Deps, amount: u128| -> SaleQuoteResponse {
+            let msg = QueryMsg::GetSaleQuote {
+                position: YES,
+                amount: Uint128::new(amount),
+            };
+            from_json(query(deps, env.clone(), msg).unwrap()).unwrap()
+        };
+        let res = quote(deps.as_ref(), 3);
+        assert_eq!((res.available, res.cost, res.refund), (max, Some(Uint128::new(2)), None));
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(2, XION_DENOM)]), buy).unwrap();
+        assert_eq!(
+            res.messages[1..],
+            [
+                SubMsg::new(create_mint_msg(&Addr::unchecked("yes_token"), &Addr::unchecked("user1"), Uint128::new(3)).unwrap()),
+                report(PollEvent::Bought { amount: Uint128::new(2) }),
+            ]
         );
+        let res = quote(deps.as_ref(), 3);
+        assert_eq!((res.sold, res.refund), (Uint128::new(3), Some(Uint128::one())));
+    }
 
-        let msg = ExecuteMsg::Stake {
-            amount: Uint128::new(100),
-            position: true,
+    #[test]
+    fn test_stake_limits() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let init_msg = |limits| InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: Some(limits),
+            protocol_fee: None,
+            sale: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        assert_eq!(1, res.attributes.len());
+        let invalid = StakeLimits {
+            min_stake: Some(Uint128::new(50)),
+            max_stake: Some(Uint128::new(10)),
+            ..StakeLimits::default()
+        };
+        let err = instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg(invalid)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidStakeLimits {});
+
+        let limits = StakeLimits {
+            min_stake: Some(Uint128::new(10)),
+            max_stake: Some(Uint128::new(100)),
+            max_user_total: Some(Uint128::new(150)),
+            max_side_total: Some(Uint128::new(200)),
+        };
+        instantiate(deps.as_mut(), env.clone(), mock_info("creator", &[]), init_msg(limits)).unwrap();
+
+        let mut stake = |user: &str, amount: u128, position: u8| {
+            let msg = ExecuteMsg::Stake {
+                amount: Uint128::new(amount),
+                position,
+            };
+            execute(deps.as_mut(), env.clone(), mock_info(user, &[Coin::new(amount, XION_DENOM)]), msg)
+        };
+        assert_eq!(stake("user1", 5, YES).unwrap_err(), ContractError::StakeBelowMinimum { min: Uint128::new(10) });
+        assert_eq!(stake("user1", 101, YES).unwrap_err(), ContractError::StakeAboveMaximum { max: Uint128::new(100) });
+        stake("user1", 100, YES).unwrap();
+        // The user cap counts every position
+        assert_eq!(stake("user1", 60, NO).unwrap_err(), ContractError::UserStakeLimit { max: Uint128::new(150) });
+        stake("user1", 50, NO).unwrap();
+        stake("user2", 100, YES).unwrap();
         assert_eq!(
-            res.attributes[0],
-            ("action", "stake")
+            stake("user3", 10, YES).unwrap_err(),
+            ContractError::SideStakeLimit {
+                position: YES,
+                max: Uint128::new(200),
+            }
         );
 
-        // Test insufficient payment
-        let info = mock_info(
-            "user2",
-            &[Coin {
-                denom: XION_DENOM.to_string(),
-                amount: Uint128::new(50),
-            }],
+        let res: LimitsResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::GetLimits { user: Some("user2".to_string()) }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.side_room, vec![Some(Uint128::zero()), Some(Uint128::new(150))]);
+        assert_eq!(res.user_room, Some(Uint128::new(50)));
+        assert_eq!(res.min_stake, Some(Uint128::new(10)));
+
+        // Switching into a full side is refused as well
+        let msg = ExecuteMsg::SwitchPosition {
+            amount: Uint128::new(50),
+            from: NO,
+            to: YES,
+        };
+        let err = execute(deps.as_mut(), env, mock_info("user1", &[]), msg).unwrap_err();
+        assert_eq!(
+            err,
+            ContractError::SideStakeLimit {
+                position: YES,
+                max: Uint128::new(200),
+            }
         );
+    }
 
-        let msg = ExecuteMsg::Stake {
+    #[test]
+    fn test_claim_rewards() {
+        let (mut deps, mut env) = setup_contract();
+        let stake = ExecuteMsg::Stake {
             amount: Uint128::new(100),
-            position: true,
+            position: YES,
         };
-        let err = execute(deps.as_mut(), env.clone(), info, msg).unwrap_err();
-        assert_eq!(err, ContractError::InvalidPaymentAmount {});
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), stake.clone()).unwrap();
+
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap_err();
+        assert_eq!(err, ContractError::NoRewardsToClaim {});
+
+        // user2 joins halfway through the first epoch with the same amount
+        env.block.time = env.block.time.plus_seconds(125);
+        execute(deps.as_mut(), env.clone(), mock_info("user2", &[Coin::new(100, XION_DENOM)]), stake).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(125);
+        let distribution = EPOCHS.load(deps.as_ref().storage, 1).unwrap().total_distribution;
+        let pending = |deps: Deps, env: Env, user: &str| -> Uint128 {
+            let res: PendingRewardsResponse = from_json(
+                query(deps, env, QueryMsg::GetPendingRewards { user: user.to_string() }).unwrap(),
+            )
+            .unwrap();
+            res.rewards[YES as usize].pending
+        };
+        // user1 had the whole epoch to itself for half of it, then shared it evenly
+        let early = pending(deps.as_ref(), env.clone(), "user1");
+        let late = pending(deps.as_ref(), env.clone(), "user2");
+        assert_eq!(early, distribution.multiply_ratio(3u128, 4u128));
+        assert_eq!(late, distribution.multiply_ratio(1u128, 4u128));
 
-        // Test wrong denom
-        let info = mock_info(
-            "user3",
-            &[Coin {
-                denom: "invalid".to_string(),
-                amount: Uint128::new(100),
-            }],
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            create_mint_msg(&Addr::unchecked("yes_token"), &Addr::unchecked("user1"), early).unwrap()
         );
+        let res: PendingRewardsResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::GetPendingRewards { user: "user1".to_string() }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.rewards[YES as usize].pending, Uint128::zero());
+        assert_eq!(res.rewards[YES as usize].claimed, early);
+        let err = execute(deps.as_mut(), env, mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap_err();
+        assert_eq!(err, ContractError::NoRewardsToClaim {});
+    }
 
-        let msg = ExecuteMsg::Stake {
+    #[test]
+    fn test_claim_epoch_rewards() {
+        let (mut deps, mut env) = setup_contract();
+        let stake = ExecuteMsg::Stake {
             amount: Uint128::new(100),
-            position: true,
+            position: YES,
         };
-        let err = execute(deps.as_mut(), env, info, msg).unwrap_err();
-        assert_eq!(err, ContractError::NoXionPayment {});
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), stake).unwrap();
+        let first = EPOCHS.load(deps.as_ref().storage, 1).unwrap().total_distribution;
+        let second = EPOCHS.load(deps.as_ref().storage, 2).unwrap().total_distribution;
+
+        // A fifth into the second epoch
+        env.block.time = env.block.time.plus_seconds(300);
+        let res: ClaimableResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::GetClaimable { user: "user1".to_string() }).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.epochs,
+            vec![
+                ClaimableEpoch {
+                    epoch_number: 1,
+                    is_ended: true,
+                    is_claimed: false,
+                    amounts: vec![first, Uint128::zero()],
+                },
+                ClaimableEpoch {
+                    epoch_number: 2,
+                    is_ended: false,
+                    is_claimed: false,
+                    amounts: vec![second.multiply_ratio(1u128, 5u128), Uint128::zero()],
+                },
+            ]
+        );
+
+        let claim = |epochs: Vec<u64>| ExecuteMsg::ClaimEpochRewards { epochs };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), claim(vec![1, 2])).unwrap_err();
+        assert_eq!(err, ContractError::EpochNotEnded {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), claim(vec![9])).unwrap_err();
+        assert_eq!(err, ContractError::EpochNotStarted {});
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user2", &[]), claim(vec![1])).unwrap_err();
+        assert_eq!(err, ContractError::NoRewardsToClaim {});
+
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), claim(vec![1])).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            create_mint_msg(&Addr::unchecked("yes_token"), &Addr::unchecked("user1"), first).unwrap()
+        );
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), claim(vec![1])).unwrap_err();
+        assert_eq!(err, ContractError::EpochAlreadyClaimed { epoch: 1 });
+
+        // The running epoch is still claimable through ClaimRewards
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            create_mint_msg(&Addr::unchecked("yes_token"), &Addr::unchecked("user1"), second.multiply_ratio(1u128, 5u128))
+                .unwrap()
+        );
+
+        // Once the second epoch ended, ClaimRewards drains and marks it as well
+        env.block.time = env.block.time.plus_seconds(250);
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+        let res: ClaimableResponse = from_json(
+            query(deps.as_ref(), env.clone(), QueryMsg::GetClaimable { user: "user1".to_string() }).unwrap(),
+        )
+        .unwrap();
+        assert!(res.epochs.iter().filter(|epoch| epoch.is_ended).all(|epoch| epoch.is_claimed));
+        assert!(res.epochs[1].is_ended);
+        let err = execute(deps.as_mut(), env, mock_info("user1", &[]), claim(vec![2])).unwrap_err();
+        assert_eq!(err, ContractError::EpochAlreadyClaimed { epoch: 2 });
+    }
+
+    /// Counts the bytes read and written through it, which is what storage gas is charged on
+    struct MeteredStorage<'a> {
+        inner: &'a mut MockStorage,
+        bytes: Cell<usize>,
+    }
+
+    impl Storage for MeteredStorage<'_> {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            let value = self.inner.get(key);
+            self.bytes.set(self.bytes.get() + key.len() + value.as_ref().map_or(0, Vec::len));
+            value
+        }
+
+        fn range<'b>(&'b self, start: Option<&[u8]>, end: Option<&[u8]>, order: Order) -> Box<dyn Iterator<Item = Record> + 'b> {
+            Box::new(self.inner.range(start, end, order).inspect(|(key, value)| {
+                self.bytes.set(self.bytes.get() + key.len() + value.len());
+            }))
+        }
+
+        fn set(&mut self, key: &[u8], value: &[u8]) {
+            self.bytes.set(self.bytes.get() + key.len() + value.len());
+            self.inner.set(key, value);
+        }
+
+        fn remove(&mut self, key: &[u8]) {
+            self.bytes.set(self.bytes.get() + key.len());
+            self.inner.remove(key);
+        }
     }
 
     #[test]
-    fn test_withdraw_stake() {
-        let (mut deps, mut env) = setup_contract();
-        
-        // First stake some XION
-        let info = mock_info(
-            "user1",
-            &[Coin {
-                denom: XION_DENOM.to_string(),
+    fn test_stake_cost_stays_flat() {
+        let (mut deps, env) = setup_contract();
+        let stake_bytes = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, staker: &str| {
+            let mut storage = MeteredStorage {
+                inner: &mut deps.storage,
+                bytes: Cell::new(0),
+            };
+            let msg = ExecuteMsg::Stake {
                 amount: Uint128::new(100),
-            }],
-        );
+                position: YES,
+            };
+            let deps_mut = DepsMut {
+                storage: &mut storage,
+                api: &deps.api,
+                querier: QuerierWrapper::new(&deps.querier),
+            };
+            execute(deps_mut, env.clone(), mock_info(staker, &[Coin::new(100, XION_DENOM)]), msg).unwrap();
+            storage.bytes.get()
+        };
 
-        let msg = ExecuteMsg::Stake {
-            amount: Uint128::new(100),
-            position: true,
+        let mut early = 0;
+        for i in 0..10_000 {
+            let bytes = stake_bytes(&mut deps, &format!("staker{i:05}"));
+            if i == 100 {
+                early = bytes;
+            }
+        }
+        let late = stake_bytes(&mut deps, "staker10000");
+        // Only the digits of growing totals and ids differ
+        assert!(late <= early + early / 20, "{early} bytes at 100 stakers, {late} at 10000");
+        assert_eq!(query_epoch_info(deps.as_ref(), 1).unwrap().num_stakers, 10_001);
+    }
+
+    #[test]
+    fn test_migrate_legacy_storage() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let now = env.block.time.seconds();
+        let user1 = Addr::unchecked("user1");
+        let user2 = Addr::unchecked("user2");
+
+        // A first-release poll 150 seconds into its second epoch, whose crank went through
+        // user1 in the first one
+        let storage = deps.as_mut().storage;
+        let config = legacy::PollConfig {
+            capy_core: Addr::unchecked("capy_core"),
+            poll_creator: Addr::unchecked("creator"),
+            yes_token: Addr::unchecked("yes_token"),
+            no_token: Addr::unchecked("no_token"),
+            end_timestamp: now + 600,
+            total_staked: Uint128::zero(),
+            is_resolved: false,
+            winning_position: None,
+            denom: XION_DENOM.to_string(),
         };
-        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        legacy::POLL_CONFIG.save(storage, &config).unwrap();
+        legacy::EPOCH_DURATION.save(storage, &250).unwrap();
+        legacy::NUM_EPOCHS.save(storage, &4).unwrap();
+        CURRENT_EPOCH.save(storage, &2).unwrap();
+        legacy::TOTAL_YES_STAKED.save(storage, &Uint128::new(130)).unwrap();
+        legacy::TOTAL_NO_STAKED.save(storage, &Uint128::new(120)).unwrap();
+        let legacy_epoch = |start_time: u64, total_epoch_staked: u128, last_processed_index: u64| EpochInfo {
+            start_time,
+            end_time: start_time + 250,
+            total_distribution: Uint128::new(1_000_000),
+            is_distributed: false,
+            total_epoch_staked: Uint128::new(total_epoch_staked),
+            last_processed_index,
+            num_stakers: 0,
+        };
+        EPOCHS.save(storage, 1, &legacy_epoch(now - 400, 220, 1)).unwrap();
+        EPOCHS.save(storage, 2, &legacy_epoch(now - 150, 30, 0)).unwrap();
+        let legacy_stake = |amount: u128, position: bool| legacy::Stake {
+            amount: Uint128::new(amount),
+            position,
+            withdrawn: false,
+        };
+        legacy::USER_STAKES.save(storage, (&user1, 1), &vec![legacy_stake(100, true), legacy_stake(50, false)]).unwrap();
+        legacy::USER_STAKES.save(storage, (&user1, 2), &vec![legacy_stake(30, true)]).unwrap();
+        legacy::USER_STAKES.save(storage, (&user2, 1), &vec![legacy_stake(70, false)]).unwrap();
+        legacy::EPOCH_STAKERS.save(storage, 1, &vec![user1.clone(), user2.clone()]).unwrap();
+        legacy::EPOCH_STAKERS.save(storage, 2, &vec![user1.clone()]).unwrap();
+        let legacy_activity = |user: &Addr, height: u64| legacy::PollActivity {
+            user: user.clone(),
+            activity_type: ActivityType::Stake,
+            amount: Some(Uint128::new(100)),
+            position: Some(true),
+            timestamp: height * 5,
+            block_height: height,
+        };
+        legacy::ACTIVITIES
+            .save(storage, 10, &vec![legacy_activity(&user1, 10), legacy_activity(&user2, 10)])
+            .unwrap();
+        legacy::ACTIVITIES.save(storage, 11, &vec![legacy_activity(&user1, 11)]).unwrap();
 
-        // Move time forward and resolve poll
-        env.block.time = env.block.time.plus_seconds(1001);
-        let resolve_info = mock_info("capy_core", &[]);
-        let msg = ExecuteMsg::ResolvePoll {
-            winning_position: true,
+        let res = migrate(deps.as_mut(), env.clone(), MigrateMsg {}).unwrap();
+        assert_eq!(res.attributes[1], ("stakes", "4"));
+        assert_eq!(res.attributes[2], ("activities", "3"));
+
+        let config = POLL_CONFIG.load(deps.as_ref().storage).unwrap();
+        assert_eq!(config.outcome_token(YES).unwrap(), &Addr::unchecked("yes_token"));
+        assert_eq!(config.outcome_token(NO).unwrap(), &Addr::unchecked("no_token"));
+        assert_eq!(config.collateral, Collateral::Native { denom: XION_DENOM.to_string() });
+        let schedule = EPOCH_SCHEDULE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(schedule.start_time, now - 400);
+        assert_eq!(schedule.epoch_at(now), 2);
+        assert_eq!(TOTAL_STAKED.load(deps.as_ref().storage, YES).unwrap(), Uint128::new(130));
+        assert_eq!(TOTAL_STAKED.load(deps.as_ref().storage, NO).unwrap(), Uint128::new(120));
+
+        let stakes = query_user_stakes(deps.as_ref(), "user1".to_string(), 1).unwrap().stakes;
+        assert_eq!(
+            stakes.iter().map(|stake| (stake.amount.u128(), stake.position)).collect::<Vec<_>>(),
+            vec![(100, YES), (50, NO)]
+        );
+        assert_eq!(query_user_stakes(deps.as_ref(), "user2".to_string(), 1).unwrap().stakes[0].epoch, 1);
+        let first = EPOCHS.load(deps.as_ref().storage, 1).unwrap();
+        assert_eq!((first.num_stakers, first.total_epoch_staked), (2, Uint128::new(220)));
+        assert!(!first.is_distributed);
+        assert_eq!(query_epoch_info(deps.as_ref(), 2).unwrap().num_stakers, 1);
+        assert_eq!(user_total(deps.as_ref().storage, &user1).unwrap(), Uint128::new(180));
+
+        // The first epoch is split between its stakes, user1 already had theirs minted. The
+        // second one emitted three fifths so far, all of it to user1's stake placed in it.
+        let first = first.total_distribution;
+        let second = query_epoch_info(deps.as_ref(), 2).unwrap().total_distribution;
+        let rewards = |deps: Deps, user: &str| -> Vec<(Uint128, Uint128)> {
+            query_pending_rewards(deps, env.clone(), user.to_string())
+                .unwrap()
+                .rewards
+                .iter()
+                .map(|reward| (reward.pending, reward.claimed))
+                .collect()
         };
-        execute(deps.as_mut(), env.clone(), resolve_info, msg).unwrap();
+        assert_eq!(
+            rewards(deps.as_ref(), "user1"),
+            vec![
+                (second.multiply_ratio(3u128, 5u128), first.multiply_ratio(100u128, 220u128)),
+                (Uint128::zero(), first.multiply_ratio(50u128, 220u128)),
+            ]
+        );
+        assert_eq!(rewards(deps.as_ref(), "user2"), vec![(Uint128::zero(), Uint128::zero()), (first.multiply_ratio(70u128, 220u128), Uint128::zero())]);
+        assert!(CLAIMED_EPOCHS.has(deps.as_ref().storage, (&user1, 1)));
+        assert!(!CLAIMED_EPOCHS.has(deps.as_ref().storage, (&user2, 1)));
+
+        let activities = query_activities(deps.as_ref(), None, None).unwrap().activities;
+        assert_eq!(activities.iter().map(|activity| activity.block_height).collect::<Vec<_>>(), vec![11, 10, 10]);
+        let activities = query_activities(deps.as_ref(), Some(10), None).unwrap().activities;
+        assert_eq!(activities[0].position, Some(YES));
+
+        assert!(legacy::USER_STAKES.is_empty(deps.as_ref().storage));
+        assert!(legacy::EPOCH_STAKERS.is_empty(deps.as_ref().storage));
+        assert!(legacy::ACTIVITIES.is_empty(deps.as_ref().storage));
+        assert!(legacy::TOTAL_YES_STAKED.may_load(deps.as_ref().storage).unwrap().is_none());
+        assert!(legacy::EPOCH_DURATION.may_load(deps.as_ref().storage).unwrap().is_none());
+
+        // New stakes continue after the migrated ids, and migrated ones unstake and stream
+        let msg = ExecuteMsg::Stake {
+            amount: Uint128::new(10),
+            position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user3", &[Coin::new(10, XION_DENOM)]), msg).unwrap();
+        assert_eq!(STAKE_COUNT.load(deps.as_ref().storage).unwrap(), 5);
+        assert_eq!(ACTIVITY_COUNT.load(deps.as_ref().storage).unwrap(), 4);
+        let msg = ExecuteMsg::Unstake {
+            amount: Uint128::new(50),
+            position: NO,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), msg).unwrap();
+        assert_eq!(rewards(deps.as_ref(), "user1")[NO as usize], (Uint128::zero(), Uint128::zero()));
 
-        // Test withdrawal
-        let msg = ExecuteMsg::WithdrawStake {};
-        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = execute(deps.as_mut(), env, mock_info("user2", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
 
-        assert_eq!(1, res.messages.len());
-        match &res.messages[0].msg {
-            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
-                assert_eq!(to_address, "user1");
-                assert_eq!(
-                    amount,
-                    &vec![Coin {
-                        denom: XION_DENOM.to_string(),
-                        amount: Uint128::new(100),
-                    }]
-                );
-            }
-            _ => panic!("Expected BankMsg::Send"),
+    #[test]
+    fn test_user_position() {
+        let (mut deps, mut env) = setup_contract();
+        let stake = |amount: u128, position: u8| ExecuteMsg::Stake {
+            amount: Uint128::new(amount),
+            position,
+        };
+        // Core hears about the first stake of a user only
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(100, XION_DENOM)]), stake(100, YES)).unwrap();
+        let hook = WasmMsg::Execute {
+            contract_addr: "capy_core".to_string(),
+            msg: to_json_binary(&CoreExecuteMsg::RecordParticipation {
+                user: "user1".to_string(),
+            })
+            .unwrap(),
+            funds: vec![],
+        };
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::reply_on_error(hook, REPLY_RECORD_PARTICIPATION),
+                report(PollEvent::Staked { amount: Uint128::new(100) }),
+            ]
+        );
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user1", &[Coin::new(50, XION_DENOM)]), stake(50, NO)).unwrap();
+        assert_eq!(res.messages, vec![report(PollEvent::Staked { amount: Uint128::new(50) })]);
+        execute(deps.as_mut(), env.clone(), mock_info("user2", &[Coin::new(300, XION_DENOM)]), stake(300, NO)).unwrap();
+
+        env.block.time = env.block.time.plus_seconds(300);
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+
+        let position = query_user_position(deps.as_ref(), env.clone(), "user1".to_string()).unwrap();
+        assert_eq!(position.positions[YES as usize].staked, Uint128::new(100));
+        assert_eq!(position.positions[NO as usize].staked, Uint128::new(50));
+        assert_eq!(position.withdrawn, Uint128::zero());
+        assert_eq!(position.epochs.len(), 2);
+        for entry in &position.positions {
+            let earned = position.epochs.iter().map(|epoch| epoch.earned[entry.position as usize]).sum::<Uint128>();
+            assert_eq!(entry.claimed, earned);
+            assert_eq!(entry.pending, Uint128::zero());
         }
+        // Both stakes were there for the whole first epoch
+        let first = &position.epochs[0];
+        assert!(first.earned[YES as usize].abs_diff(first.earned[NO as usize] * Uint128::new(2)) <= Uint128::one());
+        // Blitz odds follow the stakes, 100 out of 450 for YES
+        let odds = Decimal::from_ratio(100u128, 450u128);
+        let retention = odds + (Decimal::one() - odds) * Decimal::percent(5);
+        assert_eq!(
+            position.positions[YES as usize].blitz_value,
+            position.positions[YES as usize].claimed.mul_floor(retention)
+        );
+
+        let list = |deps: Deps, position: u8, start_after: Option<&str>, limit: Option<u32>| {
+            query_stakers(deps, position, start_after.map(str::to_string), limit)
+                .unwrap()
+                .stakers
+                .into_iter()
+                .map(|staker| (staker.user, staker.staked.u128()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(list(deps.as_ref(), NO, None, None), vec![("user1".to_string(), 50), ("user2".to_string(), 300)]);
+        assert_eq!(list(deps.as_ref(), NO, None, Some(1)), vec![("user1".to_string(), 50)]);
+        assert_eq!(list(deps.as_ref(), NO, Some("user1"), None), vec![("user2".to_string(), 300)]);
+        assert_eq!(list(deps.as_ref(), YES, None, None), vec![("user1".to_string(), 100)]);
+
+        // Unstaking burns what the stake earned and drops the user from the list
+        let msg = ExecuteMsg::Unstake {
+            amount: Uint128::new(50),
+            position: NO,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), msg).unwrap();
+        assert_eq!(list(deps.as_ref(), NO, None, None), vec![("user2".to_string(), 300)]);
+        let position = query_user_position(deps.as_ref(), env.clone(), "user1".to_string()).unwrap();
+        assert_eq!(position.withdrawn, Uint128::new(50));
+        assert_eq!(position.positions[NO as usize].claimed, Uint128::zero());
+        assert!(position.epochs.iter().all(|epoch| epoch.earned[NO as usize].is_zero()));
+
+        env.block.time = env.block.time.plus_seconds(700);
+        let msg = ExecuteMsg::ResolvePoll {
+            winning_position: YES,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("capy_core", &[]), msg).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("user1", &[]), ExecuteMsg::WithdrawStake {}).unwrap();
+
+        // Withdrawing counts as an exit too, the rewards stay claimable
+        assert_eq!(list(deps.as_ref(), YES, None, None), vec![]);
+        let position = query_user_position(deps.as_ref(), env.clone(), "user1".to_string()).unwrap();
+        let yes = &position.positions[YES as usize];
+        assert_eq!(position.withdrawn, Uint128::new(150));
+        assert_eq!(yes.staked, Uint128::zero());
+        assert!(!yes.pending.is_zero());
+        assert_eq!(position.epochs.len(), 4);
+        assert_eq!(yes.blitz_value, yes.claimed + yes.pending);
+        let pending = yes.pending;
+        let res = execute(deps.as_mut(), env, mock_info("user1", &[]), ExecuteMsg::ClaimRewards {}).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            create_mint_msg(&Addr::unchecked("yes_token"), &Addr::unchecked("user1"), pending).unwrap()
+        );
     }
 }
-
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -24,6 +24,12 @@ pub enum ContractError {
     #[error("No XION payment found")]
     NoXionPayment {},
 
+    #[error("Collateral of this poll is a cw20, send it through the token's Send")]
+    Cw20CollateralRequired {},
+
+    #[error("Sender is not the collateral token of this poll")]
+    InvalidCollateral {},
+
     #[error("Invalid payment amount")]
     InvalidPaymentAmount {},
 
@@ -51,6 +57,120 @@ pub enum ContractError {
     #[error("Epoch already distributed")]
     EpochAlreadyDistributed {},
 
+    #[error("Insufficient stake: {available} available")]
+    InsufficientStake { available: Uint128 },
+
+    #[error("No rewards to claim")]
+    NoRewardsToClaim {},
+
+    #[error("Rewards of epoch {epoch} were already claimed")]
+    EpochAlreadyClaimed { epoch: u64 },
+
+    #[error("Cannot switch a position to itself")]
+    SamePosition {},
+
+    #[error("Invalid stake limits: maximums must be above zero and the minimum at most the maximum stake")]
+    InvalidStakeLimits {},
+
+    #[error("Stake below the minimum of {min}")]
+    StakeBelowMinimum { min: Uint128 },
+
+    #[error("Stake above the maximum of {max}")]
+    StakeAboveMaximum { max: Uint128 },
+
+    #[error("Stake would take the user above {max} in total")]
+    UserStakeLimit { max: Uint128 },
+
+    #[error("Stake would take position {position} above {max} in total")]
+    SideStakeLimit { position: u8, max: Uint128 },
+
+    #[error("Invalid exit penalty: must be at most 1")]
+    InvalidExitPenalty {},
+
+    #[error("Invalid protocol fee: must be below 1")]
+    InvalidProtocolFee {},
+
+    #[error("Epoch rewards above the distribution allocation of {max}")]
+    EpochRewardsAboveAllocation { max: Uint128 },
+
+    #[error("Invalid sale: prices must be above zero and not fall, the cap between zero and the paid-list allocation of {max}")]
+    InvalidSale { max: Uint128 },
+
+    #[error("This poll has no paid-list sale")]
+    NoSale {},
+
+    #[error("Only {available} tokens of position {position} left on sale")]
+    SaleCapExceeded { position: u8, available: Uint128 },
+
+    #[error("Only the {purchased} tokens of position {position} bought on the sale can be sold back")]
+    SellBackExceeded { position: u8, purchased: Uint128 },
+
+    #[error("Sale costs {cost}")]
+    InvalidSalePayment { cost: Uint128 },
+
+    #[error("No prize to redeem")]
+    NoPrizeToRedeem {},
+
     #[error("No stakes to withdraw")]
     NoStakesToWithdraw {},
+
+    #[error("No oracle configured for this poll")]
+    NoOracleConfigured {},
+
+    #[error("Oracle has not reported an outcome yet")]
+    OracleOutcomeUnavailable {},
+
+    #[error("Optimistic resolution is not enabled for this poll")]
+    OptimisticResolutionDisabled {},
+
+    #[error("Invalid bond: expected {expected} {denom}")]
+    InvalidBond { expected: Uint128, denom: String },
+
+    #[error("No outcome has been proposed")]
+    NoOutcomeProposed {},
+
+    #[error("An outcome has already been proposed")]
+    OutcomeAlreadyProposed {},
+
+    #[error("Proposed outcome is under dispute")]
+    OutcomeDisputed {},
+
+    #[error("Proposed outcome is not under dispute")]
+    OutcomeNotDisputed {},
+
+    #[error("Proposed outcome has already been finalized")]
+    OutcomeAlreadyFinalized {},
+
+    #[error("Challenge period is still running")]
+    ChallengePeriodActive {},
+
+    #[error("Challenge period has ended")]
+    ChallengePeriodEnded {},
+
+    #[error("Resolution grace period ends at {ends_at}")]
+    GracePeriodActive { ends_at: u64 },
+
+    #[error("Invalid number of outcomes: min {min}, max {max}")]
+    InvalidOutcomeCount { min: usize, max: usize },
+
+    #[error("Invalid position {position}: poll has {num_outcomes} outcomes")]
+    InvalidPosition { position: u8, num_outcomes: usize },
+
+    #[error("Invalid epoch count: between 1 and {max}, and at most one epoch per second of duration")]
+    InvalidEpochCount { max: u64 },
+
+    #[error("Invalid epoch decay: must be above 0 and at most 1")]
+    InvalidEpochDecay {},
+
+    #[error("Invalid scalar bounds: lower bound must be below upper bound")]
+    InvalidScalarBounds {},
+
+    #[error("Scalar polls need exactly two outcomes (LONG and SHORT)")]
+    InvalidScalarOutcomes {},
+
+    #[error("Scalar polls can only be resolved with a numeric value")]
+    ScalarPoll {},
+
+    #[error("Poll is not a scalar poll")]
+    NotScalarPoll {},
 } 
\ No newline at end of file
@@ -1,13 +1,7 @@
-use super::*;
-use crate::msg::*;
-use crate::contract::{instantiate, execute, query};
+use crate::contract::{instantiate, execute, query, reply};
 use crate::error::ContractError;
-use crate::state::{calculate_epoch_distribution};
-use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::{
-    coins, from_json, Addr, Coin, Uint128, CosmosMsg, BankMsg,
-    Response, StdResult, to_json_binary,
-};
+use crate::state::{Collateral, PriceCurve, ProposalStatus, NO, YES};
+use cosmwasm_std::{Uint128, CosmosMsg, BankMsg};
 
 const XION_DENOM: &str = "uxion";
 
@@ -16,7 +10,26 @@ mod tests {
     use super::*;
     use crate::msg::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, from_json, Addr, Coin};
+    use cosmwasm_std::{
+        coins, from_json, to_json_binary, Addr, Coin, ContractResult, Decimal, SystemError, SystemResult, WasmQuery,
+    };
+    use cw_multi_test::{App, ContractWrapper, Executor};
+    use xion_capypolls_mock_oracle::msg::{
+        ExecuteMsg as OracleExecuteMsg, InstantiateMsg as OracleInstantiateMsg,
+    };
+
+    fn binary_outcomes(yes_token: &str, no_token: &str) -> Vec<OutcomeInfo> {
+        vec![
+            OutcomeInfo {
+                name: "YES".to_string(),
+                token: yes_token.to_string(),
+            },
+            OutcomeInfo {
+                name: "NO".to_string(),
+                token: no_token.to_string(),
+            },
+        ]
+    }
 
     #[test]
     fn full_poll_lifecycle() {
@@ -27,10 +40,19 @@ mod tests {
         let init_msg = InstantiateMsg {
             capy_core: "capy_core".to_string(),
             poll_creator: "creator".to_string(),
-            yes_token: "yes_token".to_string(),
-            no_token: "no_token".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
             duration: 1000,
-            denom: XION_DENOM.to_string(),
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
         };
         let info = mock_info("creator", &[]);
         instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
@@ -50,7 +72,7 @@ mod tests {
             stake_info,
             ExecuteMsg::Stake {
                 amount: stake_amount,
-                position: true,
+                position: YES,
             },
         )
         .unwrap();
@@ -58,19 +80,40 @@ mod tests {
         // Query total staked
         let query_msg = QueryMsg::GetTotalStaked {};
         let res: TotalStakedResponse =
-            from_json(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
-        assert_eq!(res.total_yes, stake_amount);
-        assert_eq!(res.total_no, Uint128::zero());
-        assert_eq!(res.denom, XION_DENOM);
+            from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(res.totals, vec![stake_amount, Uint128::zero()]);
+        assert_eq!(
+            res.collateral,
+            Collateral::Native {
+                denom: XION_DENOM.to_string()
+            }
+        );
 
-        // Resolve poll
+        // Resolve poll once it has ended; the blitz reads the losing token's supply
+        let mut env = env;
+        env.block.time = env.block.time.plus_seconds(1001);
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { msg, .. } if matches!(from_json(msg), Ok(cw20::Cw20QueryMsg::Minter {})) => {
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&None::<cw20::MinterResponse>).unwrap()))
+            }
+            WasmQuery::Smart { .. } => {
+                let token_info = cw20::TokenInfoResponse {
+                    name: "NO Token".to_string(),
+                    symbol: "NOO".to_string(),
+                    decimals: 18,
+                    total_supply: Uint128::new(1_000),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_json_binary(&token_info).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::Unknown {}),
+        });
         let resolve_info = mock_info("capy_core", &[]);
         execute(
             deps.as_mut(),
             env.clone(),
             resolve_info,
             ExecuteMsg::ResolvePoll {
-                winning_position: true,
+                winning_position: YES,
             },
         )
         .unwrap();
@@ -85,8 +128,8 @@ mod tests {
         )
         .unwrap();
 
-        // Verify withdrawal message
-        assert_eq!(1, res.messages.len());
+        // Verify withdrawal message, followed by the report to core
+        assert_eq!(2, res.messages.len());
         match &res.messages[0].msg {
             CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
                 assert_eq!(to_address, "user1");
@@ -111,10 +154,19 @@ mod tests {
         let init_msg = InstantiateMsg {
             capy_core: "capy_core".to_string(),
             poll_creator: "creator".to_string(),
-            yes_token: "yes_token".to_string(),
-            no_token: "no_token".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
             duration: 1000,
-            denom: XION_DENOM.to_string(),
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
         };
         let info = mock_info("creator", &[]);
         instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
@@ -134,7 +186,7 @@ mod tests {
             stake_info,
             ExecuteMsg::Stake {
                 amount: stake_amount,
-                position: true,
+                position: YES,
             },
         )
         .unwrap();
@@ -156,7 +208,7 @@ mod tests {
             stake_info_2,
             ExecuteMsg::Stake {
                 amount: stake_amount,
-                position: false,
+                position: NO,
             },
         )
         .unwrap();
@@ -164,8 +216,10 @@ mod tests {
         // Query epoch info
         let query_msg = QueryMsg::GetEpochInfo { epoch_number: 2 };
         let res: EpochInfoResponse =
-            from_json(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
-        assert_eq!(res.total_distribution, calculate_epoch_distribution(2));
+            from_json(query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        let schedule: EpochScheduleResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEpochSchedule {}).unwrap()).unwrap();
+        assert_eq!(res.total_distribution, schedule.epochs[1].total_distribution);
         assert_eq!(res.num_stakers, 1);
         assert!(!res.is_distributed);
     }
@@ -179,10 +233,19 @@ mod tests {
         let init_msg = InstantiateMsg {
             capy_core: "capy_core".to_string(),
             poll_creator: "creator".to_string(),
-            yes_token: "yes_token".to_string(),
-            no_token: "no_token".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
             duration: 1000,
-            denom: XION_DENOM.to_string(),
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
         };
         let info = mock_info("creator", &[]);
         instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
@@ -202,7 +265,7 @@ mod tests {
             stake_info,
             ExecuteMsg::Stake {
                 amount: stake_amount,
-                position: true,
+                position: YES,
             },
         )
         .unwrap_err();
@@ -222,7 +285,7 @@ mod tests {
             stake_info,
             ExecuteMsg::Stake {
                 amount: stake_amount,
-                position: true,
+                position: YES,
             },
         )
         .unwrap_err();
@@ -246,7 +309,7 @@ mod tests {
             env.clone(),
             resolve_info,
             ExecuteMsg::ResolvePoll {
-                winning_position: true,
+                winning_position: YES,
             },
         )
         .unwrap_err();
@@ -262,10 +325,19 @@ mod tests {
         let init_msg = InstantiateMsg {
             capy_core: "capy_core".to_string(),
             poll_creator: "creator".to_string(),
-            yes_token: "yes_token".to_string(),
-            no_token: "no_token".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
             duration: 1000,
-            denom: XION_DENOM.to_string(),
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
         };
         let info = mock_info("creator", &[]);
         instantiate(deps.as_mut(), env.clone(), info.clone(), init_msg).unwrap();
@@ -285,7 +357,7 @@ mod tests {
             stake_info,
             ExecuteMsg::Stake {
                 amount: stake_amount,
-                position: true,
+                position: YES,
             },
         )
         .unwrap();
@@ -293,15 +365,23 @@ mod tests {
         // Move to end of first epoch
         env.block.time = env.block.time.plus_seconds(251);
 
-        // Try to distribute rewards for epoch 2 (not started)
+        // Try to distribute rewards for epoch 3 (not started) and epoch 2 (still running)
         let err = execute(
             deps.as_mut(),
             env.clone(),
             info.clone(),
-            ExecuteMsg::DistributeEpochRewards { epoch_number: 2 },
+            ExecuteMsg::DistributeEpochRewards { epoch_number: 3 },
         )
         .unwrap_err();
         assert_eq!(err, ContractError::EpochNotStarted {});
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::DistributeEpochRewards { epoch_number: 2 },
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::EpochNotEnded {});
 
         // Distribute rewards for epoch 1
         let res = execute(
@@ -331,5 +411,907 @@ mod tests {
         )
         .unwrap_err();
         assert_eq!(err, ContractError::EpochAlreadyDistributed {});
+
+        // Epochs that ended without any stake in between can still be distributed
+        env.block.time = env.block.time.plus_seconds(250);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::DistributeEpochRewards { epoch_number: 2 },
+        )
+        .unwrap();
+        let res: EpochInfoResponse =
+            from_json(query(deps.as_ref(), env, QueryMsg::GetEpochInfo { epoch_number: 2 }).unwrap()).unwrap();
+        assert!(res.is_distributed);
+    }
+
+    /// Deploys a mock oracle (contract0), one cw20 per outcome symbol and a poll in cw-multi-test.
+    /// The tokens name the poll as minter so resolution can mint.
+    fn setup_multitest(
+        symbols: &[&str],
+        oracle_question: Option<&str>,
+        optimistic: Option<OptimisticInfo>,
+        scalar: Option<ScalarInfo>,
+        sale: Option<SaleInfo>,
+    ) -> (App, Addr, Addr) {
+        let owner = Addr::unchecked("owner");
+        let mut app = App::new(|router, _, storage| {
+            for user in ["proposer", "disputer", "staker1", "staker2"] {
+                router
+                    .bank
+                    .init_balance(storage, &Addr::unchecked(user), coins(1_000, XION_DENOM))
+                    .unwrap();
+            }
+        });
+
+        let oracle_code_id = app.store_code(Box::new(ContractWrapper::new(
+            xion_capypolls_mock_oracle::contract::execute,
+            xion_capypolls_mock_oracle::contract::instantiate,
+            xion_capypolls_mock_oracle::contract::query,
+        )));
+        let token_code_id = app.store_code(Box::new(ContractWrapper::new(
+            cw20_base::contract::execute,
+            cw20_base::contract::instantiate,
+            cw20_base::contract::query,
+        )));
+        let poll_code_id = app.store_code(Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply)));
+
+        let oracle = app
+            .instantiate_contract(oracle_code_id, owner.clone(), &OracleInstantiateMsg {}, &[], "oracle", None)
+            .unwrap();
+
+        // Multi-test assigns sequential addresses, so the poll comes right after its tokens
+        let poll_addr = format!("contract{}", symbols.len() + 1);
+        let token_init = |symbol: &str| cw20_base::msg::InstantiateMsg {
+            name: format!("{} Token", symbol),
+            symbol: symbol.to_string(),
+            decimals: 18,
+            initial_balances: vec![],
+            mint: Some(cw20::MinterResponse {
+                minter: poll_addr.clone(),
+                cap: None,
+            }),
+            marketing: None,
+        };
+        let outcomes = symbols
+            .iter()
+            .map(|symbol| {
+                let token = app
+                    .instantiate_contract(token_code_id, owner.clone(), &token_init(symbol), &[], *symbol, None)
+                    .unwrap();
+                OutcomeInfo {
+                    name: symbol.to_string(),
+                    token: token.to_string(),
+                }
+            })
+            .collect();
+
+        let init_msg = InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes,
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: oracle_question.map(|question_id| OracleInfo {
+                address: oracle.to_string(),
+                question_id: question_id.to_string(),
+            }),
+            optimistic,
+            scalar,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale,
+        };
+        let poll = app
+            .instantiate_contract(poll_code_id, owner, &init_msg, &[], "poll", None)
+            .unwrap();
+        assert_eq!(poll.as_str(), poll_addr);
+
+        (app, poll, oracle)
+    }
+
+    fn balance(app: &App, addr: &str) -> Uint128 {
+        app.wrap().query_balance(addr, XION_DENOM).unwrap().amount
+    }
+
+    #[test]
+    fn resolve_from_oracle_multitest() {
+        let (mut app, poll, oracle) = setup_multitest(&["YES", "NAY"], Some("question-1"), None, None, None);
+        let owner = Addr::unchecked("owner");
+
+        // Cannot resolve before the poll ends
+        let err = app
+            .execute_contract(owner.clone(), poll.clone(), &ExecuteMsg::ResolveFromOracle {}, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::PollStillActive {});
+
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+
+        // Oracle has not answered yet
+        let err = app
+            .execute_contract(owner.clone(), poll.clone(), &ExecuteMsg::ResolveFromOracle {}, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::OracleOutcomeUnavailable {}
+        );
+
+        app.execute_contract(
+            owner.clone(),
+            oracle.clone(),
+            &OracleExecuteMsg::SetOutcome {
+                question_id: "question-1".to_string(),
+                outcome: NO,
+            },
+            &[],
+        )
+        .unwrap();
+
+        // Anyone can crank the resolution once the oracle has reported
+        app.execute_contract(Addr::unchecked("anyone"), poll.clone(), &ExecuteMsg::ResolveFromOracle {}, &[])
+            .unwrap();
+
+        let info: PollInfoResponse = app
+            .wrap()
+            .query_wasm_smart(&poll, &QueryMsg::GetPollInfo {})
+            .unwrap();
+        assert!(info.is_resolved);
+        assert_eq!(info.winning_position, Some(NO));
+        assert_eq!(info.oracle.unwrap().address, oracle);
+
+        let err = app
+            .execute_contract(owner, poll, &ExecuteMsg::ResolveFromOracle {}, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::PollAlreadyResolved {}
+        );
+    }
+
+    #[test]
+    fn optimistic_resolution_undisputed() {
+        let optimistic = OptimisticInfo {
+            bond: Uint128::new(100),
+            challenge_period: 600,
+            arbiter: None,
+        };
+        let (mut app, poll, _) = setup_multitest(&["YES", "NAY"], None, Some(optimistic), None, None);
+        let proposer = Addr::unchecked("proposer");
+
+        // Proposals only open once the poll has ended
+        let err = app
+            .execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::ProposeOutcome { outcome: YES }, &coins(100, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::PollStillActive {});
+
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+
+        // The bond has to match exactly
+        let err = app
+            .execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::ProposeOutcome { outcome: YES }, &coins(50, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::InvalidBond {
+                expected: Uint128::new(100),
+                denom: XION_DENOM.to_string(),
+            }
+        );
+
+        app.execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::ProposeOutcome { outcome: YES }, &coins(100, XION_DENOM))
+            .unwrap();
+        assert_eq!(balance(&app, "proposer"), Uint128::new(900));
+
+        let err = app
+            .execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::ProposeOutcome { outcome: NO }, &coins(100, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::OutcomeAlreadyProposed {});
+
+        let err = app
+            .execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::FinalizeOutcome {}, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::ChallengePeriodActive {});
+
+        app.update_block(|block| block.time = block.time.plus_seconds(600));
+
+        let err = app
+            .execute_contract(Addr::unchecked("disputer"), poll.clone(), &ExecuteMsg::DisputeOutcome {}, &coins(100, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::ChallengePeriodEnded {});
+
+        // Anyone can finalize an unchallenged proposal, which returns the bond
+        app.execute_contract(Addr::unchecked("anyone"), poll.clone(), &ExecuteMsg::FinalizeOutcome {}, &[])
+            .unwrap();
+        assert_eq!(balance(&app, "proposer"), Uint128::new(1_000));
+
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        assert!(info.is_resolved);
+        assert_eq!(info.winning_position, Some(YES));
+
+        let res: ProposalResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetProposal {}).unwrap();
+        assert_eq!(res.proposal.unwrap().status, ProposalStatus::Finalized);
+        assert_eq!(res.challenge_period_end, Some(mock_env().block.time.seconds() + 1001 + 600));
+
+        let err = app
+            .execute_contract(proposer, poll, &ExecuteMsg::FinalizeOutcome {}, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::OutcomeAlreadyFinalized {});
+    }
+
+    #[test]
+    fn optimistic_resolution_disputed() {
+        let optimistic = OptimisticInfo {
+            bond: Uint128::new(100),
+            challenge_period: 600,
+            arbiter: Some("arbiter".to_string()),
+        };
+        let (mut app, poll, _) = setup_multitest(&["YES", "NAY"], None, Some(optimistic), None, None);
+        let proposer = Addr::unchecked("proposer");
+        let disputer = Addr::unchecked("disputer");
+
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+        app.execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::ProposeOutcome { outcome: YES }, &coins(100, XION_DENOM))
+            .unwrap();
+        app.execute_contract(disputer.clone(), poll.clone(), &ExecuteMsg::DisputeOutcome {}, &coins(100, XION_DENOM))
+            .unwrap();
+
+        let res: ProposalResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetProposal {}).unwrap();
+        let proposal = res.proposal.unwrap();
+        assert_eq!(proposal.status, ProposalStatus::Disputed);
+        assert_eq!(proposal.disputer, Some(disputer.clone()));
+
+        // A disputed proposal cannot be finalized without the arbiter
+        app.update_block(|block| block.time = block.time.plus_seconds(600));
+        let err = app
+            .execute_contract(proposer.clone(), poll.clone(), &ExecuteMsg::FinalizeOutcome {}, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::OutcomeDisputed {});
+
+        let err = app
+            .execute_contract(Addr::unchecked("capy_core"), poll.clone(), &ExecuteMsg::SettleDispute { winning_position: NO }, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::Unauthorized {});
+
+        // The arbiter sides with the disputer, who takes both bonds
+        app.execute_contract(Addr::unchecked("arbiter"), poll.clone(), &ExecuteMsg::SettleDispute { winning_position: NO }, &[])
+            .unwrap();
+        assert_eq!(balance(&app, "proposer"), Uint128::new(900));
+        assert_eq!(balance(&app, "disputer"), Uint128::new(1_100));
+        assert_eq!(balance(&app, poll.as_str()), Uint128::zero());
+
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        assert_eq!(info.winning_position, Some(NO));
+    }
+
+    #[test]
+    fn categorical_poll_multitest() {
+        let (mut app, poll, oracle) = setup_multitest(&["RED", "GREEN", "BLUE"], Some("color"), None, None, None);
+        let owner = Addr::unchecked("owner");
+
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        let names: Vec<_> = info.outcomes.iter().map(|outcome| outcome.name.as_str()).collect();
+        assert_eq!(names, vec!["RED", "GREEN", "BLUE"]);
+
+        let res: TotalStakedResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetTotalStaked {}).unwrap();
+        assert_eq!(res.totals, vec![Uint128::zero(); 3]);
+
+        // Positions are bounded by the number of outcomes
+        let err = app
+            .execute_contract(owner.clone(), poll.clone(), &ExecuteMsg::Stake { amount: Uint128::new(100), position: 3 }, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::InvalidPosition {
+                position: 3,
+                num_outcomes: 3,
+            }
+        );
+
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+        app.execute_contract(
+            owner.clone(),
+            oracle,
+            &OracleExecuteMsg::SetOutcome {
+                question_id: "color".to_string(),
+                outcome: 2,
+            },
+            &[],
+        )
+        .unwrap();
+
+        // Both losing outcome tokens get blitzed
+        let res = app
+            .execute_contract(owner, poll.clone(), &ExecuteMsg::ResolveFromOracle {}, &[])
+            .unwrap();
+        let minted: Vec<_> = res
+            .events
+            .iter()
+            .filter(|event| event.ty == "wasm" && event.attributes.iter().any(|attr| attr.value == "mint"))
+            .map(|event| event.attributes[0].value.clone())
+            .collect();
+        assert_eq!(minted, vec![info.outcomes[0].token.to_string(), info.outcomes[1].token.to_string()]);
+
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        assert_eq!(info.winning_position, Some(2));
+    }
+
+    #[test]
+    fn test_outcome_count_bounds() {
+        let mut deps = mock_dependencies();
+        let outcomes = |count: usize| {
+            (0..count)
+                .map(|i| OutcomeInfo {
+                    name: format!("Outcome {}", i),
+                    token: format!("token{}", i),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for count in [1, 17] {
+            let init_msg = InstantiateMsg {
+                capy_core: "capy_core".to_string(),
+                poll_creator: "creator".to_string(),
+                outcomes: outcomes(count),
+                duration: 1000,
+                collateral: CollateralInfo::Native {
+                    denom: XION_DENOM.to_string(),
+                },
+                oracle: None,
+                optimistic: None,
+                scalar: None,
+                epoch_schedule: None,
+                exit_penalty: None,
+                limits: None,
+                protocol_fee: None,
+                sale: None,
+            };
+            let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap_err();
+            assert_eq!(err, ContractError::InvalidOutcomeCount { min: 2, max: 16 });
+        }
+    }
+
+    #[test]
+    fn scalar_poll_multitest() {
+        let scalar = ScalarInfo {
+            lower_bound: Uint128::new(50_000),
+            upper_bound: Uint128::new(150_000),
+        };
+        let (mut app, poll, _) = setup_multitest(&["LONG", "SHORT"], None, None, Some(scalar), None);
+        let capy_core = Addr::unchecked("capy_core");
+
+        // With no stakes the implied value sits in the middle of the range
+        let res: ScalarPayoutResponse = app
+            .wrap()
+            .query_wasm_smart(&poll, &QueryMsg::GetScalarPayout { value: None })
+            .unwrap();
+        assert_eq!(res.value, Uint128::new(100_000));
+        assert!(!res.is_resolved);
+        assert_eq!(res.long_payout, Decimal::percent(50));
+        assert_eq!(res.short_payout, Decimal::percent(50));
+
+        let res: ScalarPayoutResponse = app
+            .wrap()
+            .query_wasm_smart(&poll, &QueryMsg::GetScalarPayout { value: Some(Uint128::new(75_000)) })
+            .unwrap();
+        assert_eq!(res.long_payout, Decimal::percent(25));
+        assert_eq!(res.short_payout, Decimal::percent(75));
+
+        let staker1 = Addr::unchecked("staker1");
+        let stake = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: 0,
+        };
+        app.execute_contract(staker1.clone(), poll.clone(), &stake, &coins(100, XION_DENOM)).unwrap();
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+
+        // Rewards wait for the value, the crank leaves the epoch for later
+        let distribute = ExecuteMsg::DistributeEpochRewards { epoch_number: 1 };
+        app.execute_contract(staker1.clone(), poll.clone(), &distribute, &[]).unwrap();
+        let epoch_info = |app: &App| -> EpochInfoResponse {
+            app.wrap().query_wasm_smart(&poll, &QueryMsg::GetEpochInfo { epoch_number: 1 }).unwrap()
+        };
+        assert!(!epoch_info(&app).is_distributed);
+
+        // Scalar polls only settle on a value, and only through capy_core
+        let err = app
+            .execute_contract(capy_core.clone(), poll.clone(), &ExecuteMsg::ResolvePoll { winning_position: 0 }, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::ScalarPoll {});
+        let resolve = ExecuteMsg::ResolveScalar {
+            value: Uint128::new(200_000),
+        };
+        let err = app
+            .execute_contract(Addr::unchecked("anyone"), poll.clone(), &resolve, &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::Unauthorized {});
+
+        // A value above the range pays LONG in full, so only SHORT is blitzed
+        let res = app.execute_contract(capy_core, poll.clone(), &resolve, &[]).unwrap();
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        let minted: Vec<_> = res
+            .events
+            .iter()
+            .filter(|event| event.ty == "wasm" && event.attributes.iter().any(|attr| attr.value == "mint"))
+            .map(|event| event.attributes[0].value.clone())
+            .collect();
+        assert_eq!(minted, vec![info.outcomes[1].token.to_string()]);
+        assert!(info.is_resolved);
+        assert_eq!(info.winning_position, None);
+        assert_eq!(info.scalar.unwrap().resolved_value, Some(Uint128::new(200_000)));
+
+        let res: ScalarPayoutResponse = app
+            .wrap()
+            .query_wasm_smart(&poll, &QueryMsg::GetScalarPayout { value: None })
+            .unwrap();
+        assert!(res.is_resolved);
+        assert_eq!(res.long_payout, Decimal::one());
+        assert_eq!(res.short_payout, Decimal::zero());
+
+        app.execute_contract(staker1, poll.clone(), &distribute, &[]).unwrap();
+        assert!(epoch_info(&app).is_distributed);
+    }
+
+    #[test]
+    fn test_scalar_validation() {
+        let mut deps = mock_dependencies();
+        let init_msg = |lower: u128, upper: u128, outcomes: Vec<OutcomeInfo>| InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes,
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: Some(ScalarInfo {
+                lower_bound: Uint128::new(lower),
+                upper_bound: Uint128::new(upper),
+            }),
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
+        };
+
+        let msg = init_msg(100, 100, binary_outcomes("long_token", "short_token"));
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        assert_eq!(err, ContractError::InvalidScalarBounds {});
+
+        let mut outcomes = binary_outcomes("long_token", "short_token");
+        outcomes.push(OutcomeInfo {
+            name: "OTHER".to_string(),
+            token: "other_token".to_string(),
+        });
+        let err = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg(0, 100, outcomes)).unwrap_err();
+        assert_eq!(err, ContractError::InvalidScalarOutcomes {});
+    }
+
+    #[test]
+    fn test_epoch_schedule() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let start = env.block.time.seconds();
+        let init_msg = |epoch_schedule| InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
+        };
+
+        let err = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg(Some(EpochScheduleInfo {
+                num_epochs: 0,
+                decay: Decimal::percent(50),
+                total_rewards: None,
+            })),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidEpochCount { max: 52 });
+        let err = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg(Some(EpochScheduleInfo {
+                num_epochs: 3,
+                decay: Decimal::percent(150),
+                total_rewards: None,
+            })),
+        )
+        .unwrap_err();
+        assert_eq!(err, ContractError::InvalidEpochDecay {});
+
+        // Three epochs halving each time; the last one absorbs the leftover second and rounding dust
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            init_msg(Some(EpochScheduleInfo {
+                num_epochs: 3,
+                decay: Decimal::percent(50),
+                total_rewards: Some(Uint128::new(7_000)),
+            })),
+        )
+        .unwrap();
+
+        let schedule: EpochScheduleResponse =
+            from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetEpochSchedule {}).unwrap()).unwrap();
+        assert_eq!(schedule.num_epochs, 3);
+        assert_eq!(schedule.epoch_duration, 333);
+        assert_eq!(schedule.current_epoch, 1);
+        let timeline: Vec<_> = schedule
+            .epochs
+            .iter()
+            .map(|epoch| (epoch.epoch_number, epoch.start_time - start, epoch.end_time - start, epoch.total_distribution.u128()))
+            .collect();
+        assert_eq!(
+            timeline,
+            vec![(1, 0, 333, 3_999), (2, 333, 666, 1_999), (3, 666, 1000, 1_002)]
+        );
+
+        // A stake late in the poll lands in the last epoch
+        env.block.time = env.block.time.plus_seconds(900);
+        let info = mock_info("user1", &coins(100, XION_DENOM));
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::Stake {
+                amount: Uint128::new(100),
+                position: YES,
+            },
+        )
+        .unwrap();
+        assert!(res.attributes.iter().any(|attr| attr.key == "epoch" && attr.value == "3"));
+    }
+
+    #[test]
+    fn test_default_epoch_schedule() {
+        let mut deps = mock_dependencies();
+        let init_msg = InstantiateMsg {
+            capy_core: "capy_core".to_string(),
+            poll_creator: "creator".to_string(),
+            outcomes: binary_outcomes("yes_token", "no_token"),
+            duration: 1000,
+            collateral: CollateralInfo::Native {
+                denom: XION_DENOM.to_string(),
+            },
+            oracle: None,
+            optimistic: None,
+            scalar: None,
+            epoch_schedule: None,
+            exit_penalty: None,
+            limits: None,
+            protocol_fee: None,
+            sale: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), init_msg).unwrap();
+
+        // Four epochs sharing 69% of the max supply on a 75% decay curve
+        let schedule: EpochScheduleResponse =
+            from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetEpochSchedule {}).unwrap()).unwrap();
+        let total: Uint128 = schedule.epochs.iter().map(|epoch| epoch.total_distribution).sum();
+        assert_eq!(total, schedule.total_rewards);
+        let basis_points: Vec<_> = schedule
+            .epochs
+            .iter()
+            .map(|epoch| epoch.total_distribution.multiply_ratio(10_000u128, schedule.total_rewards).u128())
+            .collect();
+        assert_eq!(basis_points, vec![3657, 2742, 2057, 1542]);
+    }
+
+    #[test]
+    fn unstake_burns_earned_tokens_multitest() {
+        let (mut app, poll, _) = setup_multitest(&["YES", "NAY"], None, None, None, None);
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        let yes_token = info.outcomes[YES as usize].token.clone();
+        let (staker1, staker2) = (Addr::unchecked("staker1"), Addr::unchecked("staker2"));
+        let token_balance = |app: &App, addr: &Addr| -> Uint128 {
+            let res: cw20::BalanceResponse = app
+                .wrap()
+                .query_wasm_smart(&yes_token, &cw20::Cw20QueryMsg::Balance { address: addr.to_string() })
+                .unwrap();
+            res.balance
+        };
+
+        let stake = ExecuteMsg::Stake {
+            amount: Uint128::new(100),
+            position: YES,
+        };
+        for staker in [&staker1, &staker2] {
+            app.execute_contract(staker.clone(), poll.clone(), &stake, &coins(100, XION_DENOM)).unwrap();
+        }
+
+        // Both earn half of the first epoch
+        app.update_block(|block| block.time = block.time.plus_seconds(251));
+        app.execute_contract(staker1.clone(), poll.clone(), &ExecuteMsg::DistributeEpochRewards { epoch_number: 1 }, &[])
+            .unwrap();
+        let earned = token_balance(&app, &staker1);
+        assert!(!earned.is_zero());
+
+        let err = app
+            .execute_contract(staker1.clone(), poll.clone(), &ExecuteMsg::Unstake { amount: Uint128::new(150), position: YES }, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::InsufficientStake {
+                available: Uint128::new(100)
+            }
+        );
+
+        // Tokens earned by the unstaked amount are pulled back, which needs an allowance
+        let unstake = ExecuteMsg::Unstake {
+            amount: Uint128::new(60),
+            position: YES,
+        };
+        app.execute_contract(staker1.clone(), poll.clone(), &unstake, &[]).unwrap_err();
+        let burned = earned.multiply_ratio(60u128, 100u128);
+        app.execute_contract(
+            staker1.clone(),
+            yes_token.clone(),
+            &cw20::Cw20ExecuteMsg::IncreaseAllowance {
+                spender: poll.to_string(),
+                amount: burned,
+                expires: None,
+            },
+            &[],
+        )
+        .unwrap();
+        app.execute_contract(staker1.clone(), poll.clone(), &unstake, &[]).unwrap();
+
+        assert_eq!(token_balance(&app, &staker1), earned - burned);
+        assert_eq!(token_balance(&app, &poll), Uint128::zero());
+        // 5% of the 60 stays behind in the pot
+        assert_eq!(balance(&app, "staker1"), Uint128::new(957));
+        let res: TotalStakedResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetTotalStaked {}).unwrap();
+        assert_eq!(res.totals, vec![Uint128::new(140), Uint128::zero()]);
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        assert_eq!(info.penalty_pot, Uint128::new(3));
+
+        // The pot is shared by the stakes left at resolution
+        app.update_block(|block| block.time = block.time.plus_seconds(750));
+        app.execute_contract(Addr::unchecked("capy_core"), poll.clone(), &ExecuteMsg::InvalidatePoll {}, &[])
+            .unwrap();
+        for staker in [&staker1, &staker2] {
+            app.execute_contract(staker.clone(), poll.clone(), &ExecuteMsg::WithdrawStake {}, &[]).unwrap();
+        }
+        assert_eq!(balance(&app, "staker1"), Uint128::new(997));
+        assert_eq!(balance(&app, "staker2"), Uint128::new(1002));
+        assert_eq!(balance(&app, poll.as_str()), Uint128::new(1));
+    }
+
+    #[test]
+    fn paid_list_sale_multitest() {
+        // The creator allocation is 10 lots, so the sale is sized in lots for it to weigh in
+        const LOT: u128 = 10u128.pow(24);
+        let sale = SaleInfo {
+            curve: PriceCurve::Linear {
+                start_price: Decimal::one(),
+                end_price: Decimal::percent(300),
+            },
+            max_per_side: Some(Uint128::new(100 * LOT)),
+        };
+        let (mut app, poll, _) = setup_multitest(&["YES", "NAY"], None, None, None, Some(sale));
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        let tokens = info.outcomes.iter().map(|outcome| outcome.token.clone()).collect::<Vec<_>>();
+        let (staker1, staker2, creator) = (Addr::unchecked("staker1"), Addr::unchecked("staker2"), Addr::unchecked("creator"));
+        for staker in [&staker1, &staker2] {
+            app.sudo(cw_multi_test::SudoMsg::Bank(cw_multi_test::BankSudo::Mint {
+                to_address: staker.to_string(),
+                amount: coins(1_000 * LOT, XION_DENOM),
+            }))
+            .unwrap();
+        }
+        let funds = balance(&app, "staker1");
+        let token_balance = |app: &App, position: u8, addr: &Addr| -> Uint128 {
+            let res: cw20::BalanceResponse = app
+                .wrap()
+                .query_wasm_smart(&tokens[position as usize], &cw20::Cw20QueryMsg::Balance { address: addr.to_string() })
+                .unwrap();
+            res.balance
+        };
+        let approve = |app: &mut App, owner: &Addr, position: u8, amount: u128| {
+            let msg = cw20::Cw20ExecuteMsg::IncreaseAllowance {
+                spender: poll.to_string(),
+                amount: Uint128::new(amount),
+                expires: None,
+            };
+            app.execute_contract(owner.clone(), tokens[position as usize].clone(), &msg, &[]).unwrap();
+        };
+        let quote = |app: &App, position: u8, amount: u128| -> SaleQuoteResponse {
+            let msg = QueryMsg::GetSaleQuote {
+                position,
+                amount: Uint128::new(amount),
+            };
+            app.wrap().query_wasm_smart(&poll, &msg).unwrap()
+        };
+        let buy = |position: u8, amount: u128| ExecuteMsg::BuyTokens {
+            position,
+            amount: Uint128::new(amount),
+        };
+        let sell = |position: u8, amount: u128| ExecuteMsg::SellTokens {
+            position,
+            amount: Uint128::new(amount),
+        };
+
+        // The price climbs from 1 to 3 over the 100 lots of a side, so the first 50 average 1.5
+        let res = quote(&app, YES, 50 * LOT);
+        assert_eq!((res.sold, res.available), (Uint128::zero(), Uint128::new(100 * LOT)));
+        assert_eq!((res.cost, res.refund, res.prize_pool), (Some(Uint128::new(75 * LOT)), None, None));
+        let err = app
+            .execute_contract(staker1.clone(), poll.clone(), &buy(YES, 50 * LOT), &coins(75 * LOT - 1, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::InvalidSalePayment { cost: Uint128::new(75 * LOT) }
+        );
+        app.execute_contract(staker1.clone(), poll.clone(), &buy(YES, 50 * LOT), &coins(75 * LOT, XION_DENOM))
+            .unwrap();
+        app.execute_contract(staker2.clone(), poll.clone(), &buy(YES, 50 * LOT), &coins(125 * LOT, XION_DENOM))
+            .unwrap();
+        app.execute_contract(staker2.clone(), poll.clone(), &buy(NO, 40 * LOT), &coins(56 * LOT, XION_DENOM))
+            .unwrap();
+        assert_eq!(token_balance(&app, YES, &staker1), Uint128::new(50 * LOT));
+        let err = app
+            .execute_contract(staker1.clone(), poll.clone(), &buy(YES, 1), &coins(3, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::SaleCapExceeded { position: YES, available: Uint128::zero() }
+        );
+
+        // Selling back burns the tokens and refunds them at the top of the curve
+        app.execute_contract(staker2.clone(), poll.clone(), &sell(YES, 20 * LOT), &[]).unwrap_err();
+        approve(&mut app, &staker2, YES, 20 * LOT);
+        app.execute_contract(staker2.clone(), poll.clone(), &sell(YES, 20 * LOT), &[]).unwrap();
+        assert_eq!(token_balance(&app, YES, &staker2), Uint128::new(30 * LOT));
+        assert_eq!(balance(&app, "staker2"), funds - Uint128::new(125 * LOT));
+        let err = app
+            .execute_contract(staker2.clone(), poll.clone(), &sell(NO, 40 * LOT + 1), &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::SellBackExceeded { position: NO, purchased: Uint128::new(40 * LOT) }
+        );
+
+        // Halfway through, the creator's vested tokens can't be sold back against the buyers' collateral
+        app.update_block(|block| block.time = block.time.plus_seconds(500));
+        app.execute_contract(creator.clone(), poll.clone(), &ExecuteMsg::ClaimCreatorRewards {}, &[])
+            .unwrap();
+        assert_eq!(token_balance(&app, YES, &creator), Uint128::new(5 * LOT));
+        approve(&mut app, &creator, YES, 5 * LOT);
+        let err = app
+            .execute_contract(creator.clone(), poll.clone(), &sell(YES, 5 * LOT), &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::SellBackExceeded { position: YES, purchased: Uint128::zero() }
+        );
+
+        app.update_block(|block| block.time = block.time.plus_seconds(501));
+        let err = app
+            .execute_contract(staker1.clone(), poll.clone(), &buy(NO, 10), &coins(10, XION_DENOM))
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::PollEnded {});
+        let resolve = ExecuteMsg::ResolvePoll { winning_position: YES };
+        app.execute_contract(Addr::unchecked("capy_core"), poll.clone(), &resolve, &[]).unwrap();
+
+        // YES holders share all 200 lots of the proceeds over the 80 sold and the 10 of the creator,
+        // NAY holders get nothing
+        assert_eq!(quote(&app, YES, 0).prize_pool, Some(Uint128::new(200 * LOT)));
+        approve(&mut app, &staker2, NO, 40 * LOT);
+        let redeem = |position: u8, amount: u128| ExecuteMsg::RedeemPrize {
+            position,
+            amount: Uint128::new(amount),
+        };
+        let err = app
+            .execute_contract(staker2.clone(), poll.clone(), &redeem(NO, 40 * LOT), &[])
+            .unwrap_err();
+        assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::NoPrizeToRedeem {});
+        let prize = |amount: u128| Uint128::new(200 * LOT).multiply_ratio(amount, 90u128);
+        approve(&mut app, &staker1, YES, 50 * LOT);
+        app.execute_contract(staker1.clone(), poll.clone(), &redeem(YES, 50 * LOT), &[]).unwrap();
+        assert_eq!(balance(&app, "staker1"), funds - Uint128::new(75 * LOT) + prize(50));
+
+        // Tokens minted after resolution redeem at the same rate as the earlier ones
+        app.execute_contract(creator.clone(), poll.clone(), &ExecuteMsg::ClaimCreatorRewards {}, &[])
+            .unwrap();
+        approve(&mut app, &creator, YES, 5 * LOT);
+        app.execute_contract(creator.clone(), poll.clone(), &redeem(YES, 10 * LOT), &[]).unwrap();
+        assert_eq!(balance(&app, "creator"), prize(10));
+        approve(&mut app, &staker2, YES, 30 * LOT);
+        app.execute_contract(staker2.clone(), poll.clone(), &redeem(YES, 30 * LOT), &[]).unwrap();
+        assert_eq!(balance(&app, "staker2"), funds - Uint128::new(125 * LOT) + prize(30));
+        assert_eq!(
+            balance(&app, poll.as_str()),
+            Uint128::new(200 * LOT) - prize(50) - prize(10) - prize(30)
+        );
+        assert!(balance(&app, poll.as_str()) < Uint128::new(3));
+        assert_eq!(token_balance(&app, YES, &staker1), Uint128::zero());
+    }
+
+    #[test]
+    fn invalid_sale_refund_multitest() {
+        let sale = SaleInfo {
+            curve: PriceCurve::Flat {
+                price: Decimal::percent(200),
+            },
+            max_per_side: Some(Uint128::new(100)),
+        };
+        let (mut app, poll, _) = setup_multitest(&["YES", "NAY"], None, None, None, Some(sale));
+        let info: PollInfoResponse = app.wrap().query_wasm_smart(&poll, &QueryMsg::GetPollInfo {}).unwrap();
+        let tokens = info.outcomes.iter().map(|outcome| outcome.token.clone()).collect::<Vec<_>>();
+        let (staker1, staker2, creator) = (Addr::unchecked("staker1"), Addr::unchecked("staker2"), Addr::unchecked("creator"));
+        let approve = |app: &mut App, owner: &Addr, position: u8, amount: u128| {
+            let msg = cw20::Cw20ExecuteMsg::IncreaseAllowance {
+                spender: poll.to_string(),
+                amount: Uint128::new(amount),
+                expires: None,
+            };
+            app.execute_contract(owner.clone(), tokens[position as usize].clone(), &msg, &[]).unwrap();
+        };
+        let buy = |position: u8, amount: u128| ExecuteMsg::BuyTokens {
+            position,
+            amount: Uint128::new(amount),
+        };
+        let redeem = |position: u8, amount: u128| ExecuteMsg::RedeemPrize {
+            position,
+            amount: Uint128::new(amount),
+        };
+
+        app.execute_contract(staker1.clone(), poll.clone(), &buy(YES, 30), &coins(60, XION_DENOM)).unwrap();
+        app.execute_contract(staker2.clone(), poll.clone(), &buy(YES, 50), &coins(100, XION_DENOM)).unwrap();
+        app.execute_contract(staker2.clone(), poll.clone(), &buy(NO, 20), &coins(40, XION_DENOM)).unwrap();
+        approve(&mut app, &staker2, YES, 10);
+        let sell = ExecuteMsg::SellTokens {
+            position: YES,
+            amount: Uint128::new(10),
+        };
+        app.execute_contract(staker2.clone(), poll.clone(), &sell, &[]).unwrap();
+
+        app.update_block(|block| block.time = block.time.plus_seconds(1001));
+        app.execute_contract(Addr::unchecked("capy_core"), poll.clone(), &ExecuteMsg::InvalidatePoll {}, &[])
+            .unwrap();
+
+        // The creator's tokens never paid into the sale, so they get none of it back
+        app.execute_contract(creator.clone(), poll.clone(), &ExecuteMsg::ClaimCreatorRewards {}, &[])
+            .unwrap();
+        approve(&mut app, &creator, YES, 10);
+        let err = app.execute_contract(creator.clone(), poll.clone(), &redeem(YES, 10), &[]).unwrap_err();
+        assert_eq!(
+            err.downcast::<ContractError>().unwrap(),
+            ContractError::SellBackExceeded { position: YES, purchased: Uint128::zero() }
+        );
+
+        // Every buyer gets back what they paid, net of their sell-backs
+        approve(&mut app, &staker1, YES, 30);
+        app.execute_contract(staker1.clone(), poll.clone(), &redeem(YES, 30), &[]).unwrap();
+        approve(&mut app, &staker2, YES, 40);
+        app.execute_contract(staker2.clone(), poll.clone(), &redeem(YES, 40), &[]).unwrap();
+        approve(&mut app, &staker2, NO, 20);
+        app.execute_contract(staker2.clone(), poll.clone(), &redeem(NO, 20), &[]).unwrap();
+        assert_eq!(balance(&app, "staker1"), Uint128::new(1_000));
+        assert_eq!(balance(&app, "staker2"), Uint128::new(1_000));
+        assert_eq!(balance(&app, poll.as_str()), Uint128::zero());
     }
-} 
\ No newline at end of file
+}
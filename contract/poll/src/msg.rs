@@ -1,31 +1,176 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 
-use crate::state::PollActivity;
+use crate::state::{
+    Collateral, ExitPenalty, OptimisticConfig, OracleConfig, Outcome, PaidListSale, PollActivity, PriceCurve, Proposal,
+    ScalarConfig, StakeLimits,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub capy_core: String,
     pub poll_creator: String,
-    pub yes_token: String,
-    pub no_token: String,
+    /// Between 2 and 16 outcomes; binary polls pass YES then NO
+    pub outcomes: Vec<OutcomeInfo>,
     pub duration: u64,
-    pub denom: String,
+    pub collateral: CollateralInfo,
+    pub oracle: Option<OracleInfo>,
+    pub optimistic: Option<OptimisticInfo>,
+    /// Turns the poll into a scalar market; outcome 0 is LONG and outcome 1 is SHORT
+    pub scalar: Option<ScalarInfo>,
+    /// Defaults to four epochs decaying by 75%, sharing 69% of the max token supply
+    pub epoch_schedule: Option<EpochScheduleInfo>,
+    /// Defaults to a flat 5% penalty on unstaking
+    pub exit_penalty: Option<ExitPenaltyInfo>,
+    /// No limits apply when unset
+    pub limits: Option<StakeLimits>,
+    /// Share of every stake sent to `capy_core` as protocol fee, below 1. Nothing is charged
+    /// when unset.
+    pub protocol_fee: Option<Decimal>,
+    /// Paid-list sale of the outcome tokens, none when unset
+    pub sale: Option<SaleInfo>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
+pub struct SaleInfo {
+    pub curve: PriceCurve,
+    /// Defaults to the whole paid-list allocation, which is also the most it can be
+    pub max_per_side: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct ExitPenaltyInfo {
+    /// Penalty in the first epoch, at most 1
+    pub start: Decimal,
+    /// Penalty in the last epoch, at most 1
+    pub end: Decimal,
+}
+
+#[cw_serde]
+pub struct EpochScheduleInfo {
+    pub num_epochs: u64,
+    /// Each epoch pays `decay` times the rewards of the one before it, in (0, 1]
+    pub decay: Decimal,
+    pub total_rewards: Option<Uint128>,
+}
+
+/// Token the poll is staked in. `Cw20` is only reachable by instantiating the poll directly,
+/// core's `CreatePoll` always picks a native denom.
+#[cw_serde]
+pub enum CollateralInfo {
+    Native { denom: String },
+    Cw20 { address: String },
+}
+
+#[cw_serde]
+pub struct OutcomeInfo {
+    pub name: String,
+    pub token: String,
+}
+
+#[cw_serde]
+pub struct OracleInfo {
+    pub address: String,
+    pub question_id: String,
+}
+
+#[cw_serde]
+pub struct ScalarInfo {
+    pub lower_bound: Uint128,
+    pub upper_bound: Uint128,
+}
+
+#[cw_serde]
+pub struct OptimisticInfo {
+    pub bond: Uint128,
+    pub challenge_period: u64,
+    /// Defaults to `capy_core`, leaving disputes to the core owner
+    pub arbiter: Option<String>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     Stake {
         amount: Uint128,
-        position: bool,
+        position: u8,
     },
+    /// Claims the rewards accrued so far by a batch of an ended epoch's stakers.
+    /// Deprecated, stakers claim their own rewards with `ClaimRewards` or `ClaimEpochRewards`.
     DistributeEpochRewards {
         epoch_number: u64,
     },
+    /// Mints the caller's rewards accrued so far, streamed by how long each unit was staked
+    ClaimRewards {},
+    /// Mints the caller's rewards of the given ended epochs, each epoch can be claimed once
+    ClaimEpochRewards {
+        epochs: Vec<u64>,
+    },
     WithdrawStake {},
+    /// Leaves an active poll early. Outcome tokens already earned by the unstaked amount are
+    /// burned, so the poll needs an allowance on them first.
+    Unstake {
+        amount: Uint128,
+        position: u8,
+    },
+    /// Moves stake between outcomes, counting as a new stake in the current epoch. Tokens
+    /// already earned on `from` are burned, which needs the same allowance as `Unstake`.
+    SwitchPosition {
+        amount: Uint128,
+        from: u8,
+        to: u8,
+    },
     ResolvePoll {
-        winning_position: bool,
+        winning_position: u8,
+    },
+    ResolveScalar {
+        value: Uint128,
+    },
+    ResolveFromOracle {},
+    ProposeOutcome {
+        outcome: u8,
     },
+    DisputeOutcome {},
+    FinalizeOutcome {},
+    SettleDispute {
+        winning_position: u8,
+    },
+    InvalidatePoll {},
+    /// Mints the creator allocation vested so far, of every outcome token. Creator only.
+    ClaimCreatorRewards {},
+    /// Buys `amount` outcome tokens of `position` on the paid-list sale, paying exactly the
+    /// cost quoted by `GetSaleQuote`
+    BuyTokens {
+        position: u8,
+        amount: Uint128,
+    },
+    /// Sells outcome tokens back to the paid-list sale before the poll ends. The tokens are
+    /// burned, which needs the same allowance as `Unstake`.
+    SellTokens {
+        position: u8,
+        amount: Uint128,
+    },
+    /// Burns outcome tokens of `position` for their share of the side's prize pool, once the
+    /// poll is resolved. On Invalid polls only tokens bought on the sale are refunded. Needs
+    /// the same allowance as `Unstake`.
+    RedeemPrize {
+        position: u8,
+        amount: Uint128,
+    },
+    /// Entry point for cw20 collateral, wraps a `ReceiveMsg`
+    Receive(Cw20ReceiveMsg),
+}
+
+/// Actions that take collateral, for polls whose collateral is a cw20
+#[cw_serde]
+pub enum ReceiveMsg {
+    Stake { position: u8 },
+    ProposeOutcome { outcome: u8 },
+    DisputeOutcome {},
+    BuyTokens { position: u8, amount: Uint128 },
 }
 
 #[cw_serde]
@@ -35,10 +180,43 @@ pub enum QueryMsg {
     GetPollInfo {},
     #[returns(EpochInfoResponse)]
     GetEpochInfo { epoch_number: u64 },
+    #[returns(EpochScheduleResponse)]
+    GetEpochSchedule {},
     #[returns(UserStakesResponse)]
     GetUserStakesForEpoch { user: String, epoch_number: u64 },
+    /// Everything `user` has in the poll, summed over all epochs
+    #[returns(UserPositionResponse)]
+    GetUserPosition { user: String },
+    /// Users with stake on `position`, in address order
+    #[returns(StakersResponse)]
+    ListStakers {
+        position: u8,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(TotalStakedResponse)]
     GetTotalStaked {},
+    /// Stake limits, and how much `user` can still stake when given
+    #[returns(LimitsResponse)]
+    GetLimits { user: Option<String> },
+    /// Rewards of `user` on each outcome, accrued up to the query time
+    #[returns(PendingRewardsResponse)]
+    GetPendingRewards { user: String },
+    /// Unclaimed rewards of `user` per started epoch
+    #[returns(ClaimableResponse)]
+    GetClaimable { user: String },
+    #[returns(ProposalResponse)]
+    GetProposal {},
+    /// Payout per side for `value`, the resolved value, or the value implied by the stakes
+    #[returns(ScalarPayoutResponse)]
+    GetScalarPayout { value: Option<Uint128> },
+    /// How the supply of each outcome token is split
+    #[returns(TokenAllocationResponse)]
+    GetTokenAllocation {},
+    /// Price of buying or selling back `amount` outcome tokens of `position` on the paid-list
+    /// sale
+    #[returns(SaleQuoteResponse)]
+    GetSaleQuote { position: u8, amount: Uint128 },
     #[returns(ActivitiesResponse)]
     GetActivities {
         start_after: Option<u64>,  // block height
@@ -46,6 +224,36 @@ pub enum QueryMsg {
     },
 }
 
+/// Amounts per outcome token
+#[cw_serde]
+pub struct TokenAllocationResponse {
+    /// Cap of every outcome token
+    pub max_supply: Uint128,
+    /// Allocation of `poll_creator`, vesting until `creator_vesting_end`
+    pub creator: Uint128,
+    pub creator_vested: Uint128,
+    pub creator_claimed: Uint128,
+    pub creator_vesting_end: u64,
+    /// Rewards streamed to stakers through the epochs
+    pub distribution: Uint128,
+    /// Sold through the paid-list sale
+    pub paid_list: Uint128,
+}
+
+#[cw_serde]
+pub struct SaleQuoteResponse {
+    /// Tokens of the side sold so far, net of tokens sold back
+    pub sold: Uint128,
+    /// Left to sell under the side's cap
+    pub available: Uint128,
+    /// `None` when `amount` is above `available`
+    pub cost: Option<Uint128>,
+    /// `None` when `amount` is above `sold`
+    pub refund: Option<Uint128>,
+    /// Left in the side's prize pool, only known once the poll is resolved
+    pub prize_pool: Option<Uint128>,
+}
+
 #[cw_serde]
 pub struct EpochInfoResponse {
     pub start_time: u64,
@@ -55,30 +263,206 @@ pub struct EpochInfoResponse {
     pub num_stakers: u64,
 }
 
+#[cw_serde]
+pub struct EpochScheduleResponse {
+    pub num_epochs: u64,
+    pub epoch_duration: u64,
+    pub decay: Decimal,
+    pub total_rewards: Uint128,
+    /// Epoch running at query time
+    pub current_epoch: u64,
+    pub epochs: Vec<EpochScheduleEntry>,
+}
+
+#[cw_serde]
+pub struct EpochScheduleEntry {
+    pub epoch_number: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub total_distribution: Uint128,
+    pub is_distributed: bool,
+}
+
 #[cw_serde]
 pub struct UserStakesResponse {
     pub stakes: Vec<crate::state::Stake>,
 }
 
+#[cw_serde]
+pub struct UserPositionResponse {
+    /// Indexed by position
+    pub positions: Vec<UserPositionEntry>,
+    /// Stake taken out, by `Unstake` before the end or `WithdrawStake` after the resolution.
+    /// Counted before exit penalties.
+    pub withdrawn: Uint128,
+    pub epochs: Vec<UserEpochRewards>,
+}
+
+#[cw_serde]
+pub struct UserPositionEntry {
+    pub position: u8,
+    /// Stake not withdrawn yet
+    pub staked: Uint128,
+    /// Outcome tokens minted so far
+    pub claimed: Uint128,
+    pub pending: Uint128,
+    /// Claimed and pending tokens, counted at the share of their value that survives the blitz.
+    /// Until resolution this is the expectation under the odds implied by the stakes.
+    pub blitz_value: Uint128,
+}
+
+#[cw_serde]
+pub struct UserEpochRewards {
+    pub epoch_number: u64,
+    /// Accrued in the epoch, claimed or not, indexed by position
+    pub earned: Vec<Uint128>,
+    /// Not claimed yet, indexed by position
+    pub pending: Vec<Uint128>,
+}
+
+#[cw_serde]
+pub struct StakersResponse {
+    pub stakers: Vec<StakerEntry>,
+}
+
+#[cw_serde]
+pub struct StakerEntry {
+    pub user: String,
+    pub staked: Uint128,
+}
+
 #[cw_serde]
 pub struct PollInfoResponse {
     pub end_timestamp: u64,
-    pub yes_token: Addr,
-    pub no_token: Addr,
+    pub outcomes: Vec<Outcome>,
     pub total_staked: Uint128,
     pub is_resolved: bool,
-    pub winning_position: Option<bool>,
-    pub denom: String,
+    pub winning_position: Option<u8>,
+    pub is_invalid: bool,
+    pub collateral: Collateral,
+    pub oracle: Option<OracleConfig>,
+    pub optimistic: Option<OptimisticConfig>,
+    pub scalar: Option<ScalarConfig>,
+    pub exit_penalty: ExitPenalty,
+    pub penalty_pot: Uint128,
+    pub protocol_fee: Decimal,
+    pub sale: Option<PaidListSale>,
 }
 
 #[cw_serde]
 pub struct TotalStakedResponse {
-    pub total_yes: Uint128,
-    pub total_no: Uint128,
-    pub denom: String,
+    /// Indexed by position
+    pub totals: Vec<Uint128>,
+    pub collateral: Collateral,
+}
+
+#[cw_serde]
+pub struct LimitsResponse {
+    pub min_stake: Option<Uint128>,
+    pub max_stake: Option<Uint128>,
+    pub max_user_total: Option<Uint128>,
+    pub max_side_total: Option<Uint128>,
+    /// Room left under `max_side_total`, indexed by position
+    pub side_room: Vec<Option<Uint128>>,
+    /// Room left under `max_user_total` for the queried user
+    pub user_room: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct PendingRewardsResponse {
+    pub rewards: Vec<PositionRewards>,
+}
+
+#[cw_serde]
+pub struct PositionRewards {
+    pub position: u8,
+    pub token: String,
+    pub staked: Uint128,
+    /// Accrued and not minted yet, before any scalar payout
+    pub pending: Uint128,
+    pub claimed: Uint128,
+}
+
+#[cw_serde]
+pub struct ClaimableResponse {
+    pub epochs: Vec<ClaimableEpoch>,
+}
+
+#[cw_serde]
+pub struct ClaimableEpoch {
+    pub epoch_number: u64,
+    pub is_ended: bool,
+    /// Claimed with `ClaimEpochRewards`
+    pub is_claimed: bool,
+    /// Unclaimed rewards indexed by position, before any scalar payout
+    pub amounts: Vec<Uint128>,
 }
 
 #[cw_serde]
 pub struct ActivitiesResponse {
     pub activities: Vec<PollActivity>,
-} 
\ No newline at end of file
+}
+
+#[cw_serde]
+pub struct ScalarPayoutResponse {
+    pub lower_bound: Uint128,
+    pub upper_bound: Uint128,
+    pub value: Uint128,
+    pub is_resolved: bool,
+    pub long_payout: Decimal,
+    pub short_payout: Decimal,
+}
+
+#[cw_serde]
+pub struct ProposalResponse {
+    pub proposal: Option<Proposal>,
+    pub challenge_period_end: Option<u64>,
+}
+
+/// Hooks core exposes to the polls it created
+#[cw_serde]
+pub enum CoreExecuteMsg {
+    /// Sent on the first stake of `user` in the poll
+    RecordParticipation { user: String },
+    /// Sent whenever collateral enters or leaves the poll, and once when it settles
+    ReportPollEvent { event: PollEvent },
+}
+
+/// Queries the polls make to core
+#[cw_serde]
+pub enum CoreQueryMsg {
+    /// Answers a plain `bool`
+    IsPollFromFactory { poll_address: String },
+}
+
+/// Change in the poll's collateral or lifecycle, as reported to core
+#[cw_serde]
+pub enum PollEvent {
+    /// Collateral staked on a position, counted as volume
+    Staked { amount: Uint128 },
+    /// Collateral posted as a proposal or dispute bond
+    Bonded { amount: Uint128 },
+    /// Collateral paid for outcome tokens on the paid-list sale, counted as volume
+    Bought { amount: Uint128 },
+    /// Collateral paid out of the poll: unstakes, withdrawals, bond settlements, sale refunds
+    /// and prizes
+    Withdrawn { amount: Uint128 },
+    /// Protocol fee taken off a stake, attached to this report as native coins
+    FeeCollected { amount: Uint128 },
+    /// The poll was resolved, or settled as Invalid
+    Resolved { invalid: bool },
+}
+
+/// Query interface every outcome oracle has to implement
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum OracleQueryMsg {
+    #[returns(OutcomeResponse)]
+    Outcome { question_id: String },
+}
+
+#[cw_serde]
+pub struct OutcomeResponse {
+    /// Index of the winning outcome, `None` until the oracle has an answer
+    pub outcome: Option<u8>,
+}
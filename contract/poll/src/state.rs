@@ -1,19 +1,304 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Uint128};
-use cw_storage_plus::{Item, Map};
+use std::fmt;
+
+use cosmwasm_std::{
+    to_json_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Decimal256, StdError, StdResult, Uint128, Uint256, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+
+use crate::ContractError;
 
 #[cw_serde]
 
 pub struct PollConfig {
     pub capy_core: Addr,
     pub poll_creator: Addr,
-    pub yes_token: Addr,
-    pub no_token: Addr,
+    /// One cw20 per outcome; binary polls use position 0 for YES and 1 for NO
+    pub outcomes: Vec<Outcome>,
     pub end_timestamp: u64,
     pub total_staked: Uint128,
     pub is_resolved: bool,
-    pub winning_position: Option<bool>,
-    pub denom: String,
+    pub winning_position: Option<u8>,
+    /// Set when the poll settled as Invalid: both tokens are void and every stake is refunded
+    pub is_invalid: bool,
+    /// Asset staked and bonded on this poll
+    pub collateral: Collateral,
+    pub oracle: Option<OracleConfig>,
+    pub optimistic: Option<OptimisticConfig>,
+    /// Present on scalar polls, which reuse the two outcome tokens as LONG and SHORT
+    pub scalar: Option<ScalarConfig>,
+    pub exit_penalty: ExitPenalty,
+    pub limits: StakeLimits,
+    /// Share of every stake sent to `capy_core`
+    #[serde(default)]
+    pub protocol_fee: Decimal,
+    #[serde(default)]
+    pub sale: Option<PaidListSale>,
+}
+
+impl PollConfig {
+    pub fn outcome_token(&self, position: u8) -> Result<&Addr, ContractError> {
+        self.outcomes
+            .get(position as usize)
+            .map(|outcome| &outcome.token)
+            .ok_or(ContractError::InvalidPosition {
+                position,
+                num_outcomes: self.outcomes.len(),
+            })
+    }
+}
+
+#[cw_serde]
+pub enum Collateral {
+    Native { denom: String },
+    /// Paid in through `Cw20ExecuteMsg::Send` to the poll's `Receive` hook
+    Cw20 { address: Addr },
+}
+
+impl Collateral {
+    /// Native collateral attached to a message. Cw20 collateral can't come with funds,
+    /// so polls using it only accept payments through `Receive`.
+    pub fn native_payment(&self, funds: &[Coin]) -> Result<Uint128, ContractError> {
+        match self {
+            Collateral::Native { denom } => Ok(funds
+                .iter()
+                .find(|coin| &coin.denom == denom)
+                .map(|coin| coin.amount)
+                .unwrap_or_default()),
+            Collateral::Cw20 { .. } => Err(ContractError::Cw20CollateralRequired {}),
+        }
+    }
+
+    pub fn transfer_msg(&self, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        Ok(match self {
+            Collateral::Native { denom } => BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin {
+                    denom: denom.clone(),
+                    amount,
+                }],
+            }
+            .into(),
+            Collateral::Cw20 { address } => WasmMsg::Execute {
+                contract_addr: address.to_string(),
+                msg: to_json_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            }
+            .into(),
+        })
+    }
+}
+
+impl fmt::Display for Collateral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Collateral::Native { denom } => write!(f, "{denom}"),
+            Collateral::Cw20 { address } => write!(f, "{address}"),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct Outcome {
+    pub name: String,
+    pub token: Addr,
+}
+
+#[cw_serde]
+pub struct ScalarConfig {
+    pub lower_bound: Uint128,
+    pub upper_bound: Uint128,
+    pub resolved_value: Option<Uint128>,
+}
+
+impl ScalarConfig {
+    /// Share of the payout owed to LONG when the poll settles on `value`. Values outside
+    /// the range are clamped, so LONG takes everything at or above `upper_bound`.
+    pub fn long_payout(&self, value: Uint128) -> Decimal {
+        let value = value.clamp(self.lower_bound, self.upper_bound);
+        Decimal::from_ratio(value - self.lower_bound, self.upper_bound - self.lower_bound)
+    }
+
+    pub fn short_payout(&self, value: Uint128) -> Decimal {
+        Decimal::one() - self.long_payout(value)
+    }
+
+    pub fn payout(&self, position: u8, value: Uint128) -> Decimal {
+        if position == LONG {
+            self.long_payout(value)
+        } else {
+            self.short_payout(value)
+        }
+    }
+}
+
+/// Caps set by the poll creator, unset ones don't apply
+#[cw_serde]
+#[derive(Default)]
+pub struct StakeLimits {
+    pub min_stake: Option<Uint128>,
+    /// Per stake message
+    pub max_stake: Option<Uint128>,
+    /// Active stake of one wallet across every position
+    pub max_user_total: Option<Uint128>,
+    /// Total staked on any single position
+    pub max_side_total: Option<Uint128>,
+}
+
+/// Price of outcome tokens on the paid-list sale, in collateral per token unit
+#[cw_serde]
+pub enum PriceCurve {
+    Flat { price: Decimal },
+    /// Rises from `start_price` on the first token of a side to `end_price` once the side sold
+    /// its cap
+    Linear { start_price: Decimal, end_price: Decimal },
+}
+
+/// Sale of the `PAID_LIST_PERCENTAGE` allocation, each side selling its own outcome token
+#[cw_serde]
+pub struct PaidListSale {
+    pub curve: PriceCurve,
+    /// Most tokens a side can have sold at once
+    pub max_per_side: Uint128,
+}
+
+impl PaidListSale {
+    /// Collateral worth of the tokens between `from` and `to` sold on one side
+    fn value(&self, from: Uint128, to: Uint128) -> Decimal256 {
+        let price = match self.curve {
+            PriceCurve::Flat { price } => Decimal256::from(price),
+            // Average price over the range
+            PriceCurve::Linear { start_price, end_price } => {
+                let progress = Decimal256::from_ratio(
+                    Uint256::from(from) + Uint256::from(to),
+                    Uint256::from(self.max_per_side) * Uint256::from(2u8),
+                );
+                Decimal256::from(start_price) + Decimal256::from(end_price - start_price) * progress
+            }
+        };
+        price * Decimal256::from_ratio(to - from, 1u8)
+    }
+
+    /// Paid for `amount` more tokens on a side that has `sold`, rounded up
+    pub fn buy_cost(&self, sold: Uint128, amount: Uint128) -> StdResult<Uint128> {
+        Ok(Uint128::try_from(self.value(sold, sold + amount).to_uint_ceil())?)
+    }
+
+    /// Paid back for `amount` tokens sold back to a side that has `sold`, rounded down
+    pub fn sell_refund(&self, sold: Uint128, amount: Uint128) -> StdResult<Uint128> {
+        Ok(Uint128::try_from(self.value(sold - amount, sold).to_uint_floor())?)
+    }
+}
+
+/// Paid-list sale state of one side
+#[cw_serde]
+#[derive(Default)]
+pub struct SaleSide {
+    /// Tokens sold and not sold back
+    pub sold: Uint128,
+    /// Collateral paid in for them, net of refunds
+    pub proceeds: Uint128,
+    /// Prize pool paid out to the side's holders after resolution
+    pub redeemed: Uint128,
+    /// Supply the prize pool is split over, fixed at resolution: the tokens out before the
+    /// blitz plus the epoch rewards and creator allocation still to be minted. Unused on Invalid
+    /// polls, which refund buyers by their `SALE_PURCHASES`.
+    #[serde(default)]
+    pub redeemable: Uint128,
+}
+
+/// Share of an unstaked amount kept by the poll, moving linearly from `start` in the first
+/// epoch to `end` in the last one
+#[cw_serde]
+pub struct ExitPenalty {
+    pub start: Decimal,
+    pub end: Decimal,
+}
+
+impl ExitPenalty {
+    pub fn rate(&self, epoch: u64, num_epochs: u64) -> Decimal {
+        if num_epochs <= 1 {
+            return self.start;
+        }
+        let progress = Decimal::from_ratio(epoch.saturating_sub(1), num_epochs - 1);
+        if self.end >= self.start {
+            self.start + (self.end - self.start) * progress
+        } else {
+            self.start - (self.start - self.end) * progress
+        }
+    }
+}
+
+#[cw_serde]
+pub struct OracleConfig {
+    pub address: Addr,
+    pub question_id: String,
+}
+
+#[cw_serde]
+pub struct OptimisticConfig {
+    pub bond: Uint128,
+    pub challenge_period: u64,
+    /// Settles disputes; falls back to `capy_core` when unset, whose owner settles them
+    /// through core's `SettleDispute`
+    pub arbiter: Option<Addr>,
+}
+
+#[cw_serde]
+pub enum ProposalStatus {
+    Proposed,
+    Disputed,
+    Finalized,
+}
+
+#[cw_serde]
+pub struct Proposal {
+    pub proposer: Addr,
+    pub outcome: u8,
+    pub bond: Uint128,
+    pub proposed_at: u64,
+    pub disputer: Option<Addr>,
+    pub status: ProposalStatus,
+}
+
+#[cw_serde]
+pub struct EpochSchedule {
+    pub start_time: u64,
+    pub num_epochs: u64,
+    /// The last epoch also absorbs the remainder of `duration / num_epochs`
+    pub epoch_duration: u64,
+    /// Each epoch pays `decay` times the rewards of the one before it
+    pub decay: Decimal,
+    pub total_rewards: Uint128,
+}
+
+impl EpochSchedule {
+    /// Epoch running at `now`, clamped to the first and last epoch
+    pub fn epoch_at(&self, now: u64) -> u64 {
+        let elapsed = now.saturating_sub(self.start_time);
+        (elapsed / self.epoch_duration + 1).min(self.num_epochs)
+    }
+
+    /// Rewards of every epoch in order. Weights follow `decay^i` and the last epoch takes the
+    /// rounding dust, so the entries always add up to `total_rewards`.
+    pub fn distributions(&self) -> StdResult<Vec<Uint128>> {
+        let weights = (0..self.num_epochs as u32)
+            .map(|i| self.decay.checked_pow(i).map_err(|err| StdError::generic_err(err.to_string())))
+            .collect::<StdResult<Vec<_>>>()?;
+        let total_weight = weights.iter().fold(Decimal::zero(), |acc, weight| acc + *weight);
+
+        let mut distributions = weights[..weights.len() - 1]
+            .iter()
+            .map(|weight| self.total_rewards.mul_floor(*weight / total_weight))
+            .collect::<Vec<_>>();
+        let distributed = distributions.iter().fold(Uint128::zero(), |acc, amount| acc + *amount);
+        distributions.push(self.total_rewards - distributed);
+        Ok(distributions)
+    }
 }
 
 #[cw_serde]
@@ -23,60 +308,372 @@ pub struct EpochInfo {
     pub total_distribution: Uint128,
     pub is_distributed: bool,
     pub total_epoch_staked: Uint128,
+    /// Id of the last stake `DistributeEpochRewards` went through
     pub last_processed_index: u64,
+    /// Users still holding a stake placed in this epoch
+    #[serde(default)]
+    pub num_stakers: u64,
+}
+
+/// Rewards per staked unit, streamed by stake-seconds. Every epoch pays its distribution out
+/// evenly over its own duration, shared by whatever is staked at the time across all positions.
+#[cw_serde]
+pub struct RewardIndex {
+    pub index: Decimal256,
+    pub last_update: u64,
+    pub total_staked: Uint128,
+    /// `index` at the end of every epoch that ended so far
+    pub epoch_ends: Vec<Decimal256>,
+}
+
+impl RewardIndex {
+    /// Streams the rewards of `epochs` up to `now`. Nothing accrues while nobody is staked,
+    /// those rewards are never minted.
+    pub fn advance(&mut self, epochs: &[EpochInfo], now: u64) -> StdResult<()> {
+        if now <= self.last_update {
+            return Ok(());
+        }
+        for (i, epoch) in epochs.iter().enumerate() {
+            let from = self.last_update.max(epoch.start_time);
+            let to = now.min(epoch.end_time);
+            if to > from && !self.total_staked.is_zero() {
+                let emitted = Uint256::from(epoch.total_distribution) * Uint256::from(to - from);
+                let shares = Uint256::from(epoch.end_time - epoch.start_time) * Uint256::from(self.total_staked);
+                self.index += Decimal256::from_ratio(emitted, shares);
+            }
+            if now >= epoch.end_time && self.epoch_ends.len() == i {
+                self.epoch_ends.push(self.index);
+            }
+        }
+        self.last_update = now;
+        Ok(())
+    }
+
+    /// Index range covered by each epoch so far, the running one last
+    pub fn epoch_ranges(&self) -> impl Iterator<Item = (Decimal256, Decimal256)> + '_ {
+        let starts = std::iter::once(Decimal256::zero()).chain(self.epoch_ends.iter().copied());
+        let ends = self.epoch_ends.iter().copied().chain(std::iter::once(self.index));
+        starts.zip(ends)
+    }
+}
+
+/// Reward position of one user on one outcome
+#[cw_serde]
+#[derive(Default)]
+pub struct UserReward {
+    pub staked: Uint128,
+    /// `RewardIndex::index` at the last settlement
+    pub index: Decimal256,
+    /// Accrued and not minted yet, indexed by epoch number - 1
+    pub pending: Vec<Uint128>,
+    /// Outcome tokens minted so far
+    pub claimed: Uint128,
+    /// Accrued per epoch, claimed or not, net of what unstaking took back
+    #[serde(default)]
+    pub earned: Vec<Uint128>,
+}
+
+impl UserReward {
+    /// Moves everything accrued since the last settlement into the `pending` of its epoch
+    pub fn settle(&mut self, rewards: &RewardIndex) -> StdResult<()> {
+        let staked = Decimal256::from_atomics(self.staked, 0).map_err(|err| StdError::generic_err(err.to_string()))?;
+        for (i, (start, end)) in rewards.epoch_ranges().enumerate() {
+            let from = start.max(self.index);
+            let to = end.min(rewards.index);
+            if to <= from {
+                continue;
+            }
+            let accrued = Uint128::try_from((staked * (to - from)).to_uint_floor())?;
+            if self.pending.len() <= i {
+                self.pending.resize(i + 1, Uint128::zero());
+            }
+            if self.earned.len() <= i {
+                self.earned.resize(i + 1, Uint128::zero());
+            }
+            self.pending[i] += accrued;
+            self.earned[i] += accrued;
+        }
+        self.index = rewards.index;
+        Ok(())
+    }
+
+    pub fn total_pending(&self) -> Uint128 {
+        self.pending.iter().sum()
+    }
+
+    /// Takes `amount` off `staked` along with its share of the rewards, unclaimed ones first and
+    /// newest epochs first. Returns the claimed outcome tokens to burn.
+    pub fn unstake(&mut self, amount: Uint128) -> Uint128 {
+        let pending = self.total_pending();
+        let earned = (pending + self.claimed).multiply_ratio(amount, self.staked);
+        let mut forfeited = earned.min(pending);
+        let burned = earned - forfeited;
+
+        for (i, epoch_pending) in self.pending.iter_mut().enumerate().rev() {
+            let taken = forfeited.min(*epoch_pending);
+            *epoch_pending -= taken;
+            forfeited -= taken;
+            if let Some(epoch_earned) = self.earned.get_mut(i) {
+                *epoch_earned = epoch_earned.saturating_sub(taken);
+            }
+        }
+        let mut to_burn = burned;
+        for (i, epoch_earned) in self.earned.iter_mut().enumerate().rev() {
+            let claimed = epoch_earned.saturating_sub(self.pending.get(i).copied().unwrap_or_default());
+            let taken = to_burn.min(claimed);
+            *epoch_earned -= taken;
+            to_burn -= taken;
+        }
+
+        self.claimed -= burned;
+        self.staked -= amount;
+        burned
+    }
+}
+
+/// Epoch rewards of every staker of one outcome taken together, accrued and not minted yet.
+/// Follows the `user_rewards` of the outcome, give or take their rounding.
+#[cw_serde]
+#[derive(Default)]
+pub struct OutcomeRewards {
+    /// `RewardIndex::index` at the last settlement
+    pub index: Decimal256,
+    pub pending: Uint128,
+}
+
+impl OutcomeRewards {
+    /// Accrues what `staked`, the outcome's stake since the last settlement, earned since then
+    pub fn settle(&mut self, staked: Uint128, rewards: &RewardIndex) -> StdResult<()> {
+        let staked = Decimal256::from_atomics(staked, 0).map_err(|err| StdError::generic_err(err.to_string()))?;
+        self.pending += Uint128::try_from((staked * (rewards.index - self.index)).to_uint_floor())?;
+        self.index = rewards.index;
+        Ok(())
+    }
+}
+
+pub struct UserRewardIndexes<'a> {
+    pub position: MultiIndex<'a, u8, UserReward, (Addr, u8)>,
+}
+
+impl<'a> IndexList<UserReward> for UserRewardIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<UserReward>> + '_> {
+        let v: Vec<&dyn Index<UserReward>> = vec![&self.position];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Reward position per user and outcome
+pub fn user_rewards<'a>() -> IndexedMap<'a, (&'a Addr, u8), UserReward, UserRewardIndexes<'a>> {
+    let indexes = UserRewardIndexes {
+        // The position is the last byte of the `(user, position)` key
+        position: MultiIndex::new(|pk, _| pk[pk.len() - 1], "user_rewards", "user_rewards__position"),
+    };
+    IndexedMap::new("user_rewards", indexes)
 }
 
 #[cw_serde]
 pub struct Stake {
+    pub user: Addr,
+    pub epoch: u64,
     pub amount: Uint128,
-    pub position: bool,
+    pub position: u8,
     pub withdrawn: bool,
 }
 
+pub struct StakeIndexes<'a> {
+    /// By user, then by the epoch the stake was placed in
+    pub user: MultiIndex<'a, (Addr, u64), Stake, u64>,
+    pub position: MultiIndex<'a, u8, Stake, u64>,
+    pub epoch: MultiIndex<'a, u64, Stake, u64>,
+}
+
+impl<'a> IndexList<Stake> for StakeIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Stake>> + '_> {
+        let v: Vec<&dyn Index<Stake>> = vec![&self.user, &self.position, &self.epoch];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Every stake under its own id, ids increase with time
+pub fn stakes<'a>() -> IndexedMap<'a, u64, Stake, StakeIndexes<'a>> {
+    let indexes = StakeIndexes {
+        user: MultiIndex::new(|_, stake| (stake.user.clone(), stake.epoch), "stakes", "stakes__user"),
+        position: MultiIndex::new(|_, stake| stake.position, "stakes", "stakes__position"),
+        epoch: MultiIndex::new(|_, stake| stake.epoch, "stakes", "stakes__epoch"),
+    };
+    IndexedMap::new("stakes", indexes)
+}
+
 #[cw_serde]
 pub struct PollActivity {
     pub user: Addr,
     pub activity_type: ActivityType,
     pub amount: Option<Uint128>,
-    pub position: Option<bool>,
+    pub position: Option<u8>,
     pub timestamp: u64,
     pub block_height: u64,
 }
 
+pub struct ActivityIndexes<'a> {
+    pub user: MultiIndex<'a, Addr, PollActivity, u64>,
+    pub height: MultiIndex<'a, u64, PollActivity, u64>,
+}
+
+impl<'a> IndexList<PollActivity> for ActivityIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PollActivity>> + '_> {
+        let v: Vec<&dyn Index<PollActivity>> = vec![&self.user, &self.height];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Every activity under its own id, ids increase with time
+pub fn activities<'a>() -> IndexedMap<'a, u64, PollActivity, ActivityIndexes<'a>> {
+    let indexes = ActivityIndexes {
+        user: MultiIndex::new(|_, activity| activity.user.clone(), "poll_activities", "poll_activities__user"),
+        height: MultiIndex::new(|_, activity| activity.block_height, "poll_activities", "poll_activities__height"),
+    };
+    IndexedMap::new("poll_activities", indexes)
+}
+
 #[cw_serde]
 pub enum ActivityType {
     Stake,
+    Unstake,
+    /// Stake moved to the activity's position
+    Switch,
     Withdraw,
     Resolve,
+    /// Outcome tokens bought on the paid-list sale, the activity's amount is in tokens
+    Buy,
+    /// Outcome tokens sold back to the paid-list sale
+    Sell,
+    /// Winning outcome tokens burned for the prize pool
+    Redeem,
 }
 
 pub const POLL_CONFIG: Item<PollConfig> = Item::new("poll_config");
+pub const PROPOSAL: Item<Proposal> = Item::new("proposal");
 pub const EPOCHS: Map<u64, EpochInfo> = Map::new("epochs");
 pub const CURRENT_EPOCH: Item<u64> = Item::new("current_epoch");
-pub const TOTAL_YES_STAKED: Item<Uint128> = Item::new("total_yes_staked");
-pub const TOTAL_NO_STAKED: Item<Uint128> = Item::new("total_no_staked");
-pub const EPOCH_DURATION: Item<u64> = Item::new("epoch_duration");
-pub const NUM_EPOCHS: Item<u64> = Item::new("num_epochs");
-pub const USER_STAKES: Map<(&Addr, u64), Vec<Stake>> = Map::new("user_stakes");
-pub const EPOCH_STAKERS: Map<u64, Vec<Addr>> = Map::new("epoch_stakers");
-
-// Store activities in a vector, indexed by block height for pagination
-pub const ACTIVITIES: Map<u64, Vec<PollActivity>> = Map::new("activities");
+pub const TOTAL_STAKED: Map<u8, Uint128> = Map::new("total_staked");
+pub const SALE_SIDES: Map<u8, SaleSide> = Map::new("sale_sides");
+/// Paid-list tokens each buyer may still sell back, or have refunded if the poll is Invalid
+pub const SALE_PURCHASES: Map<(&Addr, u8), Uint128> = Map::new("sale_purchases");
+pub const OUTCOME_REWARDS: Map<u8, OutcomeRewards> = Map::new("outcome_rewards");
+pub const EPOCH_SCHEDULE: Item<EpochSchedule> = Item::new("epoch_schedule");
+pub const REWARD_INDEX: Item<RewardIndex> = Item::new("reward_index");
+/// Epochs a user already claimed with `ClaimEpochRewards`
+pub const CLAIMED_EPOCHS: Map<(&Addr, u64), bool> = Map::new("claimed_epochs");
+/// Exit penalties, shared by whoever is still staked once the poll resolves
+pub const PENALTY_POT: Item<Uint128> = Item::new("penalty_pot");
+/// Last id handed out by `stakes()`
+pub const STAKE_COUNT: Item<u64> = Item::new("stake_count");
+/// Last id handed out by `activities()`
+pub const ACTIVITY_COUNT: Item<u64> = Item::new("activity_count");
 
 // Constants
 pub const MAX_TOKEN_SUPPLY: u128 = 1_000_000_000 * 10u128.pow(18);
 pub const CREATOR_REWARD_PERCENTAGE: u64 = 100; // 1%
 pub const DISTRIBUTION_PERCENTAGE: u64 = 6900; // 69%
 pub const PAID_LIST_PERCENTAGE: u64 = 3000; // 30%
+
+/// Part of each outcome token's `MAX_TOKEN_SUPPLY` set aside by a share in basis points
+pub fn allocation(percentage: u64) -> Uint128 {
+    Uint128::new(MAX_TOKEN_SUPPLY).multiply_ratio(percentage, 10_000u64)
+}
+
+/// Creator allocation of every outcome token, vesting linearly over the poll's lifetime
+#[cw_serde]
+pub struct CreatorVesting {
+    pub start_time: u64,
+    pub end_time: u64,
+    /// Allocation per outcome token
+    pub total: Uint128,
+    /// Minted so far, of each outcome token
+    pub claimed: Uint128,
+}
+
+impl CreatorVesting {
+    pub fn vested(&self, now: u64) -> Uint128 {
+        if now >= self.end_time {
+            return self.total;
+        }
+        self.total
+            .multiply_ratio(now.saturating_sub(self.start_time), self.end_time - self.start_time)
+    }
+}
+
+pub const CREATOR_VESTING: Item<CreatorVesting> = Item::new("creator_vesting");
+/// Epoch rewards minted so far across every outcome token, net of the ones burned back
+pub const MINTED_REWARDS: Item<Uint128> = Item::new("minted_rewards");
 pub const BATCH_SIZE: u64 = 100;
+pub const REPLY_RECORD_PARTICIPATION: u64 = 1;
+pub const REPLY_REPORT_POLL_EVENT: u64 = 2;
+pub const YES: u8 = 0;
+pub const NO: u8 = 1;
+pub const LONG: u8 = 0;
+pub const SHORT: u8 = 1;
+pub const MIN_OUTCOMES: usize = 2;
+pub const MAX_OUTCOMES: usize = 16;
+pub const DEFAULT_NUM_EPOCHS: u64 = 4;
+pub const MAX_EPOCHS: u64 = 52;
+/// Default decay of 75% per epoch, the 36.57/27.43/20.57/15.43% split of four epochs
+pub const DEFAULT_EPOCH_DECAY_PERCENT: u64 = 75;
+pub const DEFAULT_EXIT_PENALTY_PERCENT: u64 = 5;
+
+/// Storage layout of the first release, binary polls keyed by `bool` positions, read by `migrate`
+pub mod legacy {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{Addr, Uint128};
+    use cw_storage_plus::{Item, Map};
+
+    use super::{ActivityType, NO, YES};
+
+    #[cw_serde]
+    pub struct PollConfig {
+        pub capy_core: Addr,
+        pub poll_creator: Addr,
+        pub yes_token: Addr,
+        pub no_token: Addr,
+        pub end_timestamp: u64,
+        pub total_staked: Uint128,
+        pub is_resolved: bool,
+        pub winning_position: Option<bool>,
+        pub denom: String,
+    }
 
-pub const EPOCH_1_DISTRIBUTION: u64 = 3657;
-pub const EPOCH_2_DISTRIBUTION: u64 = 2743;
-pub const EPOCH_3_DISTRIBUTION: u64 = 2058;
-pub const EPOCH_4_DISTRIBUTION: u64 = 1542;
+    #[cw_serde]
+    pub struct Stake {
+        pub amount: Uint128,
+        pub position: bool,
+        pub withdrawn: bool,
+    }
 
-pub fn calculate_epoch_distribution(epoch_number: u64) -> Uint128 {
-    // Simple distribution calculation - can be adjusted based on requirements
-    let base_distribution = Uint128::new(1000000);
-    base_distribution.multiply_ratio(epoch_number, 1u64)
-} 
\ No newline at end of file
+    #[cw_serde]
+    pub struct PollActivity {
+        pub user: Addr,
+        pub activity_type: ActivityType,
+        pub amount: Option<Uint128>,
+        pub position: Option<bool>,
+        pub timestamp: u64,
+        pub block_height: u64,
+    }
+
+    /// `true` stood for YES
+    pub fn position(yes: bool) -> u8 {
+        if yes {
+            YES
+        } else {
+            NO
+        }
+    }
+
+    pub const POLL_CONFIG: Item<PollConfig> = Item::new("poll_config");
+    pub const TOTAL_YES_STAKED: Item<Uint128> = Item::new("total_yes_staked");
+    pub const TOTAL_NO_STAKED: Item<Uint128> = Item::new("total_no_staked");
+    pub const EPOCH_DURATION: Item<u64> = Item::new("epoch_duration");
+    pub const NUM_EPOCHS: Item<u64> = Item::new("num_epochs");
+    pub const USER_STAKES: Map<(&Addr, u64), Vec<Stake>> = Map::new("user_stakes");
+    pub const EPOCH_STAKERS: Map<u64, Vec<Addr>> = Map::new("epoch_stakers");
+    pub const ACTIVITIES: Map<u64, Vec<PollActivity>> = Map::new("activities");
+}
@@ -1,5 +1,5 @@
 use cosmwasm_std::{
-    entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128, to_binary,
+    entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, Uint128,
 };
 use cw20_base::contract::{
     execute as cw20_execute, instantiate as cw20_instantiate, query as cw20_query,
@@ -22,7 +22,7 @@ pub fn instantiate(
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     
     // Initialize owner (poll contract)
-    initialize_owner(deps.storage, deps.api, Some(&info.sender.to_string()))
+    initialize_owner(deps.storage, deps.api, Some(info.sender.as_str()))
         .map_err(|_| ContractError::Unauthorized {})?;
     
     // Initialize CW20 token
@@ -39,18 +39,25 @@ pub fn execute(
     match msg {
         ExecuteMsg::Mint { recipient, amount } => {
             // Only owner (poll contract) can mint
-            if let Err(_) = assert_owner(deps.storage, &info.sender) {
+            if assert_owner(deps.storage, &info.sender).is_err() {
                 return Err(ContractError::Unauthorized {});
             }
             execute_mint(deps, env, info, recipient, amount)
         }
         ExecuteMsg::Burn { amount } => {
             // Only owner (poll contract) can burn
-            if let Err(_) = assert_owner(deps.storage, &info.sender) {
+            if assert_owner(deps.storage, &info.sender).is_err() {
                 return Err(ContractError::Unauthorized {});
             }
             execute_burn(deps, env, info.clone(), info.sender.to_string(), amount)
         }
+        ExecuteMsg::UpdateMinter { new_minter } => {
+            // Minter and owner move together, so the poll ends up holding both
+            if assert_owner(deps.storage, &info.sender).is_err() {
+                return Err(ContractError::Unauthorized {});
+            }
+            execute_update_minter(deps, env, info, new_minter)
+        }
         _ => cw20_execute(deps, env, info, msg),
     }
 }
@@ -71,14 +78,31 @@ pub fn execute_mint(
     )
 }
 
+pub fn execute_update_minter(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    new_minter: Option<String>,
+) -> Result<Response, ContractError> {
+    initialize_owner(deps.storage, deps.api, new_minter.as_deref())
+        .map_err(|_| ContractError::Unauthorized {})?;
+    cw20_execute(
+        deps,
+        env,
+        info,
+        ExecuteMsg::UpdateMinter { new_minter },
+    )
+}
+
 pub fn execute_burn(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    from: String,
+    _from: String,
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     if amount.is_zero() {
+        #[allow(deprecated)]
         return Err(ContractError::InvalidZeroAmount {});
     }
     cw20_execute(
@@ -101,7 +125,7 @@ mod tests {
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage};
     use cosmwasm_std::{coins, from_json, Addr, OwnedDeps, Uint128};
-    use cw20::{Cw20Coin, TokenInfoResponse, BalanceResponse};
+    use cw20::{TokenInfoResponse, BalanceResponse};
 
     fn setup_contract() -> (OwnedDeps<MockStorage, MockApi, MockQuerier>, Addr) {
         let mut deps = mock_dependencies();
@@ -130,7 +154,7 @@ mod tests {
 
     #[test]
     fn proper_initialization() {
-        let (deps, owner) = setup_contract();
+        let (deps, _) = setup_contract();
         
         // Test token info query
         let res = query(deps.as_ref(), mock_env(), QueryMsg::TokenInfo {}).unwrap();
@@ -291,4 +315,31 @@ mod tests {
         let balance: BalanceResponse = from_json(&res).unwrap();
         assert_eq!(balance.balance, Uint128::new(600));
     }
+
+    #[test]
+    fn test_update_minter_moves_ownership() {
+        let (mut deps, owner) = setup_contract();
+
+        let msg = ExecuteMsg::UpdateMinter {
+            new_minter: Some("poll".to_string()),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info(owner.as_str(), &[]), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::Minter {}).unwrap();
+        let minter: Option<cw20::MinterResponse> = from_json(&res).unwrap();
+        assert_eq!(minter.unwrap().minter, "poll");
+        let ownership = cw_ownable::get_ownership(deps.as_ref().storage).unwrap();
+        assert_eq!(ownership.owner, Some(Addr::unchecked("poll")));
+
+        // The previous owner lost mint authority, the new one has it
+        let mint_msg = ExecuteMsg::Mint {
+            recipient: "recipient".to_string(),
+            amount: Uint128::new(1000),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info(owner.as_str(), &[]), mint_msg.clone()).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized {});
+        execute(deps.as_mut(), mock_env(), mock_info("poll", &[]), mint_msg).unwrap();
+    }
 }